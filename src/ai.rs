@@ -1,17 +1,37 @@
 use std::{
     sync::{
-        Arc,
+        Arc, OnceLock,
         atomic::{AtomicBool, Ordering},
     },
     thread::JoinHandle,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use crate::game::*;
-use strum::EnumIter;
+use difficulty::Difficulty;
+use evaluation::Evaluator;
+use strum::IntoEnumIterator;
 use tinyrand::{Rand, RandRange, Seeded, StdRand};
 use tinyrand_std::ClockSeed;
 
+pub mod analysis;
+pub mod difficulty;
+pub mod evaluation;
+pub mod external;
+pub mod move_ordering;
+pub mod network;
+pub mod opening_book;
+pub mod opening_suite;
+pub mod params;
+pub mod records;
+pub mod self_play;
+pub mod solver;
+pub mod statistics;
+pub mod tablebase;
+pub mod tournament;
+pub mod transposition;
+pub mod tuning;
+
 // All the bots
 mod min_max_v0;
 pub use min_max_v0::MinMaxV0;
@@ -21,8 +41,12 @@ mod min_max_v2;
 pub use min_max_v2::MinMaxV2;
 mod min_max_v3;
 pub use min_max_v3::MinMaxV3;
+mod min_max_v4;
+pub use min_max_v4::{DEFAULT_HASH_MIB, MinMaxV4, SearchOptions};
+mod mcts;
+pub use mcts::{Mcts, Rollout};
 
-#[derive(Clone, Copy, EnumIter, strum::Display, PartialEq)]
+#[derive(Clone, Copy, strum::Display, PartialEq)]
 pub enum AIVersion {
     Dummy,
     Random,
@@ -30,21 +54,186 @@ pub enum AIVersion {
     MinMaxV1,
     MinMaxV2,
     MinMaxV3,
+    MinMaxV4,
+    Mcts,
+    #[strum(to_string = "Level: {0}")]
+    Level(Difficulty),
 }
 impl AIVersion {
+    /// Every bot, with one entry per difficulty level
+    pub fn all() -> Vec<Self> {
+        let mut all = vec![
+            Self::Dummy,
+            Self::Random,
+            Self::MinMaxV0,
+            Self::MinMaxV1,
+            Self::MinMaxV2,
+            Self::MinMaxV3,
+            Self::MinMaxV4,
+            Self::Mcts,
+        ];
+        all.extend(Difficulty::iter().map(Self::Level));
+        all
+    }
+
+    /// Finds a bot by its displayed name, ignoring case, spaces and punctuation
+    /// Difficulty levels can also be given by the level alone, eg. "hard" for "Level: Hard"
+    pub fn from_name(name: &str) -> Option<Self> {
+        let normalize = |name: &str| -> String {
+            name.chars().filter(char::is_ascii_alphanumeric).collect::<String>().to_ascii_lowercase()
+        };
+        let name = normalize(name);
+        Self::all().into_iter().find(|version| {
+            let version_name = normalize(&version.to_string());
+            version_name == name || version_name == format!("level{name}")
+        })
+    }
+
     pub fn make_ai(&self) -> AsyncAI {
-        let ai_opponent: Arc<dyn AIOpponent> = match self {
+        AsyncAI::new(self.make_opponent(&BotSettings::default()))
+    }
+
+    /// Like `make_ai`, but bots built on `Evaluator` use the given parameters instead of their defaults
+    /// Bots without a configurable evaluation (`Dummy`, `Random`, `MinMaxV0` and `MinMaxV1`) ignore them
+    pub fn make_ai_with_params(&self, evaluator: &Evaluator) -> AsyncAI {
+        AsyncAI::new(self.make_opponent(&BotSettings::default().with_evaluator(evaluator.clone())))
+    }
+
+    /// The bot itself, for wrapping in other opponents before handing it to `AsyncAI`
+    /// Uses the evaluator parameters of the settings like `make_ai_with_params`, or the defaults if none are given
+    pub fn make_opponent(&self, settings: &BotSettings) -> Arc<dyn AIOpponent> {
+        match (self, settings.evaluator.clone()) {
+            (Self::Dummy, _) => Arc::new(Dummy),
+            (Self::Random, _) => Arc::new(RandomMover::default()),
+            (Self::MinMaxV0, _) => Arc::new(MinMaxV0::default()),
+            (Self::MinMaxV1, _) => Arc::new(MinMaxV1::default()),
+            (Self::MinMaxV2, None) => Arc::new(MinMaxV2::default()),
+            (Self::MinMaxV2, Some(evaluator)) => Arc::new(MinMaxV2::default().with_evaluator(evaluator)),
+            (Self::MinMaxV3, None) => Arc::new(MinMaxV3::default()),
+            (Self::MinMaxV3, Some(evaluator)) => Arc::new(MinMaxV3::default().with_evaluator(evaluator)),
+            (Self::MinMaxV4, _) => Arc::new(MinMaxV4::default().with_settings(settings)),
+            (Self::Mcts, None) => Arc::new(Mcts::default()),
+            (Self::Mcts, Some(evaluator)) => Arc::new(Mcts::default().with_rollout(Rollout::Evaluator(evaluator))),
+            (Self::Level(difficulty), _) => Arc::new(difficulty.make_opponent().with_settings(settings)),
+        }
+    }
+
+    /// Like `make_opponent`, but the bot is limited by depth or node count instead of time, and seeded
+    /// Searching the same position with the same settings always gives the same move and statistics
+    /// The bot finishes its search by itself, so use `AsyncAI::wait_for_move` rather than stopping it on time
+    /// Always searches on one thread, as helper threads would make the results vary
+    pub fn make_deterministic_opponent(
+        &self,
+        bot_settings: &BotSettings,
+        settings: &DeterministicSettings,
+    ) -> Arc<dyn AIOpponent> {
+        let DeterministicSettings { depth, node_limit, seed } = *settings;
+        let evaluator = bot_settings.evaluator.clone().unwrap_or_default();
+        let single_thread = bot_settings.clone().with_threads(1);
+        match self {
             Self::Dummy => Arc::new(Dummy),
-            Self::Random => Arc::new(RandomMover),
-            Self::MinMaxV0 => Arc::new(MinMaxV0::default()),
-            Self::MinMaxV1 => Arc::new(MinMaxV1::default()),
-            Self::MinMaxV2 => Arc::new(MinMaxV2::default()),
-            Self::MinMaxV3 => Arc::new(MinMaxV3::default()),
-        };
-        AsyncAI::new(ai_opponent)
+            Self::Random => Arc::new(RandomMover::default().with_seed(seed)),
+            Self::MinMaxV0 => Arc::new(MinMaxV0::new(depth)),
+            Self::MinMaxV1 => Arc::new(MinMaxV1::new(depth)),
+            Self::MinMaxV2 => Arc::new(MinMaxV2::new(depth).with_evaluator(evaluator)),
+            Self::MinMaxV3 => Arc::new(MinMaxV3::new(depth).with_evaluator(evaluator)),
+            Self::MinMaxV4 => {
+                Arc::new(MinMaxV4::new(depth).with_settings(&single_thread).with_node_limit(node_limit))
+            }
+            Self::Mcts => Arc::new(
+                Mcts::default()
+                    .with_rollout(Rollout::Evaluator(evaluator))
+                    .with_max_iterations(node_limit)
+                    .with_tree_reuse(false)
+                    .with_seed(seed),
+            ),
+            Self::Level(difficulty) => Arc::new(
+                difficulty
+                    .make_opponent()
+                    .with_settings(&single_thread)
+                    .with_fixed_limits(node_limit)
+                    .with_seed(seed),
+            ),
+        }
+    }
+}
+
+/// Options for the bots made by `AIVersion`, each bot ignores the ones that don't apply to it
+#[derive(Clone, Debug)]
+pub struct BotSettings {
+    /// Evaluator parameters of the bots built on `Evaluator`, the defaults if `None`
+    pub evaluator: Option<Evaluator>,
+    /// Threads searching in parallel in `MinMaxV4` and the difficulty levels, see `MinMaxV4::with_threads`
+    pub threads: usize,
+    /// Whether `MinMaxV4` resolves captures with a quiescence search, the difficulty levels keep their own setting
+    pub quiescence: bool,
+    /// Network evaluating positions in `MinMaxV4` and the difficulty levels instead of the evaluator
+    pub network: Option<Arc<network::Network>>,
+    /// Transposition table size of `MinMaxV4` and the difficulty levels in MiB, see `MinMaxV4::with_hash_size`
+    pub hash_mib: usize,
+    /// Endgame tablebase probed by `MinMaxV4` and the difficulty levels, see `MinMaxV4::with_tablebase`
+    pub tablebase: Option<Arc<tablebase::Tablebase>>,
+}
+impl Default for BotSettings {
+    fn default() -> Self {
+        Self {
+            evaluator: None,
+            threads: 1,
+            quiescence: true,
+            network: None,
+            hash_mib: DEFAULT_HASH_MIB,
+            tablebase: None,
+        }
+    }
+}
+impl BotSettings {
+    pub fn with_evaluator(mut self, evaluator: Evaluator) -> Self {
+        self.evaluator = Some(evaluator);
+        self
+    }
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+    pub fn with_quiescence(mut self, quiescence: bool) -> Self {
+        self.quiescence = quiescence;
+        self
+    }
+    pub fn with_network(mut self, network: Arc<network::Network>) -> Self {
+        self.network = Some(network);
+        self
+    }
+    pub fn with_hash_size(mut self, mib: usize) -> Self {
+        self.hash_mib = mib.max(1);
+        self
+    }
+    pub fn with_tablebase(mut self, tablebase: Arc<tablebase::Tablebase>) -> Self {
+        self.tablebase = Some(tablebase);
+        self
+    }
+}
+
+/// Limits and seed for bots made by `AIVersion::make_deterministic_opponent`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DeterministicSettings {
+    /// Search depth of the minmax bots
+    pub depth: u32,
+    /// Node limit of `MinMaxV4` and the difficulty levels, and iteration limit of `Mcts`
+    pub node_limit: u64,
+    pub seed: u64,
+}
+impl Default for DeterministicSettings {
+    fn default() -> Self {
+        Self {
+            depth: 6,
+            node_limit: 100_000,
+            seed: 0,
+        }
     }
 }
 
+// Depth of the search used to guess the opponent's reply when pondering
+const PONDER_GUESS_DEPTH: u32 = 4;
 
 /// Wrapper struct for the individual bots, provides synchronous interface for asynchronous searching
 /// Use `start_search` to begin looking for a good move, then `stop_search` to recover the found move
@@ -53,6 +242,9 @@ pub struct AsyncAI {
     cancel_signal: Arc<AtomicBool>,
     thread_handle: Option<JoinHandle<GameMove>>,
     ai_oppponent: Arc<dyn AIOpponent>,
+    /// Position currently being searched ahead of time, see `start_pondering`. Set by the search thread once it
+    /// has guessed the opponent's reply
+    pondered_board: Option<Arc<OnceLock<Board>>>,
 }
 impl AsyncAI {
     pub fn new(ai_oppponent: Arc<dyn AIOpponent>) -> Self {
@@ -60,13 +252,23 @@ impl AsyncAI {
             cancel_signal: Arc::new(AtomicBool::new(false)),
             ai_oppponent,
             thread_handle: None,
+            pondered_board: None,
         }
     }
 
     /// Called to start a search for a move. If a time limit is specified, the bot is free to search
     /// for an amount of time up to the time limit, otherwise the bot may search until `stop_search` is called
     /// The time limit must still be enforced on the caller end using `stop_search`, the parameter is just a hint for the bot (eg time left on a chess clock)
+    /// If the bot was pondering on this exact position, the ongoing search is kept instead (a ponder hit)
     pub fn start_search(&mut self, board: Board, remaining_time: Option<Duration>) {
+        if let Some(pondered_board) = self.pondered_board.take() {
+            if pondered_board.get().is_some_and(|pondered_board| pondered_board.is_same_board(&board)) {
+                log::info!("Ponder hit");
+                return;
+            }
+            log::info!("Ponder miss");
+            self.stop_search();
+        }
         let ai_oppponent = self.ai_oppponent.clone();
         self.cancel_signal.store(false, Ordering::Relaxed);
         let cancel_signal = self.cancel_signal.clone();
@@ -79,6 +281,7 @@ impl AsyncAI {
     /// Interrupt an ongoing search and immediately return a gamemove
     /// Panics if the search hasn't been started first with `start_search`
     pub fn stop_search(&mut self) -> GameMove {
+        self.pondered_board = None;
         self.cancel_signal.store(true, Ordering::Relaxed); // Signal detached thread to stop
         self.thread_handle
             .take()
@@ -87,9 +290,171 @@ impl AsyncAI {
             .expect("Search thread panicked")
     }
 
+    /// Like `stop_search`, but a panicking search is returned as a fault instead of panicking here, and if the
+    /// bot hasn't stopped `grace` after being told to, it is abandoned and `Fault::Timeout` is returned
+    /// An abandoned search keeps running in the background until it stops by itself
+    pub fn try_stop_search(&mut self, grace: Duration) -> Result<GameMove, Fault> {
+        self.cancel_signal.store(true, Ordering::Relaxed);
+        self.finish_search(Some(Instant::now() + grace))
+    }
+
+    /// Tells the search to stop without waiting for it, for callers that can't block
+    /// Once `search_finished`, the move is collected with `try_wait_for_move`
+    pub fn request_stop(&self) {
+        self.cancel_signal.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the search thread has returned, unlike `is_thinking` this stays false after `request_stop` until the
+    /// bot actually stops
+    pub fn search_finished(&self) -> bool {
+        self.thread_handle.as_ref().is_none_or(|handle| handle.is_finished())
+    }
+
+    /// Like `wait_for_move`, but a panicking search is returned as a fault instead of panicking here, and a
+    /// search still going after `limit` is abandoned with `Fault::Timeout`
+    pub fn try_wait_for_move(&mut self, limit: Option<Duration>) -> Result<GameMove, Fault> {
+        self.finish_search(limit.map(|limit| Instant::now() + limit))
+    }
+
+    fn finish_search(&mut self, deadline: Option<Instant>) -> Result<GameMove, Fault> {
+        self.pondered_board = None;
+        let thread_handle = self.thread_handle.take().expect("Search finished before it was started");
+        if let Some(deadline) = deadline {
+            while !thread_handle.is_finished() {
+                if Instant::now() >= deadline {
+                    self.cancel_signal.store(true, Ordering::Relaxed);
+                    return Err(Fault::Timeout);
+                }
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        }
+        thread_handle.join().map_err(Fault::from_panic)
+    }
+
+    /// Waits for the search to finish by itself and returns the move, without cancelling it
+    /// Only returns once the bot reaches its own limits, like the bots made by `AIVersion::make_deterministic_opponent`
+    /// Panics if the search hasn't been started first with `start_search`
+    pub fn wait_for_move(&mut self) -> GameMove {
+        self.pondered_board = None;
+        self.thread_handle
+            .take()
+            .expect("Search waited on before it was started")
+            .join()
+            .expect("Search thread panicked")
+    }
+
+    /// Starts searching while the opponent is thinking. The opponent's reply is guessed with a shallow search,
+    /// and the position after it is searched until `start_search` is called with the actual position
+    /// Both happen on the search thread, so this returns at once. Does nothing if the game is over
+    pub fn start_pondering(&mut self, board: Board) {
+        if board.finished() {
+            return;
+        }
+        let ai_oppponent = self.ai_oppponent.clone();
+        self.cancel_signal.store(false, Ordering::Relaxed);
+        let cancel_signal = self.cancel_signal.clone();
+        let pondered_board = Arc::new(OnceLock::new());
+        let guessed_board = pondered_board.clone();
+
+        self.thread_handle = Some(std::thread::spawn(move || {
+            // Bots set the cancel signal when they finish, so the guess gets its own
+            let guess = MinMaxV3::new(PONDER_GUESS_DEPTH).search(Arc::new(AtomicBool::new(false)), board.clone(), None);
+            let mut board = board;
+            board.make_move_unchecked(guess.clone());
+            guessed_board.get_or_init(|| board.clone());
+            // Nothing to search if the guessed reply ends the game, the move returned is never used
+            if board.finished() || cancel_signal.load(Ordering::Relaxed) {
+                return guess;
+            }
+            ai_oppponent.search(cancel_signal, board, None)
+        }));
+        self.pondered_board = Some(pondered_board);
+    }
+
+    /// Whether a search was started with `start_pondering` and hasn't been claimed by `start_search` yet
+    pub fn is_pondering(&self) -> bool {
+        self.pondered_board.is_some()
+    }
+
+    /// Position after the guessed reply while pondering, `None` until the search thread has guessed it
+    pub fn pondered_board(&self) -> Option<&Board> {
+        self.pondered_board.as_ref()?.get()
+    }
+
     /// Should return true while searching, false if the search has concluded (eg. if the bot chooses to search for less time than permitted)
+    /// A search that panicked has concluded too
     pub fn is_thinking(&self) -> bool {
         !self.cancel_signal.load(Ordering::Relaxed)
+            && self.thread_handle.as_ref().is_some_and(|handle| !handle.is_finished())
+    }
+}
+
+/// Ways a bot can fail to give a move, losing the game by forfeit
+/// Bots can report faults of their own, such as external engines playing illegal moves, by panicking with a
+/// `Fault` as the payload through `std::panic::panic_any`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Fault {
+    /// The move given isn't legal in the position, in .oni move notation
+    IllegalMove(String),
+    /// The search panicked or the engine stopped working, with the reason
+    Crash(String),
+    /// The bot didn't give a move within the hard time limit
+    Timeout,
+}
+impl Fault {
+    /// The fault behind a panic caught by joining the search thread
+    pub fn from_panic(payload: Box<dyn std::any::Any + Send>) -> Self {
+        let payload = match payload.downcast::<Self>() {
+            Ok(fault) => return *fault,
+            Err(payload) => payload,
+        };
+        if let Some(message) = payload.downcast_ref::<&str>() {
+            Self::Crash(message.to_string())
+        } else if let Some(message) = payload.downcast_ref::<String>() {
+            Self::Crash(message.clone())
+        } else {
+            Self::Crash("search panicked".to_string())
+        }
+    }
+
+    /// Checks that a move given by a bot is legal on the board
+    pub fn check_move(board: &Board, game_move: GameMove) -> Result<GameMove, Self> {
+        if board.legal_moves().contains(&game_move) {
+            Ok(game_move)
+        } else {
+            Err(Self::IllegalMove(String::from_utf8_lossy(&game_move.as_encoded_bytes()).into_owned()))
+        }
+    }
+}
+impl std::fmt::Display for Fault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::IllegalMove(notation) => write!(f, "illegal move '{notation}'"),
+            Self::Crash(reason) => write!(f, "crashed: {reason}"),
+            Self::Timeout => write!(f, "exceeded the time limit"),
+        }
+    }
+}
+
+/// Outcome of a search, along with whatever details the bot is able to report
+#[derive(Clone, Debug)]
+pub struct SearchResult {
+    pub best_move: GameMove,
+    /// Evaluation of the position from the perspective of the player to move, if the bot evaluates positions
+    pub score: Option<i32>,
+    /// Deepest completed search iteration, zero if unknown
+    pub depth: u32,
+    /// Number of positions visited, zero if unknown
+    pub nodes: u64,
+}
+impl SearchResult {
+    pub fn from_move(best_move: GameMove) -> Self {
+        Self {
+            best_move,
+            score: None,
+            depth: 0,
+            nodes: 0,
+        }
     }
 }
 
@@ -102,10 +467,34 @@ pub trait AIOpponent: Send + Sync {
         board: Board,
         remaining_time: Option<Duration>,
     ) -> GameMove;
+
+    /// Same as `search`, but also reports details about the search if the bot keeps track of them
+    fn search_with_info(
+        &self,
+        cancel_signal: Arc<AtomicBool>,
+        board: Board,
+        remaining_time: Option<Duration>,
+    ) -> SearchResult {
+        SearchResult::from_move(self.search(cancel_signal, board, remaining_time))
+    }
+
+    /// Score the bot gives a won position in `SearchResult`, so finished positions can be put on the same scale
+    fn win_score(&self) -> i32 {
+        evaluation::WIN_SCORE
+    }
 }
 
 #[derive(Default)]
-pub struct RandomMover;
+pub struct RandomMover {
+    seed: Option<u64>,
+}
+impl RandomMover {
+    /// Seed for move selection, otherwise seeded from the clock
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+}
 impl AIOpponent for RandomMover {
     fn search(
         &self,
@@ -115,7 +504,7 @@ impl AIOpponent for RandomMover {
     ) -> GameMove {
         cancel_signal.store(true, Ordering::Relaxed);
         let legal_moves = board.legal_moves();
-        let mut rng = StdRand::seed(ClockSeed.next_u64());
+        let mut rng = StdRand::seed(self.seed.unwrap_or_else(|| ClockSeed.next_u64()));
         let i = rng.next_range(0..legal_moves.len());
         legal_moves.into_iter().nth(i).unwrap()
     }
@@ -0,0 +1,105 @@
+//! Reviewing a finished game with a bot: the evaluation of every position, the move the bot prefers and the
+//! moves that threw the advantage away
+//!
+//! Scores are given from red's perspective, unlike `SearchResult`, so a whole game can be plotted on one axis.
+//! Finished positions aren't searched, they score the bot's `AIOpponent::win_score` for the winner and zero for a
+//! stalemate.
+use super::{tournament::MoveLimit, *};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(2);
+
+/// Analysis of the position before a ply, or the final position of the game
+#[derive(Clone, Debug)]
+pub struct PositionAnalysis {
+    pub red_to_move: bool,
+    /// Evaluation from red's perspective, if the bot evaluates positions
+    pub score: Option<i32>,
+    /// The move the bot would play, `None` in finished positions
+    pub best_move: Option<GameMove>,
+}
+
+/// Analyses the start position and the position after every move, calling `on_position` with the ply and its
+/// analysis as each one finishes. Setting `cancel` stops the analysis, including the search in progress
+pub fn analyse_game(
+    opponent: &dyn AIOpponent,
+    start: &Board,
+    moves: &[GameMove],
+    limit: MoveLimit,
+    cancel: &AtomicBool,
+    mut on_position: impl FnMut(usize, PositionAnalysis),
+) {
+    let win_score = opponent.win_score();
+    let mut board = start.clone();
+    for ply in 0..=moves.len() {
+        if cancel.load(Ordering::Relaxed) {
+            return;
+        }
+        let red_to_move = board.red_to_move();
+        let analysis = match board.status() {
+            GameStatus::RedWon => PositionAnalysis { red_to_move, score: Some(win_score), best_move: None },
+            GameStatus::BlueWon => PositionAnalysis { red_to_move, score: Some(-win_score), best_move: None },
+            GameStatus::Stalemate => PositionAnalysis { red_to_move, score: Some(0), best_move: None },
+            GameStatus::Playing if board.legal_moves().is_empty() => {
+                PositionAnalysis { red_to_move, score: None, best_move: None }
+            }
+            GameStatus::Playing => {
+                let result = search(opponent, &board, limit, cancel);
+                if cancel.load(Ordering::Relaxed) {
+                    return;
+                }
+                PositionAnalysis {
+                    red_to_move,
+                    score: result.score.map(|score| if red_to_move { score } else { -score }),
+                    best_move: Some(result.best_move),
+                }
+            }
+        };
+        on_position(ply, analysis);
+        if let Some(game_move) = moves.get(ply) {
+            board.make_move_unchecked(game_move.clone());
+        }
+    }
+}
+
+/// Searches one position, stopping at the time limit, or the hard limit of `MoveLimit::OwnLimits`, or as soon as
+/// `cancel` is set
+fn search(opponent: &dyn AIOpponent, board: &Board, limit: MoveLimit, cancel: &AtomicBool) -> SearchResult {
+    let signal = Arc::new(AtomicBool::new(false));
+    // Bots searching to their own limits aren't told about the hard limit
+    let (deadline, remaining_time) = match limit {
+        MoveLimit::Time(time) => (Instant::now() + time, Some(time)),
+        MoveLimit::OwnLimits(hard_limit) => (Instant::now() + hard_limit, None),
+    };
+    std::thread::scope(|scope| {
+        let timer_signal = signal.clone();
+        scope.spawn(move || {
+            while !timer_signal.load(Ordering::Relaxed) {
+                if cancel.load(Ordering::Relaxed) || Instant::now() >= deadline {
+                    timer_signal.store(true, Ordering::Relaxed);
+                }
+                std::thread::sleep(POLL_INTERVAL);
+            }
+        });
+        let result = opponent.search_with_info(signal.clone(), board.clone(), remaining_time);
+        // Also ends the timer if the bot finished by itself without saying so
+        signal.store(true, Ordering::Relaxed);
+        result
+    })
+}
+
+/// Plies whose move lost more than `threshold` for the player making it, by comparing the scores before and
+/// after. `positions` holds the analysis of every position, indexed by ply, and plies missing a score are skipped
+pub fn blunders(positions: &[Option<PositionAnalysis>], threshold: i32) -> Vec<usize> {
+    positions
+        .windows(2)
+        .enumerate()
+        .filter_map(|(ply, pair)| {
+            let (Some(before), Some(after)) = (&pair[0], &pair[1]) else {
+                return None;
+            };
+            let loss = before.score? - after.score?;
+            let loss = if before.red_to_move { loss } else { -loss };
+            (loss > threshold).then_some(ply)
+        })
+        .collect()
+}
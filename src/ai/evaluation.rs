@@ -0,0 +1,293 @@
+use crate::game::{Board, GameStatus, Piece, Pos};
+use strum::{EnumCount, EnumIter, IntoEnumIterator};
+
+pub const WIN_SCORE: i32 = 9999;
+
+/// The individual terms the evaluator is built from. Each term is computed from red's perspective
+/// and then scaled by its weight, a weight of zero disables the term entirely
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, EnumCount, strum::Display)]
+pub enum EvalTerm {
+    /// Difference in number of pieces
+    Material,
+    /// Sum of `piece_square_table` values for each piece, mirrored for blue
+    PieceSquare,
+    /// How much closer the sensei is to the enemy temple arch than the enemy sensei is to ours
+    TempleDistance,
+    /// Number of squares attacked by the cards in hand
+    CardAttacks,
+    /// Number of squares the transfer card would attack for the player about to receive it
+    TransferCard,
+    /// Number of ways the enemy sensei can be captured, minus the ways our sensei can be captured
+    SenseiSafety,
+}
+
+// Guessed good values
+#[rustfmt::skip]
+pub const DEFAULT_PIECE_SQUARE_TABLE: [i32; 25] = [
+    0, 0, 0, 0, 0,
+    1, 5, 5, 5, 1,
+    2, 5, 10, 5, 2,
+    1, 5, 5, 5, 1,
+    0, 0, 0, 0, 0,
+];
+
+/// Raw (unweighted) value of each term, from red's perspective
+pub type Features = [i32; EvalTerm::COUNT];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Evaluator {
+    weights: [i32; EvalTerm::COUNT],
+    piece_square_table: [i32; 25],
+    win_score: i32,
+}
+impl Default for Evaluator {
+    /// All terms enabled, with guessed weights
+    fn default() -> Self {
+        Self::classic()
+            .with_weight(EvalTerm::TempleDistance, 4)
+            .with_weight(EvalTerm::CardAttacks, 2)
+            .with_weight(EvalTerm::TransferCard, 1)
+            .with_weight(EvalTerm::SenseiSafety, 10)
+    }
+}
+impl Evaluator {
+    /// Evaluator with every term disabled
+    pub fn new() -> Self {
+        Self {
+            weights: [0; EvalTerm::COUNT],
+            piece_square_table: DEFAULT_PIECE_SQUARE_TABLE,
+            win_score: WIN_SCORE,
+        }
+    }
+    /// Material and piece placement only, identical to the evaluation used by `MinMaxV2` and `MinMaxV3`
+    pub fn classic() -> Self {
+        Self::new()
+            .with_weight(EvalTerm::Material, 100)
+            .with_weight(EvalTerm::PieceSquare, 1)
+    }
+    pub fn with_weight(mut self, term: EvalTerm, weight: i32) -> Self {
+        self.weights[term as usize] = weight;
+        self
+    }
+    pub fn with_piece_square_table(mut self, table: [i32; 25]) -> Self {
+        self.piece_square_table = table;
+        self
+    }
+    /// Score given to a won position, searches treat anything at or above it as a forced win
+    pub fn with_win_score(mut self, win_score: i32) -> Self {
+        self.win_score = win_score;
+        self
+    }
+    pub fn weight(&self, term: EvalTerm) -> i32 {
+        self.weights[term as usize]
+    }
+    pub fn piece_square_table(&self) -> &[i32; 25] {
+        &self.piece_square_table
+    }
+    pub fn win_score(&self) -> i32 {
+        self.win_score
+    }
+    pub fn is_enabled(&self, term: EvalTerm) -> bool {
+        self.weight(term) != 0
+    }
+
+    /// Bound on the absolute evaluation of an unfinished position with at most five pieces a side
+    pub fn max_evaluation(&self) -> i64 {
+        let mut table: Vec<i64> = self.piece_square_table.iter().map(|value| i64::from(value.unsigned_abs())).collect();
+        table.sort_unstable_by(|a, b| b.cmp(a));
+        let placement: i64 = 2 * table[..5].iter().sum::<i64>();
+        EvalTerm::iter()
+            .map(|term| {
+                let max_feature = match term {
+                    // Both senseis are still on the board
+                    EvalTerm::Material => 4,
+                    EvalTerm::PieceSquare => placement,
+                    EvalTerm::TempleDistance => 4,
+                    EvalTerm::CardAttacks | EvalTerm::TransferCard => 25,
+                    // Each piece can attack a square once per card
+                    EvalTerm::SenseiSafety => 20,
+                };
+                i64::from(self.weight(term).unsigned_abs()) * max_feature
+            })
+            .sum()
+    }
+
+    /// Evaluates a board from the perspective of the player given by `red_to_move`
+    pub fn evaluate(&self, board: &Board, red_to_move: bool) -> i32 {
+        match board.status() {
+            // Evaluation only occurs right *after* a winning move (red_to_move has been flipped),
+            // so no matter who won, we should return the negative of the win score
+            GameStatus::RedWon | GameStatus::BlueWon => {
+                return -self.win_score;
+            }
+            // Stalemates are even, regardless of material difference
+            GameStatus::Stalemate => {
+                return 0;
+            }
+            GameStatus::Playing => (),
+        }
+        let features = self.features(board);
+        let red_score: i32 = features
+            .iter()
+            .zip(self.weights)
+            .map(|(feature, weight)| feature * weight)
+            .sum();
+        if red_to_move { red_score } else { -red_score }
+    }
+
+    /// Computes the raw value of every enabled term from red's perspective, disabled terms are left at zero
+    pub fn features(&self, board: &Board) -> Features {
+        let mut features = [0; EvalTerm::COUNT];
+        let pieces = board.pieces();
+
+        if self.is_enabled(EvalTerm::Material) || self.is_enabled(EvalTerm::PieceSquare) {
+            for (piece, pos) in &pieces {
+                if piece.is_red() {
+                    features[EvalTerm::Material as usize] += 1;
+                    features[EvalTerm::PieceSquare as usize] +=
+                        self.piece_square_table[pos.to_index()];
+                } else {
+                    features[EvalTerm::Material as usize] -= 1;
+                    features[EvalTerm::PieceSquare as usize] -=
+                        self.piece_square_table[24 - pos.to_index()];
+                }
+            }
+        }
+
+        if self.is_enabled(EvalTerm::TempleDistance) {
+            let distance = |from: Pos, to: Pos| (from.0 - to.0).abs().max((from.1 - to.1).abs());
+            for (piece, pos) in &pieces {
+                match piece {
+                    Piece::RedSensei => {
+                        features[EvalTerm::TempleDistance as usize] -= distance(*pos, Pos(0, 2)) as i32
+                    }
+                    Piece::BlueSensei => {
+                        features[EvalTerm::TempleDistance as usize] += distance(*pos, Pos(4, 2)) as i32
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        if self.is_enabled(EvalTerm::CardAttacks)
+            || self.is_enabled(EvalTerm::TransferCard)
+            || self.is_enabled(EvalTerm::SenseiSafety)
+        {
+            let cards = board.cards();
+            let red_attacks = attack_counts(board, true, &cards[0..2]);
+            let blue_attacks = attack_counts(board, false, &cards[2..4]);
+            let attacked_squares = |counts: &[u8; 25]| counts.iter().filter(|&&n| n > 0).count() as i32;
+
+            features[EvalTerm::CardAttacks as usize] =
+                attacked_squares(&red_attacks) - attacked_squares(&blue_attacks);
+
+            let transfer_attacks = attacked_squares(&attack_counts(
+                board,
+                board.red_to_move(),
+                &cards[4..5],
+            ));
+            features[EvalTerm::TransferCard as usize] = if board.red_to_move() {
+                transfer_attacks
+            } else {
+                -transfer_attacks
+            };
+
+            for (piece, pos) in &pieces {
+                match piece {
+                    Piece::RedSensei => {
+                        features[EvalTerm::SenseiSafety as usize] -= blue_attacks[pos.to_index()] as i32
+                    }
+                    Piece::BlueSensei => {
+                        features[EvalTerm::SenseiSafety as usize] += red_attacks[pos.to_index()] as i32
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        for term in EvalTerm::iter() {
+            if !self.is_enabled(term) {
+                features[term as usize] = 0;
+            }
+        }
+        features
+    }
+
+    /// Per-term evaluation of a board from the perspective of the player given by `red_to_move`
+    pub fn breakdown(&self, board: &Board, red_to_move: bool) -> EvalBreakdown {
+        let features = self.features(board);
+        let sign = if red_to_move { 1 } else { -1 };
+        EvalBreakdown {
+            terms: EvalTerm::iter()
+                .filter(|term| self.is_enabled(*term))
+                .map(|term| (term, sign * features[term as usize], self.weight(term)))
+                .collect(),
+            total: self.evaluate(board, red_to_move),
+        }
+    }
+}
+
+/// Whether the sensei of the player to move could be captured by the opponent on their next move
+pub fn in_check(board: &Board) -> bool {
+    let red_to_move = board.red_to_move();
+    let cards = board.cards();
+    let opponent_cards = if red_to_move { &cards[2..4] } else { &cards[0..2] };
+    let attacks = attack_counts(board, !red_to_move, opponent_cards);
+    let own_sensei = if red_to_move {
+        Piece::RedSensei
+    } else {
+        Piece::BlueSensei
+    };
+    board
+        .squares()
+        .iter()
+        .position(|piece| *piece == Some(own_sensei))
+        .is_some_and(|i| attacks[i] > 0)
+}
+
+/// Counts, for every square, the number of ways the given player can move a piece there using the given cards
+/// Squares occupied by the player's own pieces are never attacked
+fn attack_counts(board: &Board, red: bool, cards: &[crate::cards::Card]) -> [u8; 25] {
+    let squares = board.squares();
+    let mut counts = [0; 25];
+    for (i, piece) in squares.iter().enumerate() {
+        if piece.is_none_or(|piece| piece.is_red() != red) {
+            continue;
+        }
+        let start_pos = Pos::from_index(i);
+        for card in cards {
+            let offsets = if red { card.offsets() } else { card.rev_offsets() };
+            for offset in offsets {
+                let end_pos = start_pos.offset(offset);
+                if end_pos.in_bounds()
+                    && squares[end_pos.to_index()].is_none_or(|piece| piece.is_red() != red)
+                {
+                    counts[end_pos.to_index()] += 1;
+                }
+            }
+        }
+    }
+    counts
+}
+
+/// Evaluation of a single position split into its terms, as `(term, raw value, weight)`
+/// The total includes terminal game states, so it may differ from the sum of the terms
+pub struct EvalBreakdown {
+    pub terms: Vec<(EvalTerm, i32, i32)>,
+    pub total: i32,
+}
+impl std::fmt::Display for EvalBreakdown {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (term, value, weight) in &self.terms {
+            writeln!(
+                f,
+                "{:<16}{:>5} x {:<4} = {:>6}",
+                term.to_string(),
+                value,
+                weight,
+                value * weight
+            )?;
+        }
+        write!(f, "{:<16}{:>22}", "Total", self.total)
+    }
+}
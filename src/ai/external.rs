@@ -0,0 +1,230 @@
+//! Engines running as separate programs, talking the text protocol described in engine_protocol.md
+use std::{
+    io::{BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::{
+        Mutex,
+        mpsc::{Receiver, RecvTimeoutError},
+    },
+    time::Instant,
+};
+
+use super::*;
+
+// How long to wait for the engine to start up and answer `oni`
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+// How often the cancel signal is checked while waiting for the engine
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+// How long the engine may take to answer `stop` before it is considered hung
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct EngineProcess {
+    child: Child,
+    stdin: ChildStdin,
+    /// Lines from the engine's standard output, read on a separate thread so waiting can time out
+    lines: Receiver<String>,
+}
+impl EngineProcess {
+    fn send(&mut self, command: &str) -> Result<(), EngineError> {
+        writeln!(self.stdin, "{command}").map_err(EngineError::Io)?;
+        self.stdin.flush().map_err(EngineError::Io)
+    }
+    fn receive(&self, timeout: Duration) -> Result<Option<String>, EngineError> {
+        match self.lines.recv_timeout(timeout) {
+            Ok(line) => Ok(Some(line)),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => Err(EngineError::Exited),
+        }
+    }
+}
+
+/// An `AIOpponent` running as a subprocess, such as the `engine` binary or engines from elsewhere
+/// Engine errors during a search are reported as a `Fault` panic, see `Fault` and `AsyncAI::try_stop_search`
+pub struct ExternalEngine {
+    name: String,
+    process: Mutex<EngineProcess>,
+}
+impl ExternalEngine {
+    /// Starts the engine and waits for it to complete the handshake
+    pub fn spawn(program: &str, args: &[String]) -> Result<Self, EngineError> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(EngineError::Io)?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let (sender, lines) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut process = EngineProcess { child, stdin, lines };
+        process.send("oni")?;
+        let mut name = program.to_string();
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let Some(line) = process.receive(remaining)? else {
+                let _ = process.child.kill();
+                return Err(EngineError::Timeout);
+            };
+            if let Some(engine_name) = line.trim().strip_prefix("id name ") {
+                name = engine_name.trim().to_string();
+            } else if line.trim() == "oniok" {
+                break;
+            }
+        }
+        Ok(Self {
+            name,
+            process: Mutex::new(process),
+        })
+    }
+
+    /// Name reported by the engine, or the program name if it didn't report one
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Sends the position and waits for the engine's move, sending `stop` once the cancel signal is set
+    fn search_engine(
+        &self,
+        cancel_signal: &AtomicBool,
+        board: &Board,
+        remaining_time: Option<Duration>,
+    ) -> Result<SearchResult, EngineError> {
+        let mut process = self.process.lock().unwrap();
+        process.send(&format!("position {}", board.save_game(false)))?;
+        match remaining_time {
+            Some(time) => process.send(&format!("go movetime {}", time.as_millis()))?,
+            None => process.send("go")?,
+        }
+        let mut stopped_at = None;
+        let mut info = SearchInfo::default();
+        loop {
+            if stopped_at.is_none() && cancel_signal.load(Ordering::Relaxed) {
+                process.send("stop")?;
+                stopped_at = Some(Instant::now());
+            }
+            let Some(line) = process.receive(POLL_INTERVAL)? else {
+                if stopped_at.is_some_and(|time| time.elapsed() > STOP_TIMEOUT) {
+                    return Err(EngineError::Timeout);
+                }
+                continue;
+            };
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("info") => info.update(words),
+                Some("bestmove") => {
+                    let notation = words.next().unwrap_or("");
+                    let best_move = board
+                        .legal_moves()
+                        .into_iter()
+                        .find(|game_move| game_move.as_encoded_bytes() == notation.as_bytes())
+                        .ok_or_else(|| EngineError::IllegalMove(notation.to_string()))?;
+                    return Ok(SearchResult {
+                        best_move,
+                        score: info.score,
+                        depth: info.depth,
+                        nodes: info.nodes,
+                    });
+                }
+                _ => (),
+            }
+        }
+    }
+}
+impl AIOpponent for ExternalEngine {
+    fn search(
+        &self,
+        cancel_signal: Arc<AtomicBool>,
+        board: Board,
+        remaining_time: Option<Duration>,
+    ) -> GameMove {
+        self.search_with_info(cancel_signal, board, remaining_time).best_move
+    }
+
+    fn search_with_info(
+        &self,
+        cancel_signal: Arc<AtomicBool>,
+        board: Board,
+        remaining_time: Option<Duration>,
+    ) -> SearchResult {
+        let result = self.search_engine(&cancel_signal, &board, remaining_time);
+        cancel_signal.store(true, Ordering::Relaxed);
+        // Reported as a fault, so the game is forfeited rather than continued with a made up move
+        result.unwrap_or_else(|err| {
+            log::error!("Engine {}: {err}", self.name);
+            match err {
+                EngineError::IllegalMove(notation) => std::panic::panic_any(Fault::IllegalMove(notation)),
+                err => std::panic::panic_any(Fault::Crash(err.to_string())),
+            }
+        })
+    }
+}
+impl Drop for ExternalEngine {
+    fn drop(&mut self) {
+        let Ok(process) = self.process.get_mut() else {
+            return;
+        };
+        let _ = process.send("quit");
+        // Give the engine a moment to exit by itself before killing it
+        let deadline = Instant::now() + Duration::from_millis(500);
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = process.child.try_wait() {
+                return;
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+        let _ = process.child.kill();
+        let _ = process.child.wait();
+    }
+}
+
+/// Latest values from `info` lines
+#[derive(Default)]
+struct SearchInfo {
+    score: Option<i32>,
+    depth: u32,
+    nodes: u64,
+}
+impl SearchInfo {
+    fn update<'a>(&mut self, mut words: impl Iterator<Item = &'a str>) {
+        while let Some(word) = words.next() {
+            match word {
+                "score" => self.score = words.next().and_then(|value| value.parse().ok()).or(self.score),
+                "depth" => self.depth = words.next().and_then(|value| value.parse().ok()).unwrap_or(self.depth),
+                "nodes" => self.nodes = words.next().and_then(|value| value.parse().ok()).unwrap_or(self.nodes),
+                _ => (),
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum EngineError {
+    Io(std::io::Error),
+    /// The engine didn't finish the handshake, or didn't answer `stop`, in time
+    Timeout,
+    /// The engine closed its output, most likely because it exited
+    Exited,
+    /// The engine answered with a move that isn't legal in the position
+    IllegalMove(String),
+}
+
+impl std::fmt::Display for EngineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed to communicate with engine: {err}"),
+            Self::Timeout => write!(f, "engine did not respond in time"),
+            Self::Exited => write!(f, "engine exited unexpectedly"),
+            Self::IllegalMove(notation) => write!(f, "engine played illegal move '{notation}'"),
+        }
+    }
+}
@@ -0,0 +1,337 @@
+//! Monte Carlo tree search using the UCT selection rule
+//!
+//! Each iteration walks down the tree picking the child with the best upper confidence bound, adds one new
+//! node, plays a rollout from it and propagates the result back up. The tree is kept between searches, so
+//! if the next position searched is a child or grandchild of the previous root, its subtree is reused.
+use std::sync::Mutex;
+
+use super::*;
+
+// Rollouts that go on for longer than this are scored as draws
+const MAX_ROLLOUT_PLIES: usize = 200;
+// Number of plies played by evaluator-guided rollouts before the evaluation is used to estimate the result
+const EVALUATOR_ROLLOUT_PLIES: usize = 4;
+// Scale of evaluations to win probabilities, an evaluation of this much is roughly a 90% chance of winning
+const EVALUATION_SCALE: f64 = 400.0;
+
+/// How the result of a newly expanded node is estimated
+#[derive(Clone, Debug)]
+pub enum Rollout {
+    /// Play uniformly random moves until the game ends
+    Random,
+    /// Play a few moves greedily by evaluation, then estimate the result from the evaluation of the final position
+    Evaluator(Evaluator),
+}
+
+struct Node {
+    /// Move leading to this node, `None` for the root
+    game_move: Option<GameMove>,
+    /// Whether red made the move leading to this node, rewards are counted from that player's perspective
+    red_moved: bool,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    untried_moves: Vec<GameMove>,
+    visits: u32,
+    reward: f64,
+}
+impl Node {
+    fn new(game_move: Option<GameMove>, parent: Option<usize>, board: &Board) -> Self {
+        Self {
+            game_move,
+            red_moved: !board.red_to_move(),
+            parent,
+            children: vec![],
+            untried_moves: if board.finished() { vec![] } else { board.legal_moves() },
+            visits: 0,
+            reward: 0.0,
+        }
+    }
+}
+
+/// Search tree stored as an arena, the root is always at index 0
+struct Tree {
+    root_board: Board,
+    nodes: Vec<Node>,
+}
+impl Tree {
+    fn new(board: Board) -> Self {
+        let root = Node::new(None, None, &board);
+        Self {
+            root_board: board,
+            nodes: vec![root],
+        }
+    }
+
+    /// Finds the node matching `board` among the root's children and grandchildren, and makes it the new root
+    /// Returns false if the board is not found, in which case the tree should be discarded
+    fn reroot(&mut self, board: &Board) -> bool {
+        if self.root_board.is_same_board(board) && self.root_board.game_length() == board.game_length() {
+            return true;
+        }
+        let mut scratch = self.root_board.clone();
+        let mut found = None;
+        'outer: for &child in &self.nodes[0].children {
+            scratch.make_move_unchecked(self.nodes[child].game_move.clone().unwrap());
+            if scratch.is_same_board(board) {
+                found = Some(child);
+                break;
+            }
+            for &grandchild in &self.nodes[child].children {
+                scratch.make_move_unchecked(self.nodes[grandchild].game_move.clone().unwrap());
+                let same = scratch.is_same_board(board);
+                scratch.undo_move();
+                if same {
+                    found = Some(grandchild);
+                    break 'outer;
+                }
+            }
+            scratch.undo_move();
+        }
+        let Some(new_root) = found else {
+            return false;
+        };
+
+        // Copy the subtree into a fresh arena, dropping everything else
+        let mut nodes = Vec::with_capacity(self.nodes.len());
+        let mut stack = vec![(new_root, None)];
+        while let Some((old_index, new_parent)) = stack.pop() {
+            let new_index = nodes.len();
+            let old_node = &mut self.nodes[old_index];
+            let children = std::mem::take(&mut old_node.children);
+            nodes.push(Node {
+                game_move: old_node.game_move.take(),
+                red_moved: old_node.red_moved,
+                parent: new_parent,
+                children: vec![],
+                untried_moves: std::mem::take(&mut old_node.untried_moves),
+                visits: old_node.visits,
+                reward: old_node.reward,
+            });
+            if let Some(parent) = new_parent {
+                let parent: &mut Node = &mut nodes[parent];
+                parent.children.push(new_index);
+            }
+            stack.extend(children.into_iter().map(|child| (child, Some(new_index))));
+        }
+        nodes[0].game_move = None;
+        self.nodes = nodes;
+        self.root_board = board.clone();
+        true
+    }
+
+    /// Child of `parent` with the highest upper confidence bound
+    fn select_child(&self, parent: usize, exploration: f64) -> usize {
+        let log_visits = (self.nodes[parent].visits as f64).ln();
+        let ucb = |child: usize| {
+            let node = &self.nodes[child];
+            let visits = node.visits as f64;
+            node.reward / visits + exploration * (log_visits / visits).sqrt()
+        };
+        self.nodes[parent]
+            .children
+            .iter()
+            .copied()
+            .max_by(|&a, &b| ucb(a).total_cmp(&ucb(b)))
+            .unwrap()
+    }
+
+    fn most_visited_child(&self) -> Option<usize> {
+        self.nodes[0]
+            .children
+            .iter()
+            .copied()
+            .max_by_key(|&child| self.nodes[child].visits)
+    }
+}
+
+pub struct Mcts {
+    exploration: f64,
+    rollout: Rollout,
+    max_iterations: Option<u64>,
+    seed: Option<u64>,
+    tree_reuse: bool,
+    tree: Mutex<Option<Tree>>,
+}
+impl Default for Mcts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Mcts {
+    pub fn new() -> Self {
+        Self {
+            exploration: std::f64::consts::SQRT_2,
+            rollout: Rollout::Evaluator(Evaluator::default()),
+            max_iterations: None,
+            seed: None,
+            tree_reuse: true,
+            tree: Mutex::new(None),
+        }
+    }
+    /// Constant weighing exploration of rarely visited moves against exploitation of good ones
+    pub fn with_exploration(mut self, exploration: f64) -> Self {
+        self.exploration = exploration;
+        self
+    }
+    pub fn with_rollout(mut self, rollout: Rollout) -> Self {
+        self.rollout = rollout;
+        self
+    }
+    /// Finish the search after this many iterations, instead of searching until cancelled
+    pub fn with_max_iterations(mut self, max_iterations: u64) -> Self {
+        self.max_iterations = Some(max_iterations);
+        self
+    }
+    /// Whether to keep the tree between searches, without it every search of a position gives the same result
+    pub fn with_tree_reuse(mut self, tree_reuse: bool) -> Self {
+        self.tree_reuse = tree_reuse;
+        self
+    }
+    /// Seed for rollouts, otherwise seeded from the clock
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Visits of the root of the tree kept from the last search, counting those reused from earlier searches
+    pub fn root_visits(&self) -> u32 {
+        self.tree.lock().unwrap().as_ref().map_or(0, |tree| tree.nodes[0].visits)
+    }
+
+    /// Plays out the game from `board` and returns the result from red's perspective, 1 for a red win
+    /// The board is restored before returning
+    fn rollout(&self, board: &mut Board, rng: &mut StdRand) -> f64 {
+        let mut plies = 0;
+        let result = loop {
+            match board.status() {
+                GameStatus::RedWon => break 1.0,
+                GameStatus::BlueWon => break 0.0,
+                GameStatus::Stalemate => break 0.5,
+                GameStatus::Playing => (),
+            }
+            if plies == MAX_ROLLOUT_PLIES {
+                break 0.5;
+            }
+            let mut legal_moves = board.legal_moves();
+            let game_move = match &self.rollout {
+                Rollout::Random => {
+                    let i = rng.next_range(0..legal_moves.len());
+                    legal_moves.swap_remove(i)
+                }
+                Rollout::Evaluator(evaluator) => {
+                    if plies == EVALUATOR_ROLLOUT_PLIES {
+                        let red_score = evaluator.evaluate(board, true) as f64;
+                        break 1.0 / (1.0 + 10f64.powf(-red_score / EVALUATION_SCALE));
+                    }
+                    // Shuffle first, so ties are broken randomly
+                    for i in (1..legal_moves.len()).rev() {
+                        let j = rng.next_range(0..i + 1);
+                        legal_moves.swap(i, j);
+                    }
+                    let red_to_move = board.red_to_move();
+                    legal_moves
+                        .into_iter()
+                        .max_by_key(|game_move| {
+                            board.make_move_unchecked(game_move.clone());
+                            let eval = -evaluator.evaluate(board, !red_to_move);
+                            board.undo_move();
+                            eval
+                        })
+                        .unwrap()
+                }
+            };
+            board.make_move_unchecked(game_move);
+            plies += 1;
+        };
+        for _ in 0..plies {
+            board.undo_move();
+        }
+        result
+    }
+}
+impl AIOpponent for Mcts {
+    fn search(
+        &self,
+        cancel_signal: Arc<AtomicBool>,
+        board: Board,
+        remaining_time: Option<Duration>,
+    ) -> GameMove {
+        self.search_with_info(cancel_signal, board, remaining_time).best_move
+    }
+
+    fn search_with_info(
+        &self,
+        cancel_signal: Arc<AtomicBool>,
+        board: Board,
+        _remaining_time: Option<Duration>,
+    ) -> SearchResult {
+        let mut rng = StdRand::seed(self.seed.unwrap_or_else(|| ClockSeed.next_u64()));
+        let mut stored_tree = self.tree.lock().unwrap();
+        let mut tree = match stored_tree.take().filter(|_| self.tree_reuse) {
+            Some(mut tree) => {
+                if tree.reroot(&board) { tree } else { Tree::new(board.clone()) }
+            }
+            None => Tree::new(board.clone()),
+        };
+
+        let mut iterations = 0;
+        let mut max_depth = 0;
+        let mut scratch = board.clone();
+        while !cancel_signal.load(Ordering::Relaxed)
+            && self.max_iterations.is_none_or(|max| iterations < max)
+        {
+            // Selection
+            let mut node = 0;
+            let mut depth = 0;
+            while tree.nodes[node].untried_moves.is_empty() && !tree.nodes[node].children.is_empty() {
+                node = tree.select_child(node, self.exploration);
+                scratch.make_move_unchecked(tree.nodes[node].game_move.clone().unwrap());
+                depth += 1;
+            }
+            // Expansion
+            if let Some(game_move) = tree.nodes[node].untried_moves.pop() {
+                scratch.make_move_unchecked(game_move.clone());
+                let child = tree.nodes.len();
+                tree.nodes.push(Node::new(Some(game_move), Some(node), &scratch));
+                tree.nodes[node].children.push(child);
+                node = child;
+                depth += 1;
+            }
+            // Simulation
+            let red_result = self.rollout(&mut scratch, &mut rng);
+            // Backpropagation
+            let mut current = Some(node);
+            while let Some(index) = current {
+                let node = &mut tree.nodes[index];
+                node.visits += 1;
+                node.reward += if node.red_moved { red_result } else { 1.0 - red_result };
+                current = node.parent;
+            }
+            for _ in 0..depth {
+                scratch.undo_move();
+            }
+            iterations += 1;
+            max_depth = max_depth.max(depth);
+
+            // No point searching further if there is only one move
+            if tree.nodes[0].children.len() == 1 && tree.nodes[0].untried_moves.is_empty() {
+                break;
+            }
+        }
+        cancel_signal.store(true, Ordering::Relaxed);
+
+        let best_move = match tree.most_visited_child() {
+            Some(child) => tree.nodes[child].game_move.clone().unwrap(),
+            // Cancelled before a single iteration, fall back to any legal move
+            None => board.legal_moves().swap_remove(0),
+        };
+        let result = SearchResult {
+            best_move,
+            score: None,
+            depth: max_depth,
+            nodes: iterations,
+        };
+        *stored_tree = Some(tree);
+        result
+    }
+}
@@ -1,12 +1,13 @@
 use super::*;
+use evaluation::Evaluator;
 
 // static mut TERMINAL_NODES: u32 = 0;
-const WIN_SCORE: i32 = 9999;
 const UPPER_LIM: i32 = i32::MAX / 2;
 const LOWER_LIM: i32 = i32::MIN / 2;
 
 pub struct MinMaxV2 {
     max_depth: u32,
+    evaluator: Evaluator,
 }
 impl AIOpponent for MinMaxV2 {
     fn search(
@@ -28,7 +29,7 @@ impl AIOpponent for MinMaxV2 {
             candidate_moves.sort_by_key(|(_, e)| std::cmp::Reverse(*e));
             for (game_move, eval) in &mut candidate_moves {
                 board.make_move_unchecked(game_move.clone());
-                *eval = -alphabeta(&cancel_signal, &mut board, !red_to_move, d, LOWER_LIM, UPPER_LIM);
+                *eval = -alphabeta(&cancel_signal, &self.evaluator, &mut board, !red_to_move, d, LOWER_LIM, UPPER_LIM);
                 board.undo_move();
                 if cancel_signal.load(Ordering::Relaxed) {
                     // println!("V2: Depth {}, eval {}, nodes touched {}", d, best_move.1, unsafe {TERMINAL_NODES});
@@ -51,17 +52,25 @@ impl AIOpponent for MinMaxV2 {
 }
 impl Default for MinMaxV2 {
     fn default() -> Self {
-        Self { max_depth: 20 }
+        Self::new(20)
     }
 }
 impl MinMaxV2 {
     pub fn new(max_depth: u32) -> Self {
-        Self { max_depth }
+        Self {
+            max_depth,
+            evaluator: Evaluator::classic(),
+        }
+    }
+    pub fn with_evaluator(mut self, evaluator: Evaluator) -> Self {
+        self.evaluator = evaluator;
+        self
     }
 }
 
 fn alphabeta(
     cancel_signal: &Arc<AtomicBool>,
+    evaluator: &Evaluator,
     board: &mut Board,
     red_to_move: bool,
     depth: u32,
@@ -70,19 +79,19 @@ fn alphabeta(
 ) -> i32 {
     if depth == 0 || board.finished() {
         // unsafe { TERMINAL_NODES += 1 };
-        return evaluation(board, red_to_move);
+        return evaluator.evaluate(board, red_to_move);
     }
     let mut best_eval = LOWER_LIM;
     let mut candidate_moves = board.legal_moves();
     reorder_moves(&mut candidate_moves);
     for candidate_move in candidate_moves {
         board.make_move_unchecked(candidate_move);
-        let eval = -alphabeta(cancel_signal, board, !red_to_move, depth - 1, -beta, -alpha);
+        let eval = -alphabeta(cancel_signal, evaluator, board, !red_to_move, depth - 1, -beta, -alpha);
         board.undo_move();
         best_eval = best_eval.max(eval);
 
         // Explicit check for a win to avoid doing more work than necessary
-        if eval >= WIN_SCORE { return eval }
+        if eval >= evaluator.win_score() { return eval }
 
         // If search is cancelled, leave immediately, assume this move is bad since we can't guarantee the quality
         // Have to do the check *after* the minmax call, to avoid the zero leaking into the real evaluation
@@ -97,44 +106,6 @@ fn alphabeta(
     best_eval
 }
 
-fn evaluation(board: &Board, red_to_move: bool) -> i32 {
-    match board.status() {
-        // Evaluation only occurs right *after* a winning move (red_to_move has been flipped),
-        // so no matter who won, we should return the negative of the win score
-        GameStatus::RedWon | GameStatus::BlueWon => {
-            return -WIN_SCORE;
-        }
-        // Stalemates are even, regardless of material difference
-        // Winning positions will tend to avoid it, losing positions will tend to seek it?
-        GameStatus::Stalemate => {
-            return 0;
-        }
-        GameStatus::Playing => (),
-    }
-    let mut piece_val_sum = 0;
-    let mut piece_placement_sum = 0;
-    for (piece, pos) in board.pieces() {
-        let place_value = PIECE_SQUARE_TABLE[pos.to_index()];
-        if piece.is_red() == red_to_move {
-            piece_val_sum += 100;
-            piece_placement_sum += place_value;
-        } else {
-            piece_val_sum -= 100;
-            piece_placement_sum -= place_value;
-        }
-    }
-    piece_val_sum + piece_placement_sum
-}
-
-// Guessed good values
-const PIECE_SQUARE_TABLE: [i32; 25] = [
-    0, 0, 0, 0, 0,
-    1, 5, 5, 5, 1,
-    2, 5, 10, 5, 2,
-    1, 5, 5, 5, 1,
-    0, 0, 0, 0, 0,
-];
-
 use std::cmp;
 fn reorder_moves(candidate_moves: &mut [GameMove]) {
     // Rust sorts in ascending order, so better moves should be *less* than worse moves
@@ -1,12 +1,13 @@
 use super::*;
+use evaluation::Evaluator;
 
 // static mut TERMINAL_NODES: u32 = 0;
-const WIN_SCORE: i32 = 9999;
 const UPPER_LIM: i32 = i32::MAX / 2;
 const LOWER_LIM: i32 = i32::MIN / 2;
 
 pub struct MinMaxV3 {
     max_depth: u32,
+    evaluator: Evaluator,
 }
 impl AIOpponent for MinMaxV3 {
     fn search(
@@ -28,14 +29,14 @@ impl AIOpponent for MinMaxV3 {
             candidate_moves.sort_by_key(|(_, e)| std::cmp::Reverse(*e));
             for (game_move, eval) in &mut candidate_moves {
                 board.make_move_unchecked(game_move.clone());
-                *eval = -negascout(&cancel_signal, &mut board, !red_to_move, d-1, LOWER_LIM, UPPER_LIM);
+                *eval = -negascout(&cancel_signal, &self.evaluator, &mut board, !red_to_move, d-1, LOWER_LIM, UPPER_LIM);
                 board.undo_move();
                 if cancel_signal.load(Ordering::Relaxed) {
                     // println!("V3: Depth {}, eval {}, nodes touched {}", d, best_move.1, unsafe {TERMINAL_NODES});
                     break 'outer
                 }
 
-                if *eval >= WIN_SCORE {
+                if *eval >= self.evaluator.win_score() {
                     // println!("V3: Winning in {d} moves");
                     return game_move.clone()
                 }
@@ -56,18 +57,26 @@ impl AIOpponent for MinMaxV3 {
 }
 impl Default for MinMaxV3 {
     fn default() -> Self {
-        Self { max_depth: 20 }
+        Self::new(20)
     }
 }
 impl MinMaxV3 {
     pub fn new(max_depth: u32) -> Self {
-        Self { max_depth }
+        Self {
+            max_depth,
+            evaluator: Evaluator::classic(),
+        }
+    }
+    pub fn with_evaluator(mut self, evaluator: Evaluator) -> Self {
+        self.evaluator = evaluator;
+        self
     }
 }
 
 // https://en.wikipedia.org/wiki/Principal_variation_search
 fn negascout(
     cancel_signal: &Arc<AtomicBool>,
+    evaluator: &Evaluator,
     board: &mut Board,
     red_to_move: bool,
     depth: u32,
@@ -76,7 +85,7 @@ fn negascout(
 ) -> i32 {
     if depth == 0 || board.finished() {
         // unsafe { TERMINAL_NODES += 1 };
-        return evaluation(board, red_to_move);
+        return evaluator.evaluate(board, red_to_move);
     }
     let mut candidate_moves = board.legal_moves();
     let mut first_move = true;
@@ -85,14 +94,14 @@ fn negascout(
         board.make_move_unchecked(candidate_move);
         let eval = if first_move {
             first_move = false;
-            -negascout(cancel_signal, board, !red_to_move, depth - 1, -beta, -alpha)
+            -negascout(cancel_signal, evaluator, board, !red_to_move, depth - 1, -beta, -alpha)
         } else {
             // Search with null window, assuming first move was best
-            let eval = -negascout(cancel_signal, board, !red_to_move, depth - 1, -alpha-1, -alpha);
+            let eval = -negascout(cancel_signal, evaluator, board, !red_to_move, depth - 1, -alpha-1, -alpha);
             // Move was better than first move, redo search with full window
             if alpha < eval && eval < beta {
                 // println!("assumption broken");
-                -negascout(cancel_signal, board, !red_to_move, depth - 1, -beta, -alpha)
+                -negascout(cancel_signal, evaluator, board, !red_to_move, depth - 1, -beta, -alpha)
             } else {
                 eval
             }
@@ -100,7 +109,7 @@ fn negascout(
         board.undo_move();
 
         // Explicit check for a win to avoid doing more work than necessary
-        if eval >= WIN_SCORE { return eval }
+        if eval >= evaluator.win_score() { return eval }
 
         // If search is cancelled, leave immediately, assume this move is bad since we can't guarantee the quality
         // Have to do the check *after* the minmax call, to avoid the zero leaking into the real evaluation
@@ -115,44 +124,6 @@ fn negascout(
     alpha
 }
 
-fn evaluation(board: &Board, red_to_move: bool) -> i32 {
-    match board.status() {
-        // Evaluation only occurs right *after* a winning move (red_to_move has been flipped),
-        // so no matter who won, we should return the negative of the win score
-        GameStatus::RedWon | GameStatus::BlueWon => {
-            return -WIN_SCORE;
-        }
-        // Stalemates are even, regardless of material difference
-        // Winning positions will tend to avoid it, losing positions will tend to seek it?
-        GameStatus::Stalemate => {
-            return 0;
-        }
-        GameStatus::Playing => (),
-    }
-    let mut piece_val_sum = 0;
-    let mut piece_placement_sum = 0;
-    for (piece, pos) in board.pieces() {
-        let place_value = PIECE_SQUARE_TABLE[pos.to_index()];
-        if piece.is_red() == red_to_move {
-            piece_val_sum += 100;
-            piece_placement_sum += place_value;
-        } else {
-            piece_val_sum -= 100;
-            piece_placement_sum -= place_value;
-        }
-    }
-    piece_val_sum + piece_placement_sum
-}
-
-// Guessed good values
-const PIECE_SQUARE_TABLE: [i32; 25] = [
-    0, 0, 0, 0, 0,
-    1, 5, 5, 5, 1,
-    2, 5, 10, 5, 2,
-    1, 5, 5, 5, 1,
-    0, 0, 0, 0, 0,
-];
-
 use std::cmp;
 fn reorder_moves(candidate_moves: &mut [GameMove]) {
     // Rust sorts in ascending order, so better moves should be *less* than worse moves
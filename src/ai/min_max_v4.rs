@@ -0,0 +1,537 @@
+use std::{sync::atomic::AtomicUsize, time::Instant};
+
+use super::*;
+use evaluation::{Evaluator, in_check};
+use move_ordering::{MoveOrderer, OrderingOptions};
+use network::Network;
+use tablebase::{Tablebase, TablebaseValue};
+use transposition::{Bound, PackedMove, TTEntry, TranspositionTable};
+
+const UPPER_LIM: i32 = i32::MAX / 2;
+const LOWER_LIM: i32 = i32::MIN / 2;
+
+// Limits how far quiescence search may extend past the nominal search depth
+const MAX_QUIESCENCE_DEPTH: u32 = 6;
+// Late moves are only reduced once this many moves have been searched at full depth, and with enough depth left
+const LMR_FULL_DEPTH_MOVES: usize = 3;
+const LMR_MIN_DEPTH: u32 = 3;
+/// Size of the transposition table unless set with `MinMaxV4::with_hash_size`, 2^20 entries
+pub const DEFAULT_HASH_MIB: usize = 16;
+// Bytes taken by each transposition table entry, as a power of two
+const TT_ENTRY_SIZE_LOG2: u32 = 4;
+
+/// Toggles for the individual search enhancements, all enabled by default
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SearchOptions {
+    /// Resolve captures and sensei threats with a quiescence search before evaluating
+    pub quiescence: bool,
+    /// Reuse results from transpositions, also needed for the hash move
+    pub transposition_table: bool,
+    pub ordering: OrderingOptions,
+    /// Search late quiet moves at reduced depth, re-searching if they turn out better than expected
+    pub late_move_reductions: bool,
+}
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            quiescence: true,
+            transposition_table: true,
+            ordering: OrderingOptions::default(),
+            late_move_reductions: true,
+        }
+    }
+}
+
+/// Per-search state, threaded through the recursion
+struct SearchState<'a> {
+    cancel_signal: &'a AtomicBool,
+    orderer: MoveOrderer,
+    nodes: u64,
+    node_limit: Option<u64>,
+    deadline: Option<Instant>,
+}
+impl<'a> SearchState<'a> {
+    fn new(cancel_signal: &'a AtomicBool, ordering: OrderingOptions) -> Self {
+        Self {
+            cancel_signal,
+            orderer: MoveOrderer::new(ordering),
+            nodes: 0,
+            node_limit: None,
+            deadline: None,
+        }
+    }
+    /// Whether the search should stop, either from the cancel signal or from running out of nodes or time
+    fn cancelled(&self) -> bool {
+        self.cancel_signal.load(Ordering::Relaxed)
+            || self.node_limit.is_some_and(|limit| self.nodes >= limit)
+            || self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+pub struct MinMaxV4 {
+    max_depth: u32,
+    evaluator: Evaluator,
+    options: SearchOptions,
+    threads: usize,
+    node_limit: Option<u64>,
+    move_time: Option<Duration>,
+    transposition_table: TranspositionTable,
+    /// Searches running at once, only the first of them clears the transposition table
+    active_searches: AtomicUsize,
+    tablebase: Option<Arc<Tablebase>>,
+    network: Option<Arc<Network>>,
+}
+
+/// Counts a search as active for as long as it lives, even if the search panics
+struct ActiveSearch<'a>(&'a AtomicUsize);
+impl<'a> ActiveSearch<'a> {
+    /// Also returns whether no other search was running
+    fn start(active_searches: &'a AtomicUsize) -> (Self, bool) {
+        let first = active_searches.fetch_add(1, Ordering::Relaxed) == 0;
+        (Self(active_searches), first)
+    }
+}
+impl Drop for ActiveSearch<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+impl AIOpponent for MinMaxV4 {
+    fn search(
+        &self,
+        cancel_signal: Arc<AtomicBool>,
+        board: Board,
+        remaining_time: Option<Duration>,
+    ) -> GameMove {
+        self.search_with_info(cancel_signal, board, remaining_time).best_move
+    }
+
+    fn search_with_info(
+        &self,
+        cancel_signal: Arc<AtomicBool>,
+        board: Board,
+        _remaining_time: Option<Duration>,
+    ) -> SearchResult {
+        self.search_root_scores(cancel_signal, board).0
+    }
+
+    fn win_score(&self) -> i32 {
+        self.evaluator.win_score()
+    }
+}
+impl Default for MinMaxV4 {
+    fn default() -> Self {
+        Self::new(20)
+    }
+}
+impl MinMaxV4 {
+    /// Searches like `search_with_info`, additionally returning the score of every root move from the deepest
+    /// completed iteration, best first. The scores are empty if not even the first iteration completed, and hold only
+    /// the winning move if a win was proven
+    pub fn search_root_scores(
+        &self,
+        cancel_signal: Arc<AtomicBool>,
+        board: Board,
+    ) -> (SearchResult, Vec<(GameMove, i32)>) {
+        if let Some(tablebase) = &self.tablebase
+            && let Some(best_move) = tablebase.best_move(&board)
+        {
+            cancel_signal.store(true, Ordering::Relaxed);
+            let score = self.tablebase_score(tablebase.probe(&board).unwrap());
+            let result = SearchResult { score: Some(score), ..SearchResult::from_move(best_move.clone()) };
+            return (result, vec![(best_move, score)]);
+        }
+        let deadline = self.move_time.map(|move_time| Instant::now() + move_time);
+        // A search running alongside, like pondering or a search abandoned after a timeout, is still using the
+        // table, so it is only cleared when nothing else is searching
+        let (_active, first) = ActiveSearch::start(&self.active_searches);
+        if first {
+            self.transposition_table.clear();
+        }
+        // Helpers have their own stop signal, so they can be stopped without cancelling the main search
+        let helpers_stop = AtomicBool::new(false);
+        let result = std::thread::scope(|scope| {
+            let helpers: Vec<_> = (1..self.threads)
+                .map(|helper| {
+                    let board = board.clone();
+                    let helpers_stop = &helpers_stop;
+                    scope.spawn(move || {
+                        let mut state = SearchState::new(helpers_stop, self.options.ordering);
+                        // Half of the helpers search one iteration ahead, and each starts with the root moves in a
+                        // different order, so they fill the table with different parts of the tree
+                        let first_depth = if helper % 2 == 1 { 3 } else { 1 };
+                        self.iterative_deepening(&mut state, board, first_depth, helper);
+                        state.nodes
+                    })
+                })
+                .collect();
+
+            let mut state = SearchState::new(&cancel_signal, self.options.ordering);
+            state.node_limit = self.node_limit;
+            state.deadline = deadline;
+            let (mut result, root_scores) = self.iterative_deepening(&mut state, board, 1, 0);
+            helpers_stop.store(true, Ordering::Relaxed);
+            result.nodes = state.nodes;
+            for helper in helpers {
+                result.nodes += helper.join().expect("Helper search thread panicked");
+            }
+            (result, root_scores)
+        });
+        cancel_signal.store(true, Ordering::Relaxed);
+        result
+    }
+
+    pub fn new(max_depth: u32) -> Self {
+        Self {
+            max_depth,
+            evaluator: Evaluator::default(),
+            options: SearchOptions::default(),
+            threads: 1,
+            node_limit: None,
+            move_time: None,
+            transposition_table: TranspositionTable::new(hash_size_log2(DEFAULT_HASH_MIB)),
+            active_searches: AtomicUsize::new(0),
+            tablebase: None,
+            network: None,
+        }
+    }
+    pub fn with_evaluator(mut self, evaluator: Evaluator) -> Self {
+        self.evaluator = evaluator;
+        self
+    }
+    pub fn with_options(mut self, options: SearchOptions) -> Self {
+        self.options = options;
+        self
+    }
+    /// Whether to resolve captures and sensei threats with a quiescence search before evaluating
+    pub fn with_quiescence(mut self, quiescence: bool) -> Self {
+        self.options.quiescence = quiescence;
+        self
+    }
+
+    /// Search the given number of threads in parallel, sharing the transposition table (Lazy SMP)
+    /// Only the main thread's result is used, so a single thread gives the same result every time
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Size of the transposition table in MiB, rounded down to a power of two entries
+    pub fn with_hash_size(mut self, mib: usize) -> Self {
+        let size_log2 = hash_size_log2(mib);
+        if size_log2 != self.transposition_table.size_log2() {
+            self.transposition_table = TranspositionTable::new(size_log2);
+        }
+        self
+    }
+
+    /// Applies the evaluator parameters, network and tablebase if given, the thread count, hash size and quiescence
+    /// toggle
+    pub fn with_settings(mut self, settings: &BotSettings) -> Self {
+        if let Some(evaluator) = &settings.evaluator {
+            self.evaluator = evaluator.clone();
+        }
+        if let Some(network) = &settings.network {
+            self.network = Some(network.clone());
+        }
+        if let Some(tablebase) = &settings.tablebase {
+            self.tablebase = Some(tablebase.clone());
+        }
+        self.options.quiescence = settings.quiescence;
+        self.with_threads(settings.threads).with_hash_size(settings.hash_mib)
+    }
+
+    /// Stops the search after visiting this many positions, the same limit always gives the same result
+    pub fn with_node_limit(mut self, node_limit: u64) -> Self {
+        self.node_limit = Some(node_limit);
+        self
+    }
+    /// Stops the search after this much time, regardless of the cancel signal
+    pub fn with_move_time(mut self, move_time: Duration) -> Self {
+        self.move_time = Some(move_time);
+        self
+    }
+    /// Removes the time limit set by `with_move_time`
+    pub fn without_move_time(mut self) -> Self {
+        self.move_time = None;
+        self
+    }
+
+    /// Evaluate positions with a neural network instead of the evaluator, which is still used for finished games
+    pub fn with_network(mut self, network: Arc<Network>) -> Self {
+        self.network = Some(network);
+        self
+    }
+    /// Probe an endgame tablebase during search, playing perfectly once the root position is in it
+    pub fn with_tablebase(mut self, tablebase: Arc<Tablebase>) -> Self {
+        self.tablebase = Some(tablebase);
+        self
+    }
+
+    /// Searches with increasing depth until cancelled or `max_depth` is reached
+    /// Root moves are rotated left by `rotate_moves` before the first iteration, to vary the order between threads
+    /// Also returns the root move scores of the deepest completed iteration, or only the winning move once a win is
+    /// proven
+    fn iterative_deepening(
+        &self,
+        state: &mut SearchState,
+        mut board: Board,
+        first_depth: u32,
+        rotate_moves: usize,
+    ) -> (SearchResult, Vec<(GameMove, i32)>) {
+        let red_to_move = board.red_to_move();
+
+        let mut candidate_moves: Vec<(GameMove, i32)> = board.legal_moves()
+            .into_iter()
+            .map(|m| (m, LOWER_LIM))
+            .collect();
+        let rotation = rotate_moves % candidate_moves.len();
+        candidate_moves.rotate_left(rotation);
+
+        let mut best_move = candidate_moves[0].clone();
+        let mut result = SearchResult::from_move(best_move.0.clone());
+        let mut root_scores = vec![];
+        'outer: for d in (first_depth..self.max_depth).step_by(2) {
+            candidate_moves.sort_by_key(|(_, e)| std::cmp::Reverse(*e));
+            for (game_move, eval) in &mut candidate_moves {
+                board.make_move_unchecked(game_move.clone());
+                *eval = -self.negascout(state, &mut board, !red_to_move, d-1, 1, LOWER_LIM, UPPER_LIM);
+                board.undo_move();
+                if state.cancelled() {
+                    // Moves searched before cancelling are still fully searched at this depth
+                    if best_move.1 > LOWER_LIM {
+                        result.best_move = best_move.0.clone();
+                        result.score = Some(best_move.1);
+                    }
+                    break 'outer
+                }
+
+                if *eval >= self.evaluator.win_score() {
+                    result = SearchResult {
+                        best_move: game_move.clone(),
+                        score: Some(*eval),
+                        depth: d,
+                        nodes: 0,
+                    };
+                    // The other moves' scores are from a shallower search, and may not even be complete
+                    root_scores = vec![(game_move.clone(), *eval)];
+                    break 'outer
+                }
+
+                if *eval > best_move.1 {
+                    best_move = (game_move.clone(), *eval)
+                }
+            }
+            result = SearchResult {
+                best_move: best_move.0.clone(),
+                score: Some(best_move.1),
+                depth: d,
+                nodes: 0,
+            };
+            root_scores = candidate_moves.clone();
+            root_scores.sort_by_key(|(_, e)| std::cmp::Reverse(*e));
+            best_move.1 = LOWER_LIM; // Reset evaluation before next iteration
+        }
+        (result, root_scores)
+    }
+
+    fn evaluate(&self, board: &Board, red_to_move: bool) -> i32 {
+        match &self.network {
+            Some(network) if !board.finished() => network
+                .evaluate(board, red_to_move)
+                .unwrap_or_else(|| self.evaluator.evaluate(board, red_to_move)),
+            _ => self.evaluator.evaluate(board, red_to_move),
+        }
+    }
+
+    fn tablebase_score(&self, value: TablebaseValue) -> i32 {
+        match value {
+            TablebaseValue::Win(_) => self.evaluator.win_score(),
+            TablebaseValue::Loss(_) => -self.evaluator.win_score(),
+            TablebaseValue::Draw => 0,
+        }
+    }
+
+    // https://en.wikipedia.org/wiki/Principal_variation_search
+    #[allow(clippy::too_many_arguments)]
+    fn negascout(
+        &self,
+        state: &mut SearchState,
+        board: &mut Board,
+        red_to_move: bool,
+        depth: u32,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+    ) -> i32 {
+        state.nodes += 1;
+        if board.finished() {
+            return self.evaluator.evaluate(board, red_to_move);
+        }
+        if let Some(tablebase) = &self.tablebase
+            && let Some(value) = tablebase.probe(board)
+        {
+            return self.tablebase_score(value);
+        }
+        if depth == 0 {
+            return if self.options.quiescence {
+                self.quiescence(state, board, red_to_move, MAX_QUIESCENCE_DEPTH, alpha, beta)
+            } else {
+                self.evaluate(board, red_to_move)
+            };
+        }
+
+        let hash = board.state_hash();
+        let original_alpha = alpha;
+        let mut hash_move = None;
+        if self.options.transposition_table && let Some(entry) = self.transposition_table.probe(hash) {
+            hash_move = entry.best_move;
+            if entry.depth >= depth {
+                match entry.bound {
+                    Bound::Exact => return entry.score,
+                    Bound::Lower if entry.score >= beta => return entry.score,
+                    Bound::Upper if entry.score <= alpha => return entry.score,
+                    _ => (),
+                }
+            }
+        }
+
+        let mut candidate_moves = board.legal_moves();
+        state.orderer.order(&mut candidate_moves, ply, hash_move);
+        let may_reduce = self.options.late_move_reductions && depth >= LMR_MIN_DEPTH && !in_check(board);
+        let mut best = (LOWER_LIM, None);
+        for (i, candidate_move) in candidate_moves.into_iter().enumerate() {
+            let quiet = candidate_move.captured_piece.is_none() && !state.orderer.is_killer(&candidate_move, ply);
+            board.make_move_unchecked(candidate_move.clone());
+            let eval = if i == 0 {
+                -self.negascout(state, board, !red_to_move, depth - 1, ply + 1, -beta, -alpha)
+            } else {
+                let reduce = may_reduce && i >= LMR_FULL_DEPTH_MOVES && quiet && !in_check(board);
+                // Search late quiet moves shallower with null window, only continuing if they look promising
+                let reduced_eval = if reduce {
+                    Some(-self.negascout(state, board, !red_to_move, depth - 2, ply + 1, -alpha-1, -alpha))
+                } else {
+                    None
+                };
+                match reduced_eval {
+                    Some(eval) if eval <= alpha => eval,
+                    _ => {
+                        // Search with null window, assuming first move was best
+                        let eval = -self.negascout(state, board, !red_to_move, depth - 1, ply + 1, -alpha-1, -alpha);
+                        // Move was better than first move, redo search with full window
+                        if alpha < eval && eval < beta {
+                            -self.negascout(state, board, !red_to_move, depth - 1, ply + 1, -beta, -alpha)
+                        } else {
+                            eval
+                        }
+                    }
+                }
+            };
+            board.undo_move();
+
+            // Explicit check for a win to avoid doing more work than necessary
+            if eval >= self.evaluator.win_score() { return eval }
+
+            // If search is cancelled, leave immediately, assume this move is bad since we can't guarantee the quality
+            // Have to do the check *after* the minmax call, to avoid the zero leaking into the real evaluation
+            if state.cancelled() {
+                return 0;
+            }
+
+            if eval > best.0 {
+                best = (eval, Some(PackedMove::new(&candidate_move)));
+            }
+
+            // Alpha-beta cutoff
+            alpha = alpha.max(eval);
+            if alpha >= beta {
+                state.orderer.record_cutoff(&candidate_move, ply, depth);
+                break
+            }
+        }
+
+        if self.options.transposition_table {
+            let bound = if alpha <= original_alpha {
+                Bound::Upper
+            } else if alpha >= beta {
+                Bound::Lower
+            } else {
+                Bound::Exact
+            };
+            self.transposition_table.store(hash, TTEntry { depth, score: alpha, bound, best_move: best.1 });
+        }
+        alpha
+    }
+
+    /// Keeps searching captures and sensei threats until the position is quiet, to avoid evaluating in the
+    /// middle of an exchange. The player to move may "stand pat" and take the static evaluation instead,
+    /// unless their sensei is threatened, in which case every move is searched to find a way out
+    /// Sensei threats are only extended on the first ply, so checks cannot go on indefinitely
+    fn quiescence(
+        &self,
+        state: &mut SearchState,
+        board: &mut Board,
+        red_to_move: bool,
+        depth: u32,
+        mut alpha: i32,
+        beta: i32,
+    ) -> i32 {
+        state.nodes += 1;
+        let stand_pat = self.evaluate(board, red_to_move);
+        if board.finished() || depth == 0 {
+            return stand_pat;
+        }
+        let evading = in_check(board);
+        if !evading {
+            if stand_pat >= beta {
+                return stand_pat;
+            }
+            alpha = alpha.max(stand_pat);
+        }
+        let allow_threats = depth == MAX_QUIESCENCE_DEPTH;
+
+        let mut candidate_moves = board.legal_moves();
+        if candidate_moves.is_empty() {
+            return stand_pat;
+        }
+        reorder_moves(&mut candidate_moves);
+        let mut best_eval = if evading { LOWER_LIM } else { stand_pat };
+        for candidate_move in candidate_moves {
+            let capture = candidate_move.captured_piece.is_some();
+            board.make_move_unchecked(candidate_move);
+            let noisy = evading || capture || board.finished() || (allow_threats && in_check(board));
+            if !noisy {
+                board.undo_move();
+                continue;
+            }
+            let eval = -self.quiescence(state, board, !red_to_move, depth - 1, -beta, -alpha);
+            board.undo_move();
+
+            if eval >= self.evaluator.win_score() { return eval }
+            if state.cancelled() {
+                return 0;
+            }
+
+            best_eval = best_eval.max(eval);
+            alpha = alpha.max(eval);
+            if alpha >= beta { break }
+        }
+        best_eval
+    }
+}
+
+use std::cmp;
+fn hash_size_log2(mib: usize) -> u32 {
+    ((mib.max(1) << 20) >> TT_ENTRY_SIZE_LOG2).ilog2()
+}
+
+fn reorder_moves(candidate_moves: &mut [GameMove]) {
+    // Rust sorts in ascending order, so better moves should be *less* than worse moves
+    candidate_moves.sort_by(|move_a, move_b| {
+        match (move_a.captured_piece, move_b.captured_piece) {
+            (Some(_), None) => cmp::Ordering::Less,
+            (None, Some(_)) => cmp::Ordering::Greater,
+            _ => cmp::Ordering::Equal
+        }
+    });
+}
@@ -0,0 +1,114 @@
+//! Move ordering for alpha-beta searches
+//!
+//! Moves are tried in the order: the hash move from the transposition table, captures, the killer moves
+//! for the current ply, then remaining quiet moves by their history score.
+use super::transposition::PackedMove;
+use crate::game::{GameMove, Piece};
+
+/// Which ordering heuristics are in use, captures are always ordered first
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OrderingOptions {
+    pub hash_move: bool,
+    pub killer_moves: bool,
+    pub history: bool,
+}
+impl Default for OrderingOptions {
+    fn default() -> Self {
+        Self {
+            hash_move: true,
+            killer_moves: true,
+            history: true,
+        }
+    }
+}
+
+const HASH_MOVE_SCORE: i32 = i32::MAX;
+const CAPTURE_SCORE: i32 = 1 << 30;
+const KILLER_SCORE: i32 = 1 << 29;
+const MAX_HISTORY: u32 = 1 << 28;
+
+pub struct MoveOrderer {
+    options: OrderingOptions,
+    /// Two most recent quiet moves that caused a cutoff, per ply
+    killers: Vec<[Option<PackedMove>; 2]>,
+    /// Butterfly table, indexed by card, start square and end square
+    history: Box<[[[u32; 25]; 25]; 16]>,
+}
+impl MoveOrderer {
+    pub fn new(options: OrderingOptions) -> Self {
+        Self {
+            options,
+            killers: vec![],
+            history: Box::new([[[0; 25]; 25]; 16]),
+        }
+    }
+
+    pub fn options(&self) -> OrderingOptions {
+        self.options
+    }
+
+    /// Sorts moves so the most promising come first
+    pub fn order(&self, moves: &mut [GameMove], ply: usize, hash_move: Option<PackedMove>) {
+        let hash_move = hash_move.filter(|_| self.options.hash_move);
+        moves.sort_by_cached_key(|game_move| std::cmp::Reverse(self.score(game_move, ply, hash_move)));
+    }
+
+    fn score(&self, game_move: &GameMove, ply: usize, hash_move: Option<PackedMove>) -> i32 {
+        if hash_move.is_some_and(|hash_move| hash_move.matches(game_move)) {
+            return HASH_MOVE_SCORE;
+        }
+        match game_move.captured_piece {
+            Some(Piece::RedSensei | Piece::BlueSensei) => return CAPTURE_SCORE + 1,
+            Some(_) => return CAPTURE_SCORE,
+            None => (),
+        }
+        if self.is_killer(game_move, ply) {
+            return KILLER_SCORE;
+        }
+        if self.options.history {
+            let packed = PackedMove::new(game_move);
+            self.history[packed.card_index as usize][packed.start as usize][packed.end as usize] as i32
+        } else {
+            0
+        }
+    }
+
+    pub fn is_killer(&self, game_move: &GameMove, ply: usize) -> bool {
+        self.options.killer_moves
+            && self.killers.get(ply).is_some_and(|killers| {
+                killers
+                    .iter()
+                    .flatten()
+                    .any(|killer| killer.matches(game_move))
+            })
+    }
+
+    /// Records a move that caused a beta cutoff at the given ply and remaining depth
+    pub fn record_cutoff(&mut self, game_move: &GameMove, ply: usize, depth: u32) {
+        if game_move.captured_piece.is_some() {
+            return;
+        }
+        let packed = PackedMove::new(game_move);
+        if self.options.killer_moves {
+            if self.killers.len() <= ply {
+                self.killers.resize(ply + 1, [None; 2]);
+            }
+            let killers = &mut self.killers[ply];
+            if killers[0] != Some(packed) {
+                killers[1] = killers[0];
+                killers[0] = Some(packed);
+            }
+        }
+        if self.options.history {
+            let entry = &mut self.history[packed.card_index as usize][packed.start as usize]
+                [packed.end as usize];
+            *entry += depth * depth;
+            if *entry > MAX_HISTORY {
+                // Age the whole table, keeping relative order
+                for value in self.history.iter_mut().flatten().flatten() {
+                    *value /= 2;
+                }
+            }
+        }
+    }
+}
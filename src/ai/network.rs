@@ -0,0 +1,291 @@
+//! Small dense neural network evaluation, with one hidden layer and CPU-only inference and training
+//!
+//! Positions are seen from the perspective of the player to move: the board is rotated for blue, so "own"
+//! pieces always start at the bottom. Inputs are one-hot, piece type per square, plus ownership of each of the
+//! 16 cards as own, opponent's or transfer card. The output is a logit of the probability that the player to
+//! move wins, scaled to evaluation units the same way as in the rest of the engine, where an evaluation of 400
+//! is roughly a 90% chance of winning.
+//!
+//! Network files are plain text: a header line `onitama-network <version> <inputs> <hidden>`, followed by the
+//! hidden layer weights (one line per input), the hidden biases, the output weights and the output bias.
+use std::fmt::Write as _;
+
+use super::self_play::PositionRecord;
+use super::*;
+use crate::cards;
+
+const FILE_VERSION: u32 = 1;
+/// 4 piece types on 25 squares, then 16 cards with 3 possible owners
+pub const NUM_INPUTS: usize = 4 * 25 + 16 * 3;
+// At most 10 pieces and 5 cards are active at once, positions with more pieces aren't evaluated
+const MAX_ACTIVE: usize = 15;
+// Converts the output logit to evaluation units, 400 / ln(10)
+const EVAL_SCALE: f32 = 173.7;
+// Network evaluations are kept well clear of the win score, so the search never mistakes them for wins
+const MAX_EVAL: f32 = 4000.0;
+
+/// Active inputs of a position, all inputs are either 0 or 1
+#[derive(Clone, Debug)]
+pub struct Inputs {
+    active: [u16; MAX_ACTIVE],
+    len: usize,
+}
+impl Inputs {
+    /// `squares` from blue's side first as on the board, `cards` as indices into `cards::ALL_CARDS` in .oni order
+    /// Returns `None` for more pieces than a game starts with
+    fn new(squares: impl Iterator<Item = Option<Piece>>, cards: [usize; 5], red_to_move: bool) -> Option<Self> {
+        let mut inputs = Self { active: [0; MAX_ACTIVE], len: 0 };
+        for (i, piece) in squares.enumerate() {
+            let Some(piece) = piece else { continue };
+            let square = if red_to_move { i } else { 24 - i };
+            let own = piece.is_red() == red_to_move;
+            let sensei = matches!(piece, Piece::RedSensei | Piece::BlueSensei);
+            let plane = own as usize * 2 + sensei as usize;
+            inputs.push(plane * 25 + square)?;
+        }
+        let (own, opponent) = if red_to_move { ([0, 1], [2, 3]) } else { ([2, 3], [0, 1]) };
+        for slot in own {
+            inputs.push(100 + cards[slot] * 3)?;
+        }
+        for slot in opponent {
+            inputs.push(100 + cards[slot] * 3 + 1)?;
+        }
+        inputs.push(100 + cards[4] * 3 + 2)?;
+        Some(inputs)
+    }
+    fn push(&mut self, input: usize) -> Option<()> {
+        *self.active.get_mut(self.len)? = input as u16;
+        self.len += 1;
+        Some(())
+    }
+
+    /// Returns `None` for boards with more pieces than a game starts with, which can be loaded from .oni files
+    pub fn from_board(board: &Board) -> Option<Self> {
+        let cards = board.cards().map(|card| cards::index_of_card(&card));
+        Self::new(board.squares().iter().copied(), cards, board.red_to_move())
+    }
+
+    /// Returns `None` if the record holds unknown cards or more pieces than a game starts with
+    pub fn from_record(record: &PositionRecord) -> Option<Self> {
+        let mut cards = [0; 5];
+        for (card, identifier) in cards.iter_mut().zip(record.cards.bytes()) {
+            *card = cards::ALL_CARDS_IDENT.iter().position(|other| *other == identifier)?;
+        }
+        let squares = record.position.chars().map(|c| match c {
+            '0' => Some(Piece::RedDisciple),
+            '1' => Some(Piece::BlueDisciple),
+            '2' => Some(Piece::RedSensei),
+            '3' => Some(Piece::BlueSensei),
+            _ => None,
+        });
+        Self::new(squares, cards, record.red_to_move)
+    }
+
+    fn active(&self) -> &[u16] {
+        &self.active[..self.len]
+    }
+}
+
+/// A position to train on, with the target probability of the player to move winning
+pub struct TrainingSample {
+    pub inputs: Inputs,
+    pub target: f32,
+}
+impl TrainingSample {
+    /// Target is the game result blended with the search score, `result_weight` 1 uses only the result
+    pub fn from_record(record: &PositionRecord, result_weight: f32) -> Option<Self> {
+        let result = (record.result as f32 + 1.0) / 2.0;
+        let score = sigmoid(record.score.clamp(-4000, 4000) as f32 / EVAL_SCALE);
+        Some(Self {
+            inputs: Inputs::from_record(record)?,
+            target: result_weight * result + (1.0 - result_weight) * score,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Network {
+    hidden_size: usize,
+    /// Input-major, `NUM_INPUTS` rows of `hidden_size` weights
+    hidden_weights: Vec<f32>,
+    hidden_biases: Vec<f32>,
+    output_weights: Vec<f32>,
+    output_bias: f32,
+}
+impl Network {
+    /// Randomly initialised network with the given number of hidden units
+    pub fn new(hidden_size: usize, seed: u64) -> Self {
+        let mut rng = StdRand::seed(seed);
+        let mut random = |scale: f32| (rng.next_u64() as f64 / u64::MAX as f64 * 2.0 - 1.0) as f32 * scale;
+        // Only about 15 inputs are active at a time, so that decides the scale rather than the number of inputs
+        let hidden_scale = 1.0 / (MAX_ACTIVE as f32).sqrt();
+        let output_scale = 1.0 / (hidden_size as f32).sqrt();
+        Self {
+            hidden_size,
+            hidden_weights: (0..NUM_INPUTS * hidden_size).map(|_| random(hidden_scale)).collect(),
+            hidden_biases: vec![0.0; hidden_size],
+            output_weights: (0..hidden_size).map(|_| random(output_scale)).collect(),
+            output_bias: 0.0,
+        }
+    }
+    pub fn hidden_size(&self) -> usize {
+        self.hidden_size
+    }
+
+    fn hidden(&self, inputs: &Inputs) -> Vec<f32> {
+        let mut hidden = self.hidden_biases.clone();
+        for &input in inputs.active() {
+            let row = &self.hidden_weights[input as usize * self.hidden_size..][..self.hidden_size];
+            for (h, w) in hidden.iter_mut().zip(row) {
+                *h += w;
+            }
+        }
+        hidden
+    }
+    /// Logit of the probability of the player to move winning
+    pub fn forward(&self, inputs: &Inputs) -> f32 {
+        self.output(&self.hidden(inputs))
+    }
+    fn output(&self, hidden: &[f32]) -> f32 {
+        self.output_bias
+            + hidden
+                .iter()
+                .zip(&self.output_weights)
+                .map(|(h, w)| h.max(0.0) * w)
+                .sum::<f32>()
+    }
+
+    /// Evaluation for the given player, in the same units as `Evaluator::evaluate`
+    /// Finished games are left to the caller, as the network has no notion of wins, and boards with more pieces than
+    /// a game starts with aren't evaluated
+    pub fn evaluate(&self, board: &Board, red_to_move: bool) -> Option<i32> {
+        let eval = (self.forward(&Inputs::from_board(board)?) * EVAL_SCALE).clamp(-MAX_EVAL, MAX_EVAL) as i32;
+        Some(if red_to_move == board.red_to_move() { eval } else { -eval })
+    }
+
+    /// Mean cross-entropy between predicted and target win probabilities
+    pub fn loss(&self, samples: &[TrainingSample]) -> f32 {
+        let total: f32 = samples
+            .iter()
+            .map(|sample| {
+                let p = sigmoid(self.forward(&sample.inputs)).clamp(1e-6, 1.0 - 1e-6);
+                -(sample.target * p.ln() + (1.0 - sample.target) * (1.0 - p).ln())
+            })
+            .sum();
+        total / samples.len().max(1) as f32
+    }
+
+    /// One pass of stochastic gradient descent over the samples in random order
+    pub fn train_epoch(&mut self, samples: &[TrainingSample], learning_rate: f32, rng: &mut StdRand) {
+        let mut order: Vec<usize> = (0..samples.len()).collect();
+        for i in (1..order.len()).rev() {
+            order.swap(i, rng.next_range(0..i + 1));
+        }
+        for i in order {
+            let sample = &samples[i];
+            let hidden = self.hidden(&sample.inputs);
+            let output = self.output(&hidden);
+            // Gradient of the cross-entropy with respect to the logit
+            let gradient = sigmoid(output) - sample.target;
+            self.output_bias -= learning_rate * gradient;
+            for (j, &h) in hidden.iter().enumerate() {
+                if h <= 0.0 {
+                    continue;
+                }
+                let hidden_gradient = gradient * self.output_weights[j];
+                self.output_weights[j] -= learning_rate * gradient * h;
+                self.hidden_biases[j] -= learning_rate * hidden_gradient;
+                for &input in sample.inputs.active() {
+                    self.hidden_weights[input as usize * self.hidden_size + j] -= learning_rate * hidden_gradient;
+                }
+            }
+        }
+    }
+
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, NetworkError> {
+        let text = std::fs::read_to_string(path).map_err(NetworkError::Io)?;
+        Self::from_network_str(&text)
+    }
+
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), NetworkError> {
+        std::fs::write(path, self.to_network_string()).map_err(NetworkError::Io)
+    }
+
+    pub fn from_network_str(text: &str) -> Result<Self, NetworkError> {
+        let mut lines = text.lines();
+        let header: Vec<&str> = lines.next().unwrap_or("").split_whitespace().collect();
+        let [name, version, inputs, hidden_size] = header[..] else {
+            return Err(NetworkError::Syntax);
+        };
+        if name != "onitama-network" {
+            return Err(NetworkError::Syntax);
+        }
+        let version: u32 = version.parse().map_err(|_| NetworkError::Syntax)?;
+        if version != FILE_VERSION {
+            return Err(NetworkError::UnsupportedVersion(version));
+        }
+        let inputs: usize = inputs.parse().map_err(|_| NetworkError::Syntax)?;
+        let hidden_size: usize = hidden_size.parse().map_err(|_| NetworkError::Syntax)?;
+        if inputs != NUM_INPUTS {
+            return Err(NetworkError::InputMismatch(inputs));
+        }
+        let values: Vec<f32> = lines
+            .flat_map(str::split_whitespace)
+            .map(|value| value.parse().map_err(|_| NetworkError::Syntax))
+            .collect::<Result<_, _>>()?;
+        if values.len() != NUM_INPUTS * hidden_size + 2 * hidden_size + 1 {
+            return Err(NetworkError::Syntax);
+        }
+        let (hidden_weights, rest) = values.split_at(NUM_INPUTS * hidden_size);
+        let (hidden_biases, rest) = rest.split_at(hidden_size);
+        let (output_weights, rest) = rest.split_at(hidden_size);
+        Ok(Self {
+            hidden_size,
+            hidden_weights: hidden_weights.to_vec(),
+            hidden_biases: hidden_biases.to_vec(),
+            output_weights: output_weights.to_vec(),
+            output_bias: rest[0],
+        })
+    }
+
+    pub fn to_network_string(&self) -> String {
+        let mut text = format!("onitama-network {FILE_VERSION} {NUM_INPUTS} {}\n", self.hidden_size);
+        let mut write_row = |row: &[f32]| {
+            let row: Vec<String> = row.iter().map(|value| value.to_string()).collect();
+            writeln!(text, "{}", row.join(" ")).unwrap();
+        };
+        for row in self.hidden_weights.chunks(self.hidden_size) {
+            write_row(row);
+        }
+        write_row(&self.hidden_biases);
+        write_row(&self.output_weights);
+        write_row(&[self.output_bias]);
+        text
+    }
+}
+
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+#[derive(Debug)]
+pub enum NetworkError {
+    Io(std::io::Error),
+    Syntax,
+    UnsupportedVersion(u32),
+    /// The file was made for a different number of inputs
+    InputMismatch(usize),
+}
+
+impl std::fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed to access network file: {err}"),
+            Self::Syntax => write!(f, "malformed network file"),
+            Self::UnsupportedVersion(version) => write!(f, "unsupported network file version {version}"),
+            Self::InputMismatch(inputs) => {
+                write!(f, "network has {inputs} inputs, expected {NUM_INPUTS}")
+            }
+        }
+    }
+}
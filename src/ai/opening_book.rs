@@ -0,0 +1,261 @@
+//! Opening book, mapping positions to weighted moves
+//!
+//! Positions are keyed by `Board::position_hash`, so one book can hold openings for any number of card sets.
+//! Book files are plain text, one move per line after a version line:
+//! ```text
+//! version = 1
+//! # position hash, move in .oni notation, weight
+//! 8c3f2a17d40e95b1 Bvq 12
+//! ```
+use std::collections::HashMap;
+
+use super::*;
+
+pub const BOOK_VERSION: i64 = 1;
+
+/// A move stored in the book, in the 3 character notation used by .oni files
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BookMove {
+    pub notation: [u8; 3],
+    pub weight: u32,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct OpeningBook {
+    entries: HashMap<u64, Vec<BookMove>>,
+}
+impl OpeningBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of positions in the book
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Adds weight to a move in the given position
+    pub fn add_move(&mut self, board: &Board, game_move: &GameMove, weight: u32) {
+        let notation = game_move.as_encoded_bytes();
+        let moves = self.entries.entry(board.position_hash()).or_default();
+        match moves.iter_mut().find(|book_move| book_move.notation == notation) {
+            Some(book_move) => book_move.weight += weight,
+            None => moves.push(BookMove { notation, weight }),
+        }
+    }
+
+    /// Adds the first `max_plies` moves of a finished game, moves by the winner count twice as much as draws
+    /// Moves by the losing side are not added. Returns whether the game was used
+    pub fn add_game(&mut self, game: &Board, max_plies: usize) -> bool {
+        let red_weight = match game.status() {
+            GameStatus::RedWon => Some(2),
+            GameStatus::BlueWon => None,
+            GameStatus::Stalemate => Some(1),
+            GameStatus::Playing => return false,
+        };
+        let blue_weight = match game.status() {
+            GameStatus::BlueWon => Some(2),
+            GameStatus::Stalemate => Some(1),
+            _ => None,
+        };
+        let mut board = game.clone();
+        while board.game_length() > 0 {
+            board.undo_move();
+        }
+        for game_move in game.move_history().iter().take(max_plies) {
+            let weight = if board.red_to_move() { red_weight } else { blue_weight };
+            if let Some(weight) = weight {
+                self.add_move(&board, game_move, weight);
+            }
+            board.make_move_unchecked(game_move.clone());
+        }
+        true
+    }
+
+    /// Book moves for the position which are legal on the given board, along with their weights
+    pub fn probe(&self, board: &Board) -> Vec<(GameMove, u32)> {
+        let Some(book_moves) = self.entries.get(&board.position_hash()) else {
+            return vec![];
+        };
+        let legal_moves = board.legal_moves();
+        book_moves
+            .iter()
+            .filter(|book_move| book_move.weight > 0)
+            .filter_map(|book_move| {
+                legal_moves
+                    .iter()
+                    .find(|game_move| game_move.as_encoded_bytes() == book_move.notation)
+                    .map(|game_move| (game_move.clone(), book_move.weight))
+            })
+            .collect()
+    }
+
+    /// Picks a book move, either the one with the highest weight or at random in proportion to the weights
+    pub fn choose(&self, board: &Board, varied: bool, rng: &mut StdRand) -> Option<GameMove> {
+        let book_moves = self.probe(board);
+        if !varied {
+            return book_moves
+                .into_iter()
+                .max_by_key(|(_, weight)| *weight)
+                .map(|(game_move, _)| game_move);
+        }
+        let total: u32 = book_moves.iter().map(|(_, weight)| weight).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut pick = rng.next_range(0..total);
+        for (game_move, weight) in book_moves {
+            if pick < weight {
+                return Some(game_move);
+            }
+            pick -= weight;
+        }
+        unreachable!()
+    }
+
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, BookError> {
+        let text = std::fs::read_to_string(path).map_err(BookError::Io)?;
+        Self::from_book_str(&text)
+    }
+
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), BookError> {
+        std::fs::write(path, self.to_book_string()).map_err(BookError::Io)
+    }
+
+    pub fn from_book_str(text: &str) -> Result<Self, BookError> {
+        let mut version = None;
+        let mut book = Self::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = match line.split_once('#') {
+                Some((pre, _)) => pre,
+                None => line,
+            }
+            .trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(value) = line.strip_prefix("version").and_then(|s| s.trim().strip_prefix('=')) {
+                version = Some(value.trim().parse().map_err(|_| BookError::Syntax(i + 1))?);
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [hash, notation, weight] = fields[..] else {
+                return Err(BookError::Syntax(i + 1));
+            };
+            let hash = u64::from_str_radix(hash, 16).map_err(|_| BookError::Syntax(i + 1))?;
+            let notation: [u8; 3] = notation
+                .as_bytes()
+                .try_into()
+                .map_err(|_| BookError::Syntax(i + 1))?;
+            let weight = weight.parse().map_err(|_| BookError::Syntax(i + 1))?;
+            book.entries
+                .entry(hash)
+                .or_default()
+                .push(BookMove { notation, weight });
+        }
+        match version {
+            Some(BOOK_VERSION) => Ok(book),
+            Some(version) => Err(BookError::UnsupportedVersion(version)),
+            None => Err(BookError::MissingVersion),
+        }
+    }
+
+    /// Positions are sorted by hash and moves by weight, so the same book always gives the same file
+    pub fn to_book_string(&self) -> String {
+        let mut out = format!("version = {BOOK_VERSION}\n");
+        let mut hashes: Vec<&u64> = self.entries.keys().collect();
+        hashes.sort();
+        for hash in hashes {
+            let mut moves = self.entries[hash].clone();
+            moves.sort_by_key(|book_move| (std::cmp::Reverse(book_move.weight), book_move.notation));
+            for book_move in moves {
+                out.push_str(&format!(
+                    "{hash:016x} {} {}\n",
+                    String::from_utf8_lossy(&book_move.notation),
+                    book_move.weight
+                ));
+            }
+        }
+        out
+    }
+}
+
+#[derive(Debug)]
+pub enum BookError {
+    Io(std::io::Error),
+    Syntax(usize),
+    MissingVersion,
+    UnsupportedVersion(i64),
+}
+
+impl std::fmt::Display for BookError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed to access book file: {err}"),
+            Self::Syntax(line) => write!(f, "syntax error on line {line}"),
+            Self::MissingVersion => write!(f, "book file has no version"),
+            Self::UnsupportedVersion(version) => write!(f, "unsupported book file version {version}"),
+        }
+    }
+}
+
+/// Plays from the opening book while the position is in it, otherwise searches with the wrapped bot
+pub struct BookOpponent {
+    book: Arc<OpeningBook>,
+    inner: Arc<dyn AIOpponent>,
+    varied: bool,
+    seed: Option<u64>,
+}
+impl BookOpponent {
+    pub fn new(book: Arc<OpeningBook>, inner: Arc<dyn AIOpponent>) -> Self {
+        Self {
+            book,
+            inner,
+            varied: false,
+            seed: None,
+        }
+    }
+    /// Pick book moves at random in proportion to their weights, instead of always the heaviest
+    pub fn with_variety(mut self, varied: bool) -> Self {
+        self.varied = varied;
+        self
+    }
+    /// Seed for varied book moves, otherwise seeded from the clock
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+}
+impl AIOpponent for BookOpponent {
+    fn search(
+        &self,
+        cancel_signal: Arc<AtomicBool>,
+        board: Board,
+        remaining_time: Option<Duration>,
+    ) -> GameMove {
+        self.search_with_info(cancel_signal, board, remaining_time).best_move
+    }
+
+    fn search_with_info(
+        &self,
+        cancel_signal: Arc<AtomicBool>,
+        board: Board,
+        remaining_time: Option<Duration>,
+    ) -> SearchResult {
+        let mut rng = StdRand::seed(self.seed.unwrap_or_else(|| ClockSeed.next_u64()));
+        match self.book.choose(&board, self.varied, &mut rng) {
+            Some(game_move) => {
+                cancel_signal.store(true, Ordering::Relaxed);
+                SearchResult::from_move(game_move)
+            }
+            None => self.inner.search_with_info(cancel_signal, board, remaining_time),
+        }
+    }
+
+    fn win_score(&self) -> i32 {
+        self.inner.win_score()
+    }
+}
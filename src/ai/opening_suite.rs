@@ -0,0 +1,157 @@
+//! Balanced start positions for testing bots against each other
+//!
+//! Candidates are random cards followed by a few random moves, like the arena's random positions. A candidate is
+//! kept only if a shallow `MinMaxV4` search scores it within a window around zero, so neither side starts with a
+//! decided game. Positions repeating an earlier one are dropped, and cards are picked at random favouring the least
+//! used ones, so every card is used about equally often, with a limit on how many positions share the same five cards.
+//!
+//! Suites are saved one game in .oni format per line, after a comment describing how they were made, so they can
+//! be used as the arena's pregens file and as the start positions of the `tournament` binary.
+use std::{
+    collections::{HashMap, HashSet},
+    io::Write,
+};
+
+use crate::cards;
+
+use super::*;
+use tournament::play_random_moves;
+
+#[derive(Clone, Debug)]
+pub struct SuiteConfig {
+    /// Number of positions to generate
+    pub positions: usize,
+    /// Random moves played from the start, picked uniformly between the two
+    pub min_plies: usize,
+    pub max_plies: usize,
+    /// Depth of the search scoring the candidates
+    pub depth: u32,
+    /// Largest absolute score a position may have, in evaluation units
+    pub window: i32,
+    /// Most positions allowed with the same five cards, in any arrangement
+    pub max_per_card_set: usize,
+    /// Gives up after this many candidates, even if the suite isn't full
+    pub max_attempts: usize,
+}
+impl Default for SuiteConfig {
+    fn default() -> Self {
+        Self {
+            positions: 100,
+            min_plies: 2,
+            max_plies: 8,
+            depth: 5,
+            window: 60,
+            max_per_card_set: 2,
+            max_attempts: 100_000,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SuitePosition {
+    pub board: Board,
+    /// Score of the search for the player to move
+    pub score: i32,
+}
+
+/// Generates a suite, calling `progress` with the number of positions found and candidates tried after each
+/// candidate. The same config and seed always give the same suite. Setting `cancel` returns the positions found so far
+pub fn generate_suite(
+    config: &SuiteConfig,
+    seed: u64,
+    cancel: &AtomicBool,
+    mut progress: impl FnMut(usize, usize),
+) -> Vec<SuitePosition> {
+    let mut rng = StdRand::seed(seed);
+    let engine = MinMaxV4::new(config.depth).without_move_time();
+    let mut suite = vec![];
+    let mut seen = HashSet::new();
+    let mut card_usage = [0; 16];
+    let mut card_sets = HashMap::new();
+    for attempt in 1..=config.max_attempts {
+        if suite.len() >= config.positions || cancel.load(Ordering::Relaxed) {
+            break;
+        }
+        let card_indices = pick_cards(&card_usage, &mut rng);
+        let mut card_set = card_indices;
+        card_set.sort();
+        let uses = card_sets.get(&card_set).copied().unwrap_or(0);
+        if uses < config.max_per_card_set
+            && let Some(position) = candidate(config, &engine, &card_indices, &mut rng)
+            && seen.insert(position.board.position_hash())
+        {
+            for &card in &card_indices {
+                card_usage[card] += 1;
+            }
+            card_sets.insert(card_set, uses + 1);
+            suite.push(position);
+        }
+        progress(suite.len(), attempt);
+    }
+    suite
+}
+
+/// Five different cards in a random arrangement, each picked with a weight that drops by `USAGE_FALLOFF` for every
+/// use more than the least used card, so rarely used cards are dealt most but any set can still come up
+fn pick_cards(card_usage: &[usize; 16], rng: &mut StdRand) -> [usize; 5] {
+    const USAGE_FALLOFF: u64 = 16;
+    const MAX_FALLOFFS: usize = 4;
+    let min_usage = card_usage.iter().copied().min().unwrap_or(0);
+    let max_weight = USAGE_FALLOFF.pow(MAX_FALLOFFS as u32);
+    let mut weights: Vec<u64> = card_usage
+        .iter()
+        .map(|&usage| max_weight / USAGE_FALLOFF.pow((usage - min_usage).min(MAX_FALLOFFS) as u32))
+        .collect();
+    let mut picked = [0; 5];
+    for slot in &mut picked {
+        let mut target = rng.next_range(0..weights.iter().sum::<u64>());
+        let card = weights
+            .iter()
+            .position(|&weight| {
+                if target < weight {
+                    return true;
+                }
+                target -= weight;
+                false
+            })
+            .unwrap();
+        weights[card] = 0;
+        *slot = card;
+    }
+    picked
+}
+
+/// Plays random moves with the given cards, returning the position if it scores within the window
+fn candidate(
+    config: &SuiteConfig,
+    engine: &MinMaxV4,
+    card_indices: &[usize; 5],
+    rng: &mut StdRand,
+) -> Option<SuitePosition> {
+    let card_string: String = card_indices.iter().map(|&card| cards::ALL_CARDS_IDENT[card] as char).collect();
+    let mut board = Board::load_game(&card_string).unwrap();
+    let plies = rng.next_range(config.min_plies..config.max_plies.max(config.min_plies) + 1);
+    if !play_random_moves(&mut board, rng, plies) {
+        return None;
+    }
+    let score = engine.search_with_info(Arc::new(AtomicBool::new(false)), board.clone(), None).score?;
+    (score.abs() <= config.window).then_some(SuitePosition { board, score })
+}
+
+/// Writes the suite one game per line, after a comment with the config used
+pub fn write_suite(writer: &mut impl Write, config: &SuiteConfig, suite: &[SuitePosition]) -> std::io::Result<()> {
+    writeln!(
+        writer,
+        "# {} balanced positions: {}-{} random plies, depth {} scores within ±{}, at most {} per card set",
+        suite.len(),
+        config.min_plies,
+        config.max_plies,
+        config.depth,
+        config.window,
+        config.max_per_card_set
+    )?;
+    for position in suite {
+        writeln!(writer, "{}", position.board.save_game(false))?;
+    }
+    Ok(())
+}
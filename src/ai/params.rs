@@ -0,0 +1,175 @@
+//! Loading and saving of evaluator parameters
+//!
+//! Parameter files use a small subset of TOML: `key = value` pairs with integer or integer array values,
+//! grouped under `[weights]` for the evaluation terms. Example:
+//! ```toml
+//! version = 1
+//! win_score = 9999
+//! piece_square_table = [
+//!     0, 0, 0, 0, 0,
+//!     ...
+//! ]
+//!
+//! [weights]
+//! material = 100
+//! piece_square = 1
+//! ```
+//! Terms not mentioned under `[weights]` are disabled. Values must fit in 32 bits, and `win_score` must be above
+//! `Evaluator::max_evaluation` so a won position can't be confused with an ordinary evaluation.
+use super::evaluation::{EvalTerm, Evaluator};
+use strum::IntoEnumIterator;
+
+pub const PARAMS_VERSION: i64 = 1;
+
+impl EvalTerm {
+    /// Name of the term as used in parameter files
+    pub fn key(&self) -> &'static str {
+        match self {
+            Self::Material => "material",
+            Self::PieceSquare => "piece_square",
+            Self::TempleDistance => "temple_distance",
+            Self::CardAttacks => "card_attacks",
+            Self::TransferCard => "transfer_card",
+            Self::SenseiSafety => "sensei_safety",
+        }
+    }
+}
+
+impl Evaluator {
+    /// Loads evaluator parameters from a file
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, ParamsError> {
+        let text = std::fs::read_to_string(path).map_err(ParamsError::Io)?;
+        Self::from_params_str(&text)
+    }
+
+    /// Saves evaluator parameters to a file, in a format readable by `Evaluator::load`
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), ParamsError> {
+        std::fs::write(path, self.to_params_string()).map_err(ParamsError::Io)
+    }
+
+    pub fn from_params_str(text: &str) -> Result<Self, ParamsError> {
+        let mut version = None;
+        let mut evaluator = Self::new();
+        let mut section = String::new();
+
+        // Ignore comments, and join lines belonging to an array so every entry is a single line
+        let mut entries = vec![];
+        let mut pending = String::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = match line.split_once('#') {
+                Some((pre, _)) => pre,
+                None => line,
+            }
+            .trim();
+            if !pending.is_empty() {
+                pending.push(' ');
+            }
+            pending.push_str(line);
+            if pending.contains('[') && pending.contains('=') && !pending.contains(']') {
+                continue;
+            }
+            if !pending.is_empty() {
+                entries.push((i + 1, std::mem::take(&mut pending)));
+            }
+        }
+        if !pending.is_empty() {
+            return Err(ParamsError::Syntax(text.lines().count()));
+        }
+
+        for (line_number, entry) in entries {
+            if let Some(name) = entry.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+                section = name.trim().to_string();
+                continue;
+            }
+            let (key, value) = entry
+                .split_once('=')
+                .ok_or(ParamsError::Syntax(line_number))?;
+            let (key, value) = (key.trim(), value.trim());
+            let parse_int =
+                |value: &str| value.trim().parse::<i64>().map_err(|_| ParamsError::Syntax(line_number));
+            let parse_i32 =
+                |value: &str| value.trim().parse::<i32>().map_err(|_| ParamsError::Syntax(line_number));
+
+            match (section.as_str(), key) {
+                ("", "version") => version = Some(parse_int(value)?),
+                ("", "win_score") => {
+                    evaluator = evaluator.with_win_score(parse_i32(value)?)
+                }
+                ("", "piece_square_table") => {
+                    let values = value
+                        .strip_prefix('[')
+                        .and_then(|s| s.strip_suffix(']'))
+                        .ok_or(ParamsError::Syntax(line_number))?
+                        .split(',')
+                        .filter(|s| !s.trim().is_empty())
+                        .map(parse_i32)
+                        .collect::<Result<Vec<i32>, _>>()?;
+                    let table = values
+                        .try_into()
+                        .map_err(|_| ParamsError::Syntax(line_number))?;
+                    evaluator = evaluator.with_piece_square_table(table);
+                }
+                ("weights", key) => {
+                    let term = EvalTerm::iter()
+                        .find(|term| term.key() == key)
+                        .ok_or_else(|| ParamsError::UnknownKey(key.to_string()))?;
+                    evaluator = evaluator.with_weight(term, parse_i32(value)?);
+                }
+                _ => return Err(ParamsError::UnknownKey(key.to_string())),
+            }
+        }
+
+        match version {
+            Some(PARAMS_VERSION) => (),
+            Some(version) => return Err(ParamsError::UnsupportedVersion(version)),
+            None => return Err(ParamsError::MissingVersion),
+        }
+        // Searches take any score at or above the win score as a forced win
+        if i64::from(evaluator.win_score()) <= evaluator.max_evaluation() {
+            return Err(ParamsError::WinScoreTooLow(evaluator.win_score(), evaluator.max_evaluation()));
+        }
+        Ok(evaluator)
+    }
+
+    pub fn to_params_string(&self) -> String {
+        let mut out = format!("version = {PARAMS_VERSION}\nwin_score = {}\n", self.win_score());
+        out.push_str("piece_square_table = [\n");
+        for row in self.piece_square_table().chunks(5) {
+            let row: Vec<String> = row.iter().map(|n| n.to_string()).collect();
+            out.push_str(&format!("    {},\n", row.join(", ")));
+        }
+        out.push_str("]\n\n[weights]\n");
+        for term in EvalTerm::iter().filter(|term| self.is_enabled(*term)) {
+            out.push_str(&format!("{} = {}\n", term.key(), self.weight(term)));
+        }
+        out
+    }
+}
+
+#[derive(Debug)]
+pub enum ParamsError {
+    Io(std::io::Error),
+    Syntax(usize),
+    UnknownKey(String),
+    MissingVersion,
+    UnsupportedVersion(i64),
+    /// The win score and the largest evaluation of an unfinished position it must be above
+    WinScoreTooLow(i32, i64),
+}
+
+impl std::fmt::Display for ParamsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed to access parameter file: {err}"),
+            Self::Syntax(line) => write!(f, "syntax error on line {line}"),
+            Self::UnknownKey(key) => write!(f, "unknown key '{key}'"),
+            Self::MissingVersion => write!(f, "parameter file has no version"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported parameter file version {version}")
+            }
+            Self::WinScoreTooLow(win_score, max) => {
+                write!(f, "win score {win_score} is not above the largest evaluation {max}")
+            }
+        }
+    }
+}
@@ -0,0 +1,199 @@
+//! Records of games played between bots, with the players, time taken per move and result, one game per line
+//!
+//! Fields are separated by tabs: the start position, red's player, blue's player, the time limit per move in
+//! milliseconds (`-` if the players weren't limited by time), the result, the whole game including the moves of
+//! the start position and the milliseconds each move from the start position took, separated by commas (`-` if
+//! not measured), optionally followed by the reason for a forfeit. Positions and games are in
+//! .oni format. A line holding only a start position is a game yet to be played, so plain lists of positions
+//! like the arena's pregens file load as well.
+//! Empty lines and lines starting with `#` are ignored.
+use std::io::Write;
+
+use super::*;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameRecord {
+    /// Position the game started from, in .oni format
+    pub start: String,
+    pub red: String,
+    pub blue: String,
+    /// Time limit per move, `None` if the players weren't limited by time, such as deterministic bots
+    pub time_limit: Option<Duration>,
+    pub result: GameStatus,
+    /// The game as played in .oni format, including the moves of the start position
+    pub game: String,
+    /// How long each move from the start position took, empty if not measured
+    pub move_times: Vec<Duration>,
+    /// Why the loser forfeited, if the game wasn't decided on the board
+    pub forfeit: Option<String>,
+}
+impl GameRecord {
+    /// A game yet to be played from the given start position
+    pub fn unplayed(start: String) -> Self {
+        Self {
+            game: start.clone(),
+            start,
+            red: String::new(),
+            blue: String::new(),
+            time_limit: None,
+            result: GameStatus::Playing,
+            move_times: vec![],
+            forfeit: None,
+        }
+    }
+    /// Whether any moves have been played from the start position, or the game was forfeited
+    pub fn is_played(&self) -> bool {
+        self.game != self.start || self.forfeit.is_some()
+    }
+
+    pub fn to_line(&self) -> String {
+        if !self.is_played() {
+            return self.start.clone();
+        }
+        let time_limit = match self.time_limit {
+            Some(time) => time.as_millis().to_string(),
+            None => "-".to_string(),
+        };
+        let move_times = match self.move_times.is_empty() {
+            true => "-".to_string(),
+            false => self.move_times.iter().map(|time| time.as_millis().to_string()).collect::<Vec<_>>().join(","),
+        };
+        let result = match self.result {
+            GameStatus::Playing => "unfinished",
+            GameStatus::Stalemate => "stalemate",
+            GameStatus::RedWon => "red won",
+            GameStatus::BlueWon => "blue won",
+        };
+        // Tabs separate the fields, so they can't be part of a name
+        let mut fields = vec![
+            self.start.clone(),
+            self.red.replace('\t', " "),
+            self.blue.replace('\t', " "),
+            time_limit,
+            result.to_string(),
+            self.game.clone(),
+            move_times,
+        ];
+        fields.extend(self.forfeit.as_ref().map(|reason| reason.replace(['\t', '\n'], " ")));
+        fields.join("\t")
+    }
+
+    /// Parses a line written by `to_line`, checking that the start position and game load, the game continues
+    /// from the start position and there's a move time for every move if any
+    pub fn from_line(line: &str) -> Result<Self, RecordError> {
+        let mut fields: Vec<&str> = line.trim().split('\t').map(str::trim).collect();
+        let forfeit = match fields.len() {
+            8 => fields.pop().map(str::to_string),
+            _ => None,
+        };
+        let record = match fields[..] {
+            [start] => Self::unplayed(start.to_string()),
+            [start, red, blue, time_limit, result, game, move_times] => Self {
+                start: start.to_string(),
+                red: red.to_string(),
+                blue: blue.to_string(),
+                time_limit: match time_limit {
+                    "-" => None,
+                    _ => Some(parse_millis(time_limit)?),
+                },
+                result: match result {
+                    "unfinished" => GameStatus::Playing,
+                    "stalemate" => GameStatus::Stalemate,
+                    "red won" => GameStatus::RedWon,
+                    "blue won" => GameStatus::BlueWon,
+                    _ => return Err(RecordError::Syntax),
+                },
+                game: game.to_string(),
+                move_times: match move_times {
+                    "-" => vec![],
+                    _ => move_times.split(',').map(parse_millis).collect::<Result<_, _>>()?,
+                },
+                forfeit,
+            },
+            _ => return Err(RecordError::Syntax),
+        };
+        let start = Board::load_game(&record.start).map_err(RecordError::Game)?;
+        let mut game = Board::load_game(&record.game).map_err(RecordError::Game)?;
+        let moves = start.game_length();
+        if game.game_length() < moves || game.move_history()[..moves] != *start.move_history() {
+            return Err(RecordError::NotContinued);
+        }
+        let played = game.game_length() - moves;
+        while game.game_length() > moves {
+            game.undo_move();
+        }
+        if !game.is_same_board(&start) {
+            return Err(RecordError::NotContinued);
+        }
+        if !record.move_times.is_empty() && record.move_times.len() != played {
+            return Err(RecordError::Syntax);
+        }
+        Ok(record)
+    }
+
+    /// Board at the start position
+    pub fn start_board(&self) -> Board {
+        Board::load_game(&self.start).expect("record holds a valid start position")
+    }
+    /// Board at the end of the game
+    pub fn game_board(&self) -> Board {
+        Board::load_game(&self.game).expect("record holds a valid game")
+    }
+}
+
+fn parse_millis(text: &str) -> Result<Duration, RecordError> {
+    text.trim().parse().map(Duration::from_millis).map_err(|_| RecordError::Syntax)
+}
+
+pub fn load_records(path: impl AsRef<std::path::Path>) -> Result<Vec<GameRecord>, LoadRecordsError> {
+    let text = std::fs::read_to_string(path).map_err(LoadRecordsError::Io)?;
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(i, line)| GameRecord::from_line(line).map_err(|err| LoadRecordsError::Record(i + 1, err)))
+        .collect()
+}
+
+pub fn save_records(path: impl AsRef<std::path::Path>, records: &[GameRecord]) -> std::io::Result<()> {
+    let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+    for record in records {
+        writeln!(writer, "{}", record.to_line())?;
+    }
+    writer.flush()
+}
+
+#[derive(Debug)]
+pub enum RecordError {
+    /// Wrong number of fields, an unknown time or result, or a move time missing for some moves
+    Syntax,
+    /// The start position or game doesn't load
+    Game(LoadGameError),
+    /// The game doesn't start from the start position
+    NotContinued,
+}
+
+impl std::fmt::Display for RecordError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Syntax => write!(f, "malformed game record"),
+            Self::Game(err) => write!(f, "invalid game: {err}"),
+            Self::NotContinued => write!(f, "game doesn't continue from the start position"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum LoadRecordsError {
+    Io(std::io::Error),
+    /// Invalid record on the given line
+    Record(usize, RecordError),
+}
+
+impl std::fmt::Display for LoadRecordsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed to read game records: {err}"),
+            Self::Record(line, err) => write!(f, "line {line}: {err}"),
+        }
+    }
+}
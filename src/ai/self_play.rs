@@ -0,0 +1,186 @@
+//! Self-play games for generating training data
+//!
+//! `MinMaxV4` plays both sides, optionally picking among its root moves by a softmax over their scores for the
+//! first plies so the games don't all follow the same line. Every position is recorded with its search score,
+//! the move played and the final result, all from the perspective of the player to move.
+//!
+//! Records are stored one per line as flat JSON objects, for example:
+//! ```json
+//! {"position":"11311...............00200","cards":"BCQKD","red_to_move":true,"ply":0,"score":12,"move":"Bvq","result":1}
+//! ```
+//! `position` uses the board characters of the .oni format, and `result` is 1 for a win, 0 for a draw and -1
+//! for a loss.
+use std::io::Write;
+
+use crate::cards;
+
+use super::difficulty::pick_with_temperature;
+use super::*;
+
+#[derive(Clone, Debug)]
+pub struct SelfPlayConfig {
+    pub depth: u32,
+    pub node_limit: Option<u64>,
+    pub evaluator: Evaluator,
+    /// Softmax temperature over root move scores, in evaluation units. Zero always plays the best move
+    pub temperature: f64,
+    /// Only the first this many plies are played with temperature
+    pub temperature_plies: usize,
+    /// Games that haven't ended after this many plies are counted as draws
+    pub max_plies: usize,
+}
+impl Default for SelfPlayConfig {
+    fn default() -> Self {
+        Self {
+            depth: 6,
+            node_limit: None,
+            evaluator: Evaluator::default(),
+            temperature: 0.0,
+            temperature_plies: 8,
+            max_plies: 200,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PositionRecord {
+    /// The 25 squares in .oni notation, blue's side first
+    pub position: String,
+    /// Card identifiers in .oni order: red's two cards, blue's two cards and the transfer card
+    pub cards: String,
+    pub red_to_move: bool,
+    /// Plies played since the start of the game, including the moves made before self-play started
+    pub ply: usize,
+    /// Search score for the player to move
+    pub score: i32,
+    /// Move played, in .oni move notation
+    pub game_move: String,
+    /// Result for the player to move, 1 for a win, 0 for a draw and -1 for a loss
+    pub result: i8,
+}
+impl PositionRecord {
+    pub fn to_json(&self) -> String {
+        format!(
+            r#"{{"position":"{}","cards":"{}","red_to_move":{},"ply":{},"score":{},"move":"{}","result":{}}}"#,
+            self.position, self.cards, self.red_to_move, self.ply, self.score, self.game_move, self.result
+        )
+    }
+
+    /// Parses a line written by `to_json`, returning `None` if any field is missing or malformed
+    pub fn from_json(line: &str) -> Option<Self> {
+        let body = line.trim().strip_prefix('{')?.strip_suffix('}')?;
+        let mut fields = std::collections::HashMap::new();
+        // None of the values contain commas or colons, so a plain split is enough
+        for field in body.split(',') {
+            let (key, value) = field.split_once(':')?;
+            fields.insert(key.trim().trim_matches('"'), value.trim());
+        }
+        let string = |key: &str| -> Option<String> {
+            Some(fields.get(key)?.strip_prefix('"')?.strip_suffix('"')?.to_string())
+        };
+        let record = Self {
+            position: string("position")?,
+            cards: string("cards")?,
+            red_to_move: fields.get("red_to_move")?.parse().ok()?,
+            ply: fields.get("ply")?.parse().ok()?,
+            score: fields.get("score")?.parse().ok()?,
+            game_move: string("move")?,
+            result: fields.get("result")?.parse().ok()?,
+        };
+        let valid = record.position.len() == 25
+            && record.position.bytes().all(|c| b".0123".contains(&c))
+            && record.cards.len() == 5
+            && record.cards.bytes().all(|c| cards::ALL_CARDS_IDENT.contains(&c))
+            && (-1..=1).contains(&record.result);
+        valid.then_some(record)
+    }
+}
+
+/// Board characters of the .oni format, blue's side first
+pub fn position_string(board: &Board) -> String {
+    board
+        .squares()
+        .iter()
+        .map(|square| match square {
+            None => '.',
+            Some(Piece::RedDisciple) => '0',
+            Some(Piece::BlueDisciple) => '1',
+            Some(Piece::RedSensei) => '2',
+            Some(Piece::BlueSensei) => '3',
+        })
+        .collect()
+}
+
+/// Plays a game against itself from `start`, returning a record of every position where a move was searched
+pub fn play_game(config: &SelfPlayConfig, start: Board, seed: u64) -> Vec<PositionRecord> {
+    let mut engine = MinMaxV4::new(config.depth).with_evaluator(config.evaluator.clone());
+    if let Some(node_limit) = config.node_limit {
+        engine = engine.with_node_limit(node_limit);
+    }
+    let mut rng = StdRand::seed(seed);
+    let mut board = start;
+    let mut records = vec![];
+    let mut played = 0;
+    while !board.finished() && played < config.max_plies && !board.legal_moves().is_empty() {
+        let (result, root_scores) = engine.search_root_scores(Arc::new(AtomicBool::new(false)), board.clone());
+        let mut game_move = result.best_move;
+        let mut score = result.score.unwrap_or(0);
+        if played < config.temperature_plies && config.temperature > 0.0 && !root_scores.is_empty() {
+            (game_move, score) = root_scores[pick_with_temperature(&root_scores, config.temperature, &mut rng)].clone();
+        }
+        records.push(PositionRecord {
+            position: position_string(&board),
+            cards: board.cards().iter().map(|card| cards::card_identifier(card) as char).collect(),
+            red_to_move: board.red_to_move(),
+            ply: board.game_length(),
+            score,
+            game_move: String::from_utf8_lossy(&game_move.as_encoded_bytes()).into_owned(),
+            result: 0,
+        });
+        board.make_move_unchecked(game_move);
+        played += 1;
+    }
+    let red_result = match board.status() {
+        GameStatus::RedWon => 1,
+        GameStatus::BlueWon => -1,
+        _ => 0,
+    };
+    for record in &mut records {
+        record.result = if record.red_to_move { red_result } else { -red_result };
+    }
+    records
+}
+
+/// Appends records to a shard file, one JSON object per line
+pub fn write_shard(writer: &mut impl Write, records: &[PositionRecord]) -> std::io::Result<()> {
+    for record in records {
+        writeln!(writer, "{}", record.to_json())?;
+    }
+    Ok(())
+}
+
+/// Reads every record of a shard file written by `write_shard`
+pub fn load_shard(path: impl AsRef<std::path::Path>) -> Result<Vec<PositionRecord>, ShardError> {
+    let text = std::fs::read_to_string(path).map_err(ShardError::Io)?;
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| PositionRecord::from_json(line).ok_or(ShardError::Syntax(i + 1)))
+        .collect()
+}
+
+#[derive(Debug)]
+pub enum ShardError {
+    Io(std::io::Error),
+    /// Malformed record on the given line
+    Syntax(usize),
+}
+
+impl std::fmt::Display for ShardError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed to read shard: {err}"),
+            Self::Syntax(line) => write!(f, "malformed record on line {line}"),
+        }
+    }
+}
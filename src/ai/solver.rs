@@ -0,0 +1,333 @@
+//! Solves the game from a starting position, proving forced wins and losses with a hashed negamax
+//!
+//! The search is iteratively deepened, iteration `d` proves whether either side can force a win within `d`
+//! plies, so the first win found for the player to move at the start is also the shortest. Proven results are
+//! kept in a table between iterations, and can be saved as a checkpoint to resume long runs.
+//!
+//! Only the start position's win distance is minimal. Below it a win is proven by the first winning move found,
+//! not the quickest, so stored distances and the distance of a loss at the start are upper bounds: the winner
+//! can force the win within that many plies, maybe sooner. Draws are never proven, a position neither side can
+//! win stays `Outcome::Unknown` however deep the search goes.
+//!
+//! A repetition ends the game in a stalemate, so results found below a repeated position depend on the line
+//! leading to it, and those are never stored in the table. Stored results are still looked up by position
+//! alone, so proofs assume no repetition outside the line they were searched along: reached by another line,
+//! the losing side may be able to repeat one of that line's earlier positions and escape into a stalemate,
+//! which a stored result doesn't account for. Outcomes relying on such a transposition can be wrong.
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+    path::PathBuf,
+    time::Instant,
+};
+
+use super::*;
+
+const CHECKPOINT_MAGIC: &[u8; 8] = b"ONISOLV1";
+// How often, in nodes, the search checks whether a checkpoint is due
+const CHECKPOINT_CHECK_NODES: u64 = 1 << 16;
+
+/// Game-theoretic value of a position for the player to move, distances are in plies
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// The shortest win when found for the start position, see the module documentation
+    Win(u32),
+    /// The opponent wins within this many plies, but maybe sooner with best play
+    Loss(u32),
+    /// Neither side can force a win within the searched depth. Drawn positions are always unknown
+    Unknown,
+}
+
+#[derive(Clone, Copy)]
+enum Entry {
+    /// Win in n plies if positive, loss in -n plies if negative
+    Proven(i32),
+    /// No forced result within this many plies
+    Unknown(u32),
+}
+
+#[derive(Clone, Debug)]
+pub struct SolveResult {
+    pub outcome: Outcome,
+    /// Shortest win, or longest loss. `None` if the outcome is unknown
+    pub best_move: Option<GameMove>,
+    /// Deepest completed iteration
+    pub depth: u32,
+    /// Positions searched in total, including before resuming from a checkpoint
+    pub nodes: u64,
+}
+
+pub struct Solver {
+    start: Board,
+    table: HashMap<u64, Entry>,
+    completed_depth: u32,
+    nodes: u64,
+    checkpoint: Option<(PathBuf, Duration)>,
+    last_checkpoint: Instant,
+}
+impl Solver {
+    pub fn new(start: Board) -> Self {
+        Self {
+            start,
+            table: HashMap::new(),
+            completed_depth: 0,
+            nodes: 0,
+            checkpoint: None,
+            last_checkpoint: Instant::now(),
+        }
+    }
+    /// Saves a checkpoint to `path` after every iteration, and during iterations at least every `interval`
+    pub fn with_checkpoint(mut self, path: impl Into<PathBuf>, interval: Duration) -> Self {
+        self.checkpoint = Some((path.into(), interval));
+        self
+    }
+
+    pub fn start(&self) -> &Board {
+        &self.start
+    }
+    pub fn completed_depth(&self) -> u32 {
+        self.completed_depth
+    }
+    /// Number of proven positions in the table
+    pub fn num_proven(&self) -> usize {
+        self.table.values().filter(|entry| matches!(entry, Entry::Proven(_))).count()
+    }
+
+    /// Deepens the search until a win or loss is proven for the start position, `max_depth` is reached or the
+    /// search is cancelled. A drawn start position searches until `max_depth` and ends as `Outcome::Unknown`
+    /// `progress` is called after every completed iteration
+    pub fn solve(
+        &mut self,
+        max_depth: u32,
+        cancel_signal: &AtomicBool,
+        mut progress: impl FnMut(&SolveResult),
+    ) -> Result<SolveResult, SolverError> {
+        let mut result = SolveResult {
+            outcome: Outcome::Unknown,
+            best_move: None,
+            depth: self.completed_depth,
+            nodes: self.nodes,
+        };
+        for depth in self.completed_depth + 1..=max_depth {
+            let Some((outcome, best_move)) = self.search_root(depth, cancel_signal)? else {
+                break;
+            };
+            self.completed_depth = depth;
+            result = SolveResult {
+                outcome,
+                best_move,
+                depth,
+                nodes: self.nodes,
+            };
+            self.save_due_checkpoint(true)?;
+            progress(&result);
+            if outcome != Outcome::Unknown {
+                break;
+            }
+        }
+        Ok(result)
+    }
+
+    /// Returns `None` if cancelled before the iteration completed
+    fn search_root(
+        &mut self,
+        depth: u32,
+        cancel_signal: &AtomicBool,
+    ) -> Result<Option<(Outcome, Option<GameMove>)>, SolverError> {
+        let mut board = self.start.clone();
+        let mut longest_loss = None;
+        let mut all_lost = true;
+        for game_move in ordered_moves(&board) {
+            board.make_move_unchecked(game_move.clone());
+            let (child, _) = self.search(&mut board, depth - 1, cancel_signal)?;
+            board.undo_move();
+            if cancel_signal.load(Ordering::Relaxed) {
+                return Ok(None);
+            }
+            match child {
+                Outcome::Loss(plies) => return Ok(Some((Outcome::Win(plies + 1), Some(game_move)))),
+                Outcome::Win(plies) => {
+                    if longest_loss.as_ref().is_none_or(|(longest, _)| plies > *longest) {
+                        longest_loss = Some((plies, game_move));
+                    }
+                }
+                Outcome::Unknown => all_lost = false,
+            }
+        }
+        Ok(Some(match longest_loss {
+            Some((plies, game_move)) if all_lost => (Outcome::Loss(plies + 1), Some(game_move)),
+            _ => (Outcome::Unknown, None),
+        }))
+    }
+
+    /// Also returns whether a repetition was found in the subtree, making the outcome depend on the line played
+    fn search(
+        &mut self,
+        board: &mut Board,
+        depth: u32,
+        cancel_signal: &AtomicBool,
+    ) -> Result<(Outcome, bool), SolverError> {
+        self.nodes += 1;
+        if self.nodes.is_multiple_of(CHECKPOINT_CHECK_NODES) {
+            self.save_due_checkpoint(false)?;
+        }
+        match board.status() {
+            // The previous player won
+            GameStatus::RedWon | GameStatus::BlueWon => return Ok((Outcome::Loss(0), false)),
+            GameStatus::Stalemate => return Ok((Outcome::Unknown, true)),
+            GameStatus::Playing => (),
+        }
+        if depth == 0 || cancel_signal.load(Ordering::Relaxed) {
+            return Ok((Outcome::Unknown, false));
+        }
+
+        let hash = board.state_hash();
+        match self.table.get(&hash) {
+            Some(Entry::Proven(plies)) if plies.unsigned_abs() <= depth => {
+                let outcome = if *plies > 0 { Outcome::Win(*plies as u32) } else { Outcome::Loss(plies.unsigned_abs()) };
+                return Ok((outcome, false));
+            }
+            Some(Entry::Unknown(searched)) if *searched >= depth => return Ok((Outcome::Unknown, false)),
+            _ => (),
+        }
+
+        let mut repetition = false;
+        let mut all_lost = true;
+        let mut longest_loss = 0;
+        let mut outcome = None;
+        for game_move in ordered_moves(board) {
+            board.make_move_unchecked(game_move);
+            let (child, child_repetition) = self.search(board, depth - 1, cancel_signal)?;
+            board.undo_move();
+            repetition |= child_repetition;
+            match child {
+                Outcome::Loss(plies) => {
+                    outcome = Some(Outcome::Win(plies + 1));
+                    break;
+                }
+                Outcome::Win(plies) => longest_loss = longest_loss.max(plies + 1),
+                Outcome::Unknown => all_lost = false,
+            }
+        }
+        // No legal moves is not a loss, the game simply can't go on
+        let outcome = outcome.unwrap_or(if all_lost && longest_loss > 0 {
+            Outcome::Loss(longest_loss)
+        } else {
+            Outcome::Unknown
+        });
+
+        if cancel_signal.load(Ordering::Relaxed) && outcome == Outcome::Unknown {
+            // Incomplete, don't store
+            return Ok((outcome, true));
+        }
+        // Only repetitions within this line are known about, see the module documentation
+        if !repetition {
+            let entry = match outcome {
+                Outcome::Win(plies) => Entry::Proven(plies as i32),
+                Outcome::Loss(plies) => Entry::Proven(-(plies as i32)),
+                Outcome::Unknown => Entry::Unknown(depth),
+            };
+            self.table.insert(hash, entry);
+        }
+        Ok((outcome, repetition))
+    }
+
+    fn save_due_checkpoint(&mut self, force: bool) -> Result<(), SolverError> {
+        let Some((path, interval)) = &self.checkpoint else {
+            return Ok(());
+        };
+        if force || self.last_checkpoint.elapsed() >= *interval {
+            self.save_checkpoint(path)?;
+            self.last_checkpoint = Instant::now();
+        }
+        Ok(())
+    }
+
+    /// Saves the start position, progress and every proven position, enough to resume with `Solver::resume`
+    pub fn save_checkpoint(&self, path: impl AsRef<std::path::Path>) -> Result<(), SolverError> {
+        // Written to a temporary file first, so an interruption while saving doesn't lose the previous checkpoint
+        let path = path.as_ref();
+        let temp_path = path.with_extension("tmp");
+        let file = std::fs::File::create(&temp_path).map_err(SolverError::Io)?;
+        let mut file = std::io::BufWriter::new(file);
+        let start = self.start.save_game(false);
+        let mut header = CHECKPOINT_MAGIC.to_vec();
+        header.extend((start.len() as u16).to_le_bytes());
+        header.extend(start.as_bytes());
+        header.extend(self.completed_depth.to_le_bytes());
+        header.extend(self.nodes.to_le_bytes());
+        file.write_all(&header).map_err(SolverError::Io)?;
+        for (hash, entry) in &self.table {
+            if let Entry::Proven(plies) = entry {
+                let mut record = hash.to_le_bytes().to_vec();
+                record.extend(plies.to_le_bytes());
+                file.write_all(&record).map_err(SolverError::Io)?;
+            }
+        }
+        file.flush().map_err(SolverError::Io)?;
+        drop(file);
+        std::fs::rename(&temp_path, path).map_err(SolverError::Io)
+    }
+
+    /// Loads a checkpoint saved by `Solver::save_checkpoint`, continuing from the iteration after the last one saved
+    pub fn resume(path: impl AsRef<std::path::Path>) -> Result<Self, SolverError> {
+        let mut file = std::io::BufReader::new(std::fs::File::open(path).map_err(SolverError::Io)?);
+        let mut magic = [0; 8];
+        file.read_exact(&mut magic).map_err(SolverError::Io)?;
+        if &magic != CHECKPOINT_MAGIC {
+            return Err(SolverError::Format);
+        }
+        let mut length = [0; 2];
+        file.read_exact(&mut length).map_err(SolverError::Io)?;
+        let mut start = vec![0; u16::from_le_bytes(length) as usize];
+        file.read_exact(&mut start).map_err(SolverError::Io)?;
+        let start = String::from_utf8(start).map_err(|_| SolverError::Format)?;
+        let start = Board::load_game(&start).map_err(|_| SolverError::Format)?;
+
+        let mut progress = [0; 12];
+        file.read_exact(&mut progress).map_err(SolverError::Io)?;
+        let mut solver = Self::new(start);
+        solver.completed_depth = u32::from_le_bytes(progress[0..4].try_into().unwrap());
+        solver.nodes = u64::from_le_bytes(progress[4..12].try_into().unwrap());
+
+        let mut record = [0; 12];
+        loop {
+            match file.read_exact(&mut record) {
+                Ok(()) => (),
+                Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(err) => return Err(SolverError::Io(err)),
+            }
+            let hash = u64::from_le_bytes(record[0..8].try_into().unwrap());
+            let plies = i32::from_le_bytes(record[8..12].try_into().unwrap());
+            solver.table.insert(hash, Entry::Proven(plies));
+        }
+        Ok(solver)
+    }
+}
+
+/// Winning moves first, then captures, to find proofs quickly
+fn ordered_moves(board: &Board) -> Vec<GameMove> {
+    let mut moves = board.legal_moves();
+    let goal = if board.red_to_move() { Pos(0, 2) } else { Pos(4, 2) };
+    moves.sort_by_key(|game_move| {
+        let wins = matches!(game_move.captured_piece, Some(Piece::RedSensei | Piece::BlueSensei))
+            || (matches!(game_move.moved_piece, Piece::RedSensei | Piece::BlueSensei) && game_move.end_pos == goal);
+        (!wins, game_move.captured_piece.is_none())
+    });
+    moves
+}
+
+#[derive(Debug)]
+pub enum SolverError {
+    Io(std::io::Error),
+    Format,
+}
+
+impl std::fmt::Display for SolverError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed to access checkpoint file: {err}"),
+            Self::Format => write!(f, "not a valid checkpoint file"),
+        }
+    }
+}
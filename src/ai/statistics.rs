@@ -0,0 +1,229 @@
+//! Statistics for matches between two bots: Elo difference, likelihood of superiority and the sequential
+//! probability ratio test (SPRT)
+//!
+//! All values are from the perspective of the first bot. Error bars are 95% confidence intervals, using the
+//! normal approximation of the mean score over wins, draws and losses.
+//! The SPRT uses the same approximation, testing whether the Elo difference is `elo0` (the null hypothesis)
+//! or `elo1` (the alternative), like the generalized SPRT used by chess engine testing frameworks.
+//! Ratings of more than two bots are fitted to all their results at once, see `fit_ratings`.
+
+// Standard normal quantile for a two-sided 95% interval
+const CONFIDENCE_Z: f64 = 1.959964;
+const RATING_ITERATIONS: usize = 10_000;
+
+/// Wins, losses and draws of one bot against another
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Score {
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+}
+impl Score {
+    pub fn new(wins: u32, losses: u32, draws: u32) -> Self {
+        Self { wins, losses, draws }
+    }
+    /// Adds one game, `Some(true)` for a win, `Some(false)` for a loss and `None` for a draw
+    pub fn add(&mut self, won: Option<bool>) {
+        match won {
+            Some(true) => self.wins += 1,
+            Some(false) => self.losses += 1,
+            None => self.draws += 1,
+        }
+    }
+    /// The same games seen from the other bot
+    pub fn reversed(&self) -> Self {
+        Self::new(self.losses, self.wins, self.draws)
+    }
+    pub fn games(&self) -> u32 {
+        self.wins + self.losses + self.draws
+    }
+
+    /// Mean points per game, with draws as half a point, 0.5 before any games are played
+    pub fn score(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.5;
+        }
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games() as f64
+    }
+
+    /// Variance of the mean score
+    fn variance(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.0;
+        }
+        let games = self.games() as f64;
+        let (win_rate, draw_rate) = (self.wins as f64 / games, self.draws as f64 / games);
+        let score = self.score();
+        (win_rate + draw_rate / 4.0 - score * score) / games
+    }
+
+    /// Elo difference with its 95% error bar, infinite if every game was won or lost
+    pub fn elo(&self) -> Elo {
+        let deviation = CONFIDENCE_Z * self.variance().sqrt();
+        let (lower, upper) = (elo_from_score(self.score() - deviation), elo_from_score(self.score() + deviation));
+        Elo {
+            difference: elo_from_score(self.score()),
+            error: (upper - lower) / 2.0,
+        }
+    }
+
+    /// Likelihood of superiority, the chance the bot is the stronger one given its wins and losses
+    /// Draws say nothing about which is stronger, so they are left out
+    pub fn los(&self) -> f64 {
+        let decisive = (self.wins + self.losses) as f64;
+        if decisive == 0.0 {
+            return 0.5;
+        }
+        0.5 * (1.0 + erf((self.wins as f64 - self.losses as f64) / (2.0 * decisive).sqrt()))
+    }
+}
+impl std::fmt::Display for Score {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "+{} -{} ={}", self.wins, self.losses, self.draws)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Elo {
+    pub difference: f64,
+    /// Half the width of the 95% confidence interval
+    pub error: f64,
+}
+impl std::fmt::Display for Elo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.difference.is_finite() && self.error.is_finite() {
+            write!(f, "{:+.1} ± {:.1}", self.difference, self.error)
+        } else {
+            write!(f, "{:+}", self.difference)
+        }
+    }
+}
+
+/// Elo difference giving the expected score, infinite for scores of 0 and 1
+pub fn elo_from_score(score: f64) -> f64 {
+    let score = score.clamp(0.0, 1.0);
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+/// Expected score against a bot rated `difference` Elo lower
+pub fn score_from_elo(difference: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-difference / 400.0))
+}
+
+/// Maximum likelihood Elo ratings from the scores between every pair of players
+/// `scores[a][b]` is the score of player `a` against player `b`. Draws count as half a win and half a loss,
+/// and every pair that has played gets one extra virtual draw, so players who won or lost every game still
+/// get finite ratings. Players without games are rated zero, and the rest average zero.
+pub fn fit_ratings(scores: &[Vec<Score>]) -> Vec<f64> {
+    let num_players = scores.len();
+    // Bradley-Terry strengths, 10^(rating / 400), fitted by minorization-maximization
+    let mut strengths = vec![1.0; num_players];
+    for _ in 0..RATING_ITERATIONS {
+        let mut largest_change: f64 = 0.0;
+        for player in 0..num_players {
+            let (mut points, mut denominator) = (0.0, 0.0);
+            for opponent in (0..num_players).filter(|&opponent| opponent != player) {
+                let score = &scores[player][opponent];
+                if score.games() == 0 {
+                    continue;
+                }
+                points += score.wins as f64 + score.draws as f64 / 2.0 + 0.5;
+                denominator += (score.games() + 1) as f64 / (strengths[player] + strengths[opponent]);
+            }
+            if denominator > 0.0 {
+                let strength = points / denominator;
+                largest_change = largest_change.max((strength / strengths[player]).ln().abs());
+                strengths[player] = strength;
+            }
+        }
+        if largest_change < 1e-10 {
+            break;
+        }
+    }
+    let played: Vec<bool> = scores.iter().map(|row| row.iter().any(|score| score.games() > 0)).collect();
+    let ratings: Vec<f64> = strengths.iter().map(|strength| 400.0 * strength.log10()).collect();
+    let num_rated = played.iter().filter(|&&played| played).count();
+    let mean = ratings.iter().zip(&played).filter(|(_, played)| **played).map(|(rating, _)| rating).sum::<f64>()
+        / num_rated.max(1) as f64;
+    ratings.iter().zip(&played).map(|(rating, &played)| if played { rating - mean } else { 0.0 }).collect()
+}
+
+/// Sequential probability ratio test between two Elo differences
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sprt {
+    /// Elo difference of the null hypothesis, usually 0 for "no better"
+    pub elo0: f64,
+    /// Elo difference of the alternative hypothesis, the smallest gain worth detecting
+    pub elo1: f64,
+    /// Chance of accepting `elo1` when `elo0` is true
+    pub alpha: f64,
+    /// Chance of accepting `elo0` when `elo1` is true
+    pub beta: f64,
+}
+impl Sprt {
+    /// Test with 5% error rates both ways
+    pub fn new(elo0: f64, elo1: f64) -> Self {
+        Self {
+            elo0,
+            elo1,
+            alpha: 0.05,
+            beta: 0.05,
+        }
+    }
+    pub fn with_error_rates(mut self, alpha: f64, beta: f64) -> Self {
+        self.alpha = alpha;
+        self.beta = beta;
+        self
+    }
+
+    /// Lower and upper bound of the log-likelihood ratio, the test ends once it falls outside them
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    /// Log-likelihood ratio of `elo1` against `elo0`, zero while every game has had the same result
+    pub fn llr(&self, score: &Score) -> f64 {
+        let variance = score.variance();
+        if variance <= 0.0 {
+            return 0.0;
+        }
+        let (score0, score1) = (score_from_elo(self.elo0), score_from_elo(self.elo1));
+        (score1 - score0) * (2.0 * score.score() - score0 - score1) / (2.0 * variance)
+    }
+
+    pub fn test(&self, score: &Score) -> SprtResult {
+        let llr = self.llr(score);
+        let (lower, upper) = self.bounds();
+        if llr >= upper {
+            SprtResult::AcceptH1
+        } else if llr <= lower {
+            SprtResult::AcceptH0
+        } else {
+            SprtResult::Continue
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, strum::Display)]
+pub enum SprtResult {
+    /// Not enough games to decide yet
+    #[strum(to_string = "undecided")]
+    Continue,
+    /// The difference is at most `elo0`, the bot is not better
+    #[strum(to_string = "H0 accepted")]
+    AcceptH0,
+    /// The difference is at least `elo1`, the bot is better
+    #[strum(to_string = "H1 accepted")]
+    AcceptH1,
+}
+
+/// Error function, Abramowitz and Stegun formula 7.1.26, accurate to about 1e-7
+fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.3275911 * x.abs());
+    let polynomial = t * (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let value = 1.0 - polynomial * (-x * x).exp();
+    if x < 0.0 { -value } else { value }
+}
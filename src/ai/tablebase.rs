@@ -0,0 +1,547 @@
+//! Endgame tablebases for a fixed set of five cards, generated by retrograde analysis
+//!
+//! Every position with both senseis and at most `max_pieces` pieces in total is given an exact value: a win or
+//! loss for the player to move in a number of plies, or a draw. Positions are split into tables by the number
+//! of disciples on each side, so captures always lead into a smaller, already solved table.
+//!
+//! Positions are indexed by side to move, distribution of the cards, both sensei squares, and the
+//! combinations of squares holding red and blue disciples. Values are found by repeated sweeps: sweep `n`
+//! finds every position won in `n` plies (a move into a position lost in `n - 1`) and every position lost in
+//! `n` plies (all moves lead into positions won in at most `n - 1`, at least one in exactly `n - 1`).
+//! Whatever is left undecided can be held forever by both sides, and is a draw. Distances are stored in 16 bits,
+//! generation panics rather than store a position it couldn't resolve within `i16::MAX` plies as a draw.
+//!
+//! Repetitions are not considered, so values assume the game can't end by repeating a position. Following the
+//! shortest win never repeats a position, so wins and losses are still accurate in practice.
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+};
+
+use crate::{
+    cards::{self, Card},
+    game::{Board, GameMove, GameStatus, Piece, Pos},
+};
+
+const FILE_MAGIC: &[u8; 8] = b"ONITB\x00\x00\x02";
+// Squares the senseis need to reach to win, blue's temple for red and vice versa
+const RED_GOAL: usize = 2;
+const BLUE_GOAL: usize = 22;
+const NUM_CARD_STATES: usize = 30;
+
+/// Value of a position for the player to move, distances are in plies
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TablebaseValue {
+    Win(u16),
+    Loss(u16),
+    Draw,
+}
+impl TablebaseValue {
+    fn from_stored(value: i16) -> Self {
+        match value {
+            0 => Self::Draw,
+            1.. => Self::Win(value as u16),
+            _ => Self::Loss(value.unsigned_abs()),
+        }
+    }
+}
+
+/// Which of the five cards each side holds, as indices into the tablebase's card set
+#[derive(Clone, Copy, Debug)]
+struct CardState {
+    red: [u8; 2],
+    blue: [u8; 2],
+    transfer: u8,
+}
+
+/// Position restricted to what matters for the tablebase, disciples are stored as bitmasks over the squares
+#[derive(Clone, Copy, Debug)]
+struct Position {
+    red_to_move: bool,
+    cards: u8,
+    red_sensei: u8,
+    blue_sensei: u8,
+    red_disciples: u32,
+    blue_disciples: u32,
+}
+
+enum MoveOutcome {
+    Win,
+    Continue(Position),
+}
+
+pub struct Tablebase {
+    cards: [Card; 5],
+    max_pieces: u32,
+    card_states: Vec<CardState>,
+    /// Card state index by the bitmasks of red's and blue's cards, `red | blue << 5`
+    card_state_index: Vec<u8>,
+    /// Card state after the player to move uses one of their cards, by state and card
+    card_state_after: Vec<[u8; 5]>,
+    /// Solved tables by number of red and blue disciples
+    tables: HashMap<(u32, u32), Vec<i16>>,
+}
+impl std::fmt::Debug for Tablebase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let cards: String = self.cards.iter().map(|card| cards::card_identifier(card) as char).collect();
+        f.debug_struct("Tablebase").field("cards", &cards).field("max_pieces", &self.max_pieces).finish_non_exhaustive()
+    }
+}
+impl Tablebase {
+    fn empty(cards: [Card; 5], max_pieces: u32) -> Self {
+        let mut cards = cards;
+        cards.sort_by_key(cards::index_of_card);
+
+        let mut card_states = vec![];
+        let mut card_state_index = vec![u8::MAX; 1 << 10];
+        for transfer in 0..5u8 {
+            let hands: Vec<u8> = (0..5).filter(|&card| card != transfer).collect();
+            for i in 0..4 {
+                for j in i + 1..4 {
+                    let red = [hands[i], hands[j]];
+                    let blue_hand: Vec<u8> = hands.iter().copied().filter(|card| !red.contains(card)).collect();
+                    let blue = [blue_hand[0], blue_hand[1]];
+                    let mask = |hand: [u8; 2]| (1 << hand[0]) | (1 << hand[1]);
+                    card_state_index[mask(red) | mask(blue) << 5] = card_states.len() as u8;
+                    card_states.push(CardState { red, blue, transfer });
+                }
+            }
+        }
+        let mut tablebase = Self {
+            cards,
+            max_pieces,
+            card_state_after: vec![],
+            card_state_index,
+            card_states,
+            tables: HashMap::new(),
+        };
+        tablebase.card_state_after = (0..NUM_CARD_STATES)
+            .map(|state| {
+                let mut after = [u8::MAX; 5];
+                let CardState { red, blue, transfer } = tablebase.card_states[state];
+                for used in 0..5u8 {
+                    let replace = |hand: [u8; 2]| hand.map(|card| if card == used { transfer } else { card });
+                    if red.contains(&used) {
+                        after[used as usize] = tablebase.state_index(replace(red), blue);
+                    } else if blue.contains(&used) {
+                        after[used as usize] = tablebase.state_index(red, replace(blue));
+                    }
+                }
+                after
+            })
+            .collect();
+        tablebase
+    }
+
+    fn state_index(&self, red: [u8; 2], blue: [u8; 2]) -> u8 {
+        let mask = |hand: [u8; 2]| (1usize << hand[0]) | (1 << hand[1]);
+        self.card_state_index[mask(red) | mask(blue) << 5]
+    }
+
+    /// Solves every position with up to `max_pieces` pieces, senseis included, for the given cards
+    /// Reports progress through `log` as `(red disciples, blue disciples, sweeps, values)`
+    pub fn generate(cards: [Card; 5], max_pieces: u32, mut log: impl FnMut(u32, u32, u32, &[i16])) -> Self {
+        let mut tablebase = Self::empty(cards, max_pieces);
+        let max_disciples = max_pieces.saturating_sub(2);
+        for total in 0..=max_disciples {
+            for red in 0..=total.min(4) {
+                let blue = total - red;
+                if blue > 4 {
+                    continue;
+                }
+                let (values, sweeps) = tablebase.solve(red, blue);
+                log(red, blue, sweeps, &values);
+                tablebase.tables.insert((red, blue), values);
+            }
+        }
+        tablebase
+    }
+
+    /// Cards of the tablebase, sorted in the order of `cards::ALL_CARDS`
+    pub fn cards(&self) -> &[Card; 5] {
+        &self.cards
+    }
+    pub fn max_pieces(&self) -> u32 {
+        self.max_pieces
+    }
+
+    fn solve(&self, red: u32, blue: u32) -> (Vec<i16>, u32) {
+        let size = table_size(red, blue);
+        let mut values = vec![0i16; size];
+        // Captures lead into smaller tables, whose distances may be longer than anything found in this one
+        let longest_capture = [(red.wrapping_sub(1), blue), (red, blue.wrapping_sub(1))]
+            .iter()
+            .filter_map(|key| self.tables.get(key))
+            .flat_map(|table| table.iter().map(|value| value.unsigned_abs()))
+            .max()
+            .unwrap_or(0) as i16;
+
+        let mut sweep: i16 = 1;
+        loop {
+            let mut changed = false;
+            for index in 0..size {
+                if values[index] != 0 {
+                    continue;
+                }
+                let position = decode(index, red, blue);
+                if !position.is_valid() {
+                    continue;
+                }
+                let mut has_moves = false;
+                let mut win = false;
+                let mut all_lost = true;
+                let mut longest_loss = 0;
+                self.for_each_move(&position, |outcome| {
+                    has_moves = true;
+                    let value = match outcome {
+                        MoveOutcome::Win => {
+                            win |= sweep == 1;
+                            return;
+                        }
+                        MoveOutcome::Continue(child) => self.lookup(&child, &values, red, blue),
+                    };
+                    // Values are from the opponent's perspective after the move
+                    if value == -(sweep - 1) && value < 0 {
+                        win = true;
+                    }
+                    if value > 0 {
+                        longest_loss = longest_loss.max(value);
+                    } else {
+                        all_lost = false;
+                    }
+                });
+                if win {
+                    values[index] = sweep;
+                    changed = true;
+                } else if has_moves && all_lost && longest_loss == sweep - 1 {
+                    values[index] = -sweep;
+                    changed = true;
+                }
+            }
+            if !changed && sweep > longest_capture {
+                break;
+            }
+            // Stopping here would leave positions that are still being decided to be stored as draws
+            assert!(sweep < i16::MAX, "tablebase distances don't fit in 16 bits");
+            sweep += 1;
+        }
+        (values, sweep as u32)
+    }
+
+    /// Value of a position after a move, from the table being solved or a smaller one after a capture
+    fn lookup(&self, position: &Position, current: &[i16], red: u32, blue: u32) -> i16 {
+        let key = (position.red_disciples.count_ones(), position.blue_disciples.count_ones());
+        let index = encode(position);
+        if key == (red, blue) {
+            current[index]
+        } else {
+            self.tables[&key][index]
+        }
+    }
+
+    fn for_each_move(&self, position: &Position, mut f: impl FnMut(MoveOutcome)) {
+        let red_to_move = position.red_to_move;
+        let state = self.card_states[position.cards as usize];
+        let (hand, sensei, disciples, enemy_sensei, enemy_disciples, goal) = if red_to_move {
+            (state.red, position.red_sensei, position.red_disciples, position.blue_sensei, position.blue_disciples, RED_GOAL)
+        } else {
+            (state.blue, position.blue_sensei, position.blue_disciples, position.red_sensei, position.red_disciples, BLUE_GOAL)
+        };
+        let own = disciples | 1 << sensei;
+        for card_index in hand {
+            let card = self.cards[card_index as usize];
+            let offsets = if red_to_move { card.offsets() } else { card.rev_offsets() };
+            for start in bits(own) {
+                let start_pos = Pos::from_index(start as usize);
+                for offset in offsets {
+                    let end_pos = start_pos.offset(offset);
+                    if !end_pos.in_bounds() {
+                        continue;
+                    }
+                    let end = end_pos.to_index() as u8;
+                    if own & 1 << end != 0 {
+                        continue;
+                    }
+                    let moving_sensei = start == sensei;
+                    if end == enemy_sensei || (moving_sensei && end as usize == goal) {
+                        f(MoveOutcome::Win);
+                        continue;
+                    }
+                    let (mut new_sensei, mut new_disciples) = (sensei, disciples);
+                    if moving_sensei {
+                        new_sensei = end;
+                    } else {
+                        new_disciples = new_disciples & !(1 << start) | 1 << end;
+                    }
+                    let new_enemy_disciples = enemy_disciples & !(1 << end);
+                    let cards = self.card_state_after[position.cards as usize][card_index as usize];
+                    f(MoveOutcome::Continue(if red_to_move {
+                        Position {
+                            red_to_move: false,
+                            cards,
+                            red_sensei: new_sensei,
+                            blue_sensei: enemy_sensei,
+                            red_disciples: new_disciples,
+                            blue_disciples: new_enemy_disciples,
+                        }
+                    } else {
+                        Position {
+                            red_to_move: true,
+                            cards,
+                            red_sensei: enemy_sensei,
+                            blue_sensei: new_sensei,
+                            red_disciples: new_enemy_disciples,
+                            blue_disciples: new_disciples,
+                        }
+                    }));
+                }
+            }
+        }
+    }
+
+    /// Looks up a position, if it uses the tablebase's cards and has few enough pieces
+    pub fn probe(&self, board: &Board) -> Option<TablebaseValue> {
+        if board.finished() {
+            return None;
+        }
+        let local_card = |card: &Card| self.cards.iter().position(|other| other == card).map(|i| i as u8);
+        let cards = board.cards();
+        let red = [local_card(&cards[0])?, local_card(&cards[1])?];
+        let blue = [local_card(&cards[2])?, local_card(&cards[3])?];
+        // The transfer card isn't part of the index, it's whichever card is left over
+        local_card(&cards[4])?;
+        let mut position = Position {
+            red_to_move: board.red_to_move(),
+            cards: self.state_index(red, blue),
+            red_sensei: 0,
+            blue_sensei: 0,
+            red_disciples: 0,
+            blue_disciples: 0,
+        };
+        let mut num_pieces = 0;
+        for (i, piece) in board.squares().iter().enumerate() {
+            match piece {
+                Some(Piece::RedSensei) => position.red_sensei = i as u8,
+                Some(Piece::BlueSensei) => position.blue_sensei = i as u8,
+                Some(Piece::RedDisciple) => position.red_disciples |= 1 << i,
+                Some(Piece::BlueDisciple) => position.blue_disciples |= 1 << i,
+                None => continue,
+            }
+            num_pieces += 1;
+        }
+        if num_pieces > self.max_pieces {
+            return None;
+        }
+        let key = (position.red_disciples.count_ones(), position.blue_disciples.count_ones());
+        let value = self.tables.get(&key)?[encode(&position)];
+        Some(TablebaseValue::from_stored(value))
+    }
+
+    /// The move with the best value, the fastest win, or the slowest loss if every move loses
+    /// Returns `None` if the position is not in the tablebase
+    pub fn best_move(&self, board: &Board) -> Option<GameMove> {
+        self.probe(board)?;
+        let mut board = board.clone();
+        let red_to_move = board.red_to_move();
+        let legal_moves = board.legal_moves();
+        legal_moves.into_iter().max_by_key(|game_move| {
+            board.make_move_unchecked(game_move.clone());
+            // Ranked by value first, then by distance
+            let rank = match (board.status(), self.probe(&board)) {
+                (GameStatus::RedWon, _) | (GameStatus::BlueWon, _) => {
+                    if (board.status() == GameStatus::RedWon) == red_to_move { (3, 0) } else { (0, 0) }
+                }
+                (_, Some(TablebaseValue::Loss(distance))) => (2, -(distance as i32)),
+                (_, Some(TablebaseValue::Win(distance))) => (0, distance as i32),
+                _ => (1, 0),
+            };
+            board.undo_move();
+            rank
+        })
+    }
+
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, TablebaseError> {
+        let mut file = std::io::BufReader::new(std::fs::File::open(path).map_err(TablebaseError::Io)?);
+        let mut header = [0; 14];
+        file.read_exact(&mut header).map_err(TablebaseError::Io)?;
+        if &header[0..8] != FILE_MAGIC {
+            return Err(TablebaseError::Format);
+        }
+        let mut cards = [cards::ALL_CARDS[0]; 5];
+        for (card, identifier) in cards.iter_mut().zip(&header[8..13]) {
+            let index = cards::ALL_CARDS_IDENT
+                .iter()
+                .position(|other| other == identifier)
+                .ok_or(TablebaseError::Format)?;
+            *card = cards::ALL_CARDS[index];
+        }
+        let mut tablebase = Self::empty(cards, header[13] as u32);
+        let mut table_header = [0; 2];
+        loop {
+            match file.read_exact(&mut table_header) {
+                Ok(()) => (),
+                Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(err) => return Err(TablebaseError::Io(err)),
+            }
+            let (red, blue) = (table_header[0] as u32, table_header[1] as u32);
+            if red > 4 || blue > 4 {
+                return Err(TablebaseError::Format);
+            }
+            let mut bytes = vec![0u8; table_size(red, blue) * 2];
+            file.read_exact(&mut bytes).map_err(TablebaseError::Io)?;
+            let values = bytes.chunks_exact(2).map(|value| i16::from_le_bytes([value[0], value[1]])).collect();
+            tablebase.tables.insert((red, blue), values);
+        }
+        Ok(tablebase)
+    }
+
+    /// Saves the tablebase in a binary format readable by `Tablebase::load`
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), TablebaseError> {
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path).map_err(TablebaseError::Io)?);
+        let mut header = FILE_MAGIC.to_vec();
+        header.extend(self.cards.iter().map(cards::card_identifier));
+        header.push(self.max_pieces as u8);
+        file.write_all(&header).map_err(TablebaseError::Io)?;
+        let mut keys: Vec<&(u32, u32)> = self.tables.keys().collect();
+        keys.sort();
+        for key in keys {
+            file.write_all(&[key.0 as u8, key.1 as u8]).map_err(TablebaseError::Io)?;
+            let bytes: Vec<u8> = self.tables[key].iter().flat_map(|value| value.to_le_bytes()).collect();
+            file.write_all(&bytes).map_err(TablebaseError::Io)?;
+        }
+        file.flush().map_err(TablebaseError::Io)
+    }
+}
+
+impl Position {
+    /// Positions where a sensei already stands on the enemy temple can't occur, the game would be over
+    fn is_valid(&self) -> bool {
+        self.red_sensei as usize != RED_GOAL && self.blue_sensei as usize != BLUE_GOAL
+    }
+}
+
+const fn binomial(n: u32, k: u32) -> usize {
+    if k > n {
+        return 0;
+    }
+    let mut result = 1;
+    let mut i = 0;
+    while i < k {
+        result = result * (n - i) as usize / (i + 1) as usize;
+        i += 1;
+    }
+    result
+}
+
+fn table_size(red: u32, blue: u32) -> usize {
+    2 * NUM_CARD_STATES * 25 * 24 * binomial(23, red) * binomial(23 - red, blue)
+}
+
+fn bits(mut mask: u32) -> impl Iterator<Item = u8> {
+    std::iter::from_fn(move || {
+        (mask != 0).then(|| {
+            let bit = mask.trailing_zeros() as u8;
+            mask &= mask - 1;
+            bit
+        })
+    })
+}
+
+/// Renumbers the squares in `mask` as if the squares in `excluded` didn't exist
+fn compress(mask: u32, excluded: u32) -> u32 {
+    bits(mask).fold(0, |acc, bit| acc | 1 << (bit as u32 - (excluded & ((1 << bit) - 1)).count_ones()))
+}
+fn decompress(mask: u32, excluded: u32) -> u32 {
+    let mut result = 0;
+    let mut compressed = 0;
+    for square in 0..25 {
+        if excluded & 1 << square != 0 {
+            continue;
+        }
+        if mask & 1 << compressed != 0 {
+            result |= 1 << square;
+        }
+        compressed += 1;
+    }
+    result
+}
+
+/// Combinatorial number system, ranking a set of squares among all sets of the same size
+fn rank(mask: u32) -> usize {
+    bits(mask)
+        .enumerate()
+        .map(|(i, bit)| binomial(bit as u32, i as u32 + 1))
+        .sum()
+}
+fn unrank(mut rank: usize, count: u32) -> u32 {
+    let mut mask = 0;
+    for k in (1..=count).rev() {
+        let mut c = k - 1;
+        while binomial(c + 1, k) <= rank {
+            c += 1;
+        }
+        rank -= binomial(c, k);
+        mask |= 1 << c;
+    }
+    mask
+}
+
+fn encode(position: &Position) -> usize {
+    let red = position.red_disciples.count_ones();
+    let blue = position.blue_disciples.count_ones();
+    let (red_sensei, blue_sensei) = (position.red_sensei as usize, position.blue_sensei as usize);
+    let senseis = 1 << red_sensei | 1 << blue_sensei;
+    let mut index = position.red_to_move as usize;
+    index = index * NUM_CARD_STATES + position.cards as usize;
+    index = index * 25 + red_sensei;
+    index = index * 24 + blue_sensei - (blue_sensei > red_sensei) as usize;
+    index = index * binomial(23, red) + rank(compress(position.red_disciples, senseis));
+    index = index * binomial(23 - red, blue)
+        + rank(compress(position.blue_disciples, senseis | position.red_disciples));
+    index
+}
+
+fn decode(mut index: usize, red: u32, blue: u32) -> Position {
+    let blue_combinations = binomial(23 - red, blue);
+    let blue_rank = index % blue_combinations;
+    index /= blue_combinations;
+    let red_combinations = binomial(23, red);
+    let red_rank = index % red_combinations;
+    index /= red_combinations;
+    let mut blue_sensei = index % 24;
+    index /= 24;
+    let red_sensei = index % 25;
+    index /= 25;
+    if blue_sensei >= red_sensei {
+        blue_sensei += 1;
+    }
+    let cards = index % NUM_CARD_STATES;
+    index /= NUM_CARD_STATES;
+
+    let senseis = 1 << red_sensei | 1 << blue_sensei;
+    let red_disciples = decompress(unrank(red_rank, red), senseis);
+    let blue_disciples = decompress(unrank(blue_rank, blue), senseis | red_disciples);
+    Position {
+        red_to_move: index == 1,
+        cards: cards as u8,
+        red_sensei: red_sensei as u8,
+        blue_sensei: blue_sensei as u8,
+        red_disciples,
+        blue_disciples,
+    }
+}
+
+#[derive(Debug)]
+pub enum TablebaseError {
+    Io(std::io::Error),
+    Format,
+}
+
+impl std::fmt::Display for TablebaseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed to access tablebase file: {err}"),
+            Self::Format => write!(f, "not a valid tablebase file"),
+        }
+    }
+}
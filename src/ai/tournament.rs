@@ -0,0 +1,430 @@
+//! Headless matches between bots, played in parallel without the arena window
+use std::{
+    sync::{Mutex, mpsc},
+    time::Instant,
+};
+
+use super::external::{EngineError, ExternalEngine};
+use super::statistics::{Score, fit_ratings};
+use super::*;
+
+// How often a time-limited search is checked for finishing early
+const POLL_INTERVAL: Duration = Duration::from_millis(2);
+/// How long a bot may take to stop after its time is up before it forfeits the game
+pub const STOP_GRACE: Duration = Duration::from_secs(1);
+/// Hard limit for bots searching to their own limits, so a hung bot forfeits instead of stalling the games
+pub const OWN_LIMITS_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A named bot taking part in a tournament, a fresh instance is made for every game
+#[derive(Clone)]
+pub struct Player {
+    name: String,
+    factory: Arc<dyn Fn() -> Arc<dyn AIOpponent> + Send + Sync>,
+}
+impl Player {
+    pub fn new(name: impl Into<String>, factory: impl Fn() -> Arc<dyn AIOpponent> + Send + Sync + 'static) -> Self {
+        Self {
+            name: name.into(),
+            factory: Arc::new(factory),
+        }
+    }
+    /// One of the built-in bots, deterministic if settings are given
+    pub fn builtin(version: AIVersion, settings: BotSettings, deterministic: Option<DeterministicSettings>) -> Self {
+        Self::new(version.to_string(), move || match &deterministic {
+            Some(deterministic) => version.make_deterministic_opponent(&settings, deterministic),
+            None => version.make_opponent(&settings),
+        })
+    }
+    /// An external engine, started anew for every game so games can be played in parallel
+    /// The engine is started once here to check that it works and to learn its name
+    pub fn external(program: &str, args: &[String]) -> Result<Self, EngineError> {
+        let name = ExternalEngine::spawn(program, args)?.name().to_string();
+        let (program, args) = (program.to_string(), args.to_vec());
+        Ok(Self::new(name, move || -> Arc<dyn AIOpponent> {
+            match ExternalEngine::spawn(&program, &args) {
+                Ok(engine) => Arc::new(engine),
+                Err(err) => {
+                    log::error!("Engine {program}: {err}");
+                    Arc::new(FailedEngine(err.to_string()))
+                }
+            }
+        }))
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+    fn make_opponent(&self) -> Arc<dyn AIOpponent> {
+        (self.factory)()
+    }
+}
+
+/// Stands in for an external engine that failed to start, forfeiting on its first move
+struct FailedEngine(String);
+impl AIOpponent for FailedEngine {
+    fn search(&self, _cancel_signal: Arc<AtomicBool>, _board: Board, _remaining_time: Option<Duration>) -> GameMove {
+        std::panic::panic_any(Fault::Crash(self.0.clone()))
+    }
+}
+
+/// How long bots may think about each move
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MoveLimit {
+    /// Searches are stopped after this long, and bots still going `STOP_GRACE` later forfeit
+    Time(Duration),
+    /// Bots are never stopped, they must finish by themselves, like deterministic bots
+    /// Bots still going after the hard limit, usually `OWN_LIMITS_TIMEOUT`, forfeit
+    OwnLimits(Duration),
+}
+
+/// One game to play: a start position and which players take which side, as indices into the players
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pairing {
+    pub start_index: usize,
+    pub red: usize,
+    pub blue: usize,
+}
+
+/// Every start played twice between the two players, once with each colour
+pub fn pairings_both_colours(num_starts: usize, a: usize, b: usize) -> Vec<Pairing> {
+    (0..num_starts)
+        .flat_map(|start_index| {
+            [
+                Pairing { start_index, red: a, blue: b },
+                Pairing { start_index, red: b, blue: a },
+            ]
+        })
+        .collect()
+}
+
+/// Every pair of players meets on every start, once with each colour
+pub fn pairings_round_robin(num_players: usize, num_starts: usize) -> Vec<Pairing> {
+    let pairs: Vec<(usize, usize)> = (0..num_players)
+        .flat_map(|a| (a + 1..num_players).map(move |b| (a, b)))
+        .collect();
+    interleave_pairs(&pairs, num_starts)
+}
+
+/// The first player meets every other player on every start, once with each colour
+pub fn pairings_gauntlet(num_players: usize, num_starts: usize) -> Vec<Pairing> {
+    let pairs: Vec<(usize, usize)> = (1..num_players).map(|b| (0, b)).collect();
+    interleave_pairs(&pairs, num_starts)
+}
+
+// Start by start rather than pair by pair, so a run cut short is still spread evenly over the pairs
+fn interleave_pairs(pairs: &[(usize, usize)], num_starts: usize) -> Vec<Pairing> {
+    (0..num_starts)
+        .flat_map(|start_index| {
+            pairs.iter().flat_map(move |&(a, b)| {
+                [
+                    Pairing { start_index, red: a, blue: b },
+                    Pairing { start_index, red: b, blue: a },
+                ]
+            })
+        })
+        .collect()
+}
+
+/// A game lost through a fault rather than on the board
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Forfeit {
+    /// Whether red was at fault
+    pub red: bool,
+    pub fault: Fault,
+}
+impl Forfeit {
+    /// Result of the game, a win for the side not at fault
+    pub fn status(&self) -> GameStatus {
+        if self.red { GameStatus::BlueWon } else { GameStatus::RedWon }
+    }
+}
+impl std::fmt::Display for Forfeit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", if self.red { "red" } else { "blue" }, self.fault)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GameResult {
+    pub pairing: Pairing,
+    /// The game as played, from the start position
+    pub game: Board,
+    /// How long each move from the start position took
+    pub move_times: Vec<Duration>,
+    pub forfeit: Option<Forfeit>,
+}
+impl GameResult {
+    /// Result of the game, counting forfeits as losses
+    pub fn status(&self) -> GameStatus {
+        match &self.forfeit {
+            Some(forfeit) => forfeit.status(),
+            None => self.game.status(),
+        }
+    }
+    /// Index of the winning player, `None` for a draw or a game cut short
+    pub fn winner(&self) -> Option<usize> {
+        match self.status() {
+            GameStatus::RedWon => Some(self.pairing.red),
+            GameStatus::BlueWon => Some(self.pairing.blue),
+            _ => None,
+        }
+    }
+}
+
+/// Plays one game from `start`, games that haven't ended after `max_plies` plies are left unfinished
+/// A bot that panics, plays an illegal move or overruns its time forfeits, ending the game
+/// Also returns how long each move took
+pub fn play_game(
+    red: &Player,
+    blue: &Player,
+    start: Board,
+    limit: MoveLimit,
+    max_plies: usize,
+) -> (Board, Vec<Duration>, Option<Forfeit>) {
+    let mut ais = (AsyncAI::new(red.make_opponent()), AsyncAI::new(blue.make_opponent()));
+    let mut game = start;
+    let mut move_times = vec![];
+    let mut plies = 0;
+    while !game.finished() && plies < max_plies && !game.legal_moves().is_empty() {
+        let ai = if game.red_to_move() { &mut ais.0 } else { &mut ais.1 };
+        let search_start = Instant::now();
+        let game_move = match limit {
+            MoveLimit::Time(time) => {
+                ai.start_search(game.clone(), Some(time));
+                while ai.is_thinking() && search_start.elapsed() < time {
+                    std::thread::sleep(POLL_INTERVAL);
+                }
+                ai.try_stop_search(STOP_GRACE)
+            }
+            MoveLimit::OwnLimits(hard_limit) => {
+                ai.start_search(game.clone(), None);
+                ai.try_wait_for_move(Some(hard_limit))
+            }
+        };
+        match game_move.and_then(|game_move| Fault::check_move(&game, game_move)) {
+            Ok(game_move) => {
+                move_times.push(search_start.elapsed());
+                game.make_move_unchecked(game_move);
+            }
+            Err(fault) => {
+                let red_to_move = game.red_to_move();
+                log::error!("{} forfeits: {fault}", if red_to_move { red.name() } else { blue.name() });
+                return (game, move_times, Some(Forfeit { red: red_to_move, fault }));
+            }
+        }
+        plies += 1;
+    }
+    (game, move_times, None)
+}
+
+/// Plays the pairings on `threads` threads, calling `on_result` on this thread as each game finishes
+/// Returning false from `on_result` stops the run, games already in progress are still finished but not reported
+pub fn run_games(
+    players: &[Player],
+    starts: &[Board],
+    pairings: &[Pairing],
+    limit: MoveLimit,
+    max_plies: usize,
+    threads: usize,
+    mut on_result: impl FnMut(&GameResult) -> bool,
+) {
+    let next_pairing = Mutex::new(pairings.iter());
+    let stopped = AtomicBool::new(false);
+    let (sender, receiver) = mpsc::channel();
+    std::thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            let sender = sender.clone();
+            let (next_pairing, stopped) = (&next_pairing, &stopped);
+            scope.spawn(move || {
+                while !stopped.load(Ordering::Relaxed) {
+                    let Some(&pairing) = next_pairing.lock().unwrap().next() else {
+                        break;
+                    };
+                    let start = starts[pairing.start_index].clone();
+                    let (game, move_times, forfeit) =
+                        play_game(&players[pairing.red], &players[pairing.blue], start, limit, max_plies);
+                    if sender.send(GameResult { pairing, game, move_times, forfeit }).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+        for result in receiver {
+            if !stopped.load(Ordering::Relaxed) && !on_result(&result) {
+                stopped.store(true, Ordering::Relaxed);
+            }
+        }
+    });
+}
+
+/// Random cards followed by up to `max_plies` random moves, avoiding positions where the player to move
+/// can win immediately, like the arena's generated positions
+pub fn random_start(rng: &mut StdRand, max_plies: usize) -> Board {
+    loop {
+        let mut identifiers = crate::cards::ALL_CARDS_IDENT.to_vec();
+        let card_set: String = (0..5)
+            .map(|_| identifiers.swap_remove(rng.next_range(0..identifiers.len())) as char)
+            .collect();
+        let mut board = Board::load_game(&card_set).unwrap();
+        let plies = rng.next_range(0..max_plies + 1);
+        if play_random_moves(&mut board, rng, plies) && !one_move_from_winning(&mut board) {
+            return board;
+        }
+    }
+}
+
+/// Plays `plies` random moves, returning false if the game ends or runs out of moves on the way
+pub fn play_random_moves(board: &mut Board, rng: &mut StdRand, plies: usize) -> bool {
+    for _ in 0..plies {
+        let legal_moves = board.legal_moves();
+        if board.finished() || legal_moves.is_empty() {
+            return false;
+        }
+        board.make_move_unchecked(legal_moves[rng.next_range(0..legal_moves.len())].clone());
+    }
+    !board.finished() && !board.legal_moves().is_empty()
+}
+
+/// Doesn't modify board despite the mutable borrow
+fn one_move_from_winning(board: &mut Board) -> bool {
+    board.legal_moves().into_iter().any(|game_move| {
+        board.make_move_unchecked(game_move);
+        let won = matches!(board.status(), GameStatus::RedWon | GameStatus::BlueWon);
+        board.undo_move();
+        won
+    })
+}
+
+/// Results between every pair of players, with ratings fitted to them
+#[derive(Clone, Debug)]
+pub struct Crosstable {
+    names: Vec<String>,
+    /// `scores[a][b]` is the score of player `a` against player `b`
+    scores: Vec<Vec<Score>>,
+}
+impl Crosstable {
+    pub fn new(players: &[Player]) -> Self {
+        Self {
+            names: players.iter().map(|player| player.name().to_string()).collect(),
+            scores: vec![vec![Score::default(); players.len()]; players.len()],
+        }
+    }
+    pub fn add(&mut self, result: &GameResult) {
+        let Pairing { red, blue, .. } = result.pairing;
+        let red_won = result.winner().map(|winner| winner == red);
+        self.scores[red][blue].add(red_won);
+        self.scores[blue][red].add(red_won.map(|won| !won));
+    }
+    pub fn score(&self, player: usize, opponent: usize) -> Score {
+        self.scores[player][opponent]
+    }
+    /// Score of a player against everyone
+    pub fn total(&self, player: usize) -> Score {
+        self.scores[player].iter().fold(Score::default(), |total, score| {
+            Score::new(total.wins + score.wins, total.losses + score.losses, total.draws + score.draws)
+        })
+    }
+    /// Elo ratings of the players, averaging zero, see `fit_ratings`
+    pub fn ratings(&self) -> Vec<f64> {
+        fit_ratings(&self.scores)
+    }
+
+    /// Player indices from the highest rating to the lowest
+    fn ranking(&self, ratings: &[f64]) -> Vec<usize> {
+        let mut ranking: Vec<usize> = (0..self.names.len()).collect();
+        ranking.sort_by(|&a, &b| ratings[b].total_cmp(&ratings[a]));
+        ranking
+    }
+
+    /// Rating, games and score of every player, best first
+    pub fn ranking_table(&self, format: TableFormat) -> String {
+        let ratings = self.ratings();
+        let header = ["Rank", "Player", "Elo", "Games", "Points", "Score", "Draws"];
+        let rows: Vec<Vec<String>> = self
+            .ranking(&ratings)
+            .into_iter()
+            .enumerate()
+            .map(|(rank, player)| {
+                let total = self.total(player);
+                let games = total.games().max(1) as f64;
+                vec![
+                    (rank + 1).to_string(),
+                    self.names[player].clone(),
+                    format!("{:.0}", ratings[player]),
+                    total.games().to_string(),
+                    format!("{:.1}", total.wins as f64 + total.draws as f64 / 2.0),
+                    format!("{:.1}%", total.score() * 100.0),
+                    format!("{:.1}%", total.draws as f64 / games * 100.0),
+                ]
+            })
+            .collect();
+        format.table(&header.map(str::to_string), &rows)
+    }
+
+    /// Points scored by each player (rows) against each opponent (columns) out of the games played, best first
+    pub fn results_table(&self, format: TableFormat) -> String {
+        let ranking = self.ranking(&self.ratings());
+        let mut header = vec!["Player".to_string()];
+        header.extend(ranking.iter().map(|&player| self.names[player].clone()));
+        let rows: Vec<Vec<String>> = ranking
+            .iter()
+            .map(|&player| {
+                let mut row = vec![self.names[player].clone()];
+                row.extend(ranking.iter().map(|&opponent| {
+                    let score = self.scores[player][opponent];
+                    if player == opponent || score.games() == 0 {
+                        "-".to_string()
+                    } else {
+                        format!("{}/{}", score.wins as f64 + score.draws as f64 / 2.0, score.games())
+                    }
+                }));
+                row
+            })
+            .collect();
+        format.table(&header, &rows)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TableFormat {
+    Csv,
+    Markdown,
+}
+impl TableFormat {
+    /// Markdown for `.md` files, CSV for anything else
+    pub fn from_path(path: &str) -> Self {
+        if path.ends_with(".md") { Self::Markdown } else { Self::Csv }
+    }
+
+    fn table(&self, header: &[String], rows: &[Vec<String>]) -> String {
+        let mut lines = vec![self.row(header)];
+        if *self == Self::Markdown {
+            lines.push(format!("|{}", "---|".repeat(header.len())));
+        }
+        lines.extend(rows.iter().map(|row| self.row(row)));
+        lines.join("\n") + "\n"
+    }
+    fn row(&self, cells: &[String]) -> String {
+        match self {
+            Self::Csv => cells
+                .iter()
+                .map(|cell| {
+                    if cell.contains([',', '"']) {
+                        format!("\"{}\"", cell.replace('"', "\"\""))
+                    } else {
+                        cell.clone()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(","),
+            Self::Markdown => {
+                let cells: Vec<String> = cells.iter().map(|cell| cell.replace('|', "\\|")).collect();
+                format!("| {} |", cells.join(" | "))
+            }
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! Lock-free transposition table, safe to share between search threads
+//!
+//! Every entry is two atomic words, the packed data and the key xor'ed with the data. A torn write from two
+//! threads storing at once leaves an entry whose key no longer matches, so it is simply treated as a miss.
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{
+    cards,
+    game::{GameMove, Pos},
+};
+
+/// How the stored score relates to the true score of the position
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bound {
+    Exact = 0,
+    /// The true score is at least the stored score (the search failed high)
+    Lower = 1,
+    /// The true score is at most the stored score (the search failed low)
+    Upper = 2,
+}
+
+/// A game move reduced to what identifies it within a position: the card, start and end square
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PackedMove {
+    pub card_index: u8,
+    pub start: u8,
+    pub end: u8,
+}
+impl PackedMove {
+    pub fn new(game_move: &GameMove) -> Self {
+        Self {
+            card_index: cards::index_of_card(&game_move.used_card) as u8,
+            start: game_move.start_pos.to_index() as u8,
+            end: game_move.end_pos.to_index() as u8,
+        }
+    }
+    pub fn matches(&self, game_move: &GameMove) -> bool {
+        game_move.used_card == cards::ALL_CARDS[self.card_index as usize]
+            && game_move.start_pos == Pos::from_index(self.start as usize)
+            && game_move.end_pos == Pos::from_index(self.end as usize)
+    }
+    fn to_bits(self) -> u64 {
+        (self.card_index as u64) << 10 | (self.start as u64) << 5 | self.end as u64
+    }
+    fn from_bits(bits: u64) -> Self {
+        Self {
+            card_index: (bits >> 10 & 0b1111) as u8,
+            start: (bits >> 5 & 0b11111) as u8,
+            end: (bits & 0b11111) as u8,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TTEntry {
+    pub depth: u32,
+    pub score: i32,
+    pub bound: Bound,
+    pub best_move: Option<PackedMove>,
+}
+impl TTEntry {
+    // Layout: score (32 bits) | depth (8 bits) | bound (2 bits) | has move (1 bit) | move (14 bits)
+    fn pack(&self) -> u64 {
+        let mut data = (self.score as u32 as u64) << 32;
+        data |= (self.depth.min(255) as u64) << 24;
+        data |= (self.bound as u64) << 22;
+        if let Some(best_move) = self.best_move {
+            data |= 1 << 21 | best_move.to_bits();
+        }
+        data
+    }
+    fn unpack(data: u64) -> Self {
+        Self {
+            score: (data >> 32) as u32 as i32,
+            depth: (data >> 24 & 0xff) as u32,
+            bound: match data >> 22 & 0b11 {
+                0 => Bound::Exact,
+                1 => Bound::Lower,
+                _ => Bound::Upper,
+            },
+            best_move: (data >> 21 & 1 == 1).then(|| PackedMove::from_bits(data & 0x3fff)),
+        }
+    }
+}
+
+pub struct TranspositionTable {
+    keys: Box<[AtomicU64]>,
+    data: Box<[AtomicU64]>,
+    shift: u32,
+}
+impl TranspositionTable {
+    /// Creates a table with `2^size_log2` entries, each entry takes up 16 bytes
+    pub fn new(size_log2: u32) -> Self {
+        let size = 1 << size_log2;
+        Self {
+            keys: (0..size).map(|_| AtomicU64::new(0)).collect(),
+            data: (0..size).map(|_| AtomicU64::new(0)).collect(),
+            shift: 64 - size_log2,
+        }
+    }
+
+    /// Number of entries as a power of two, as given to `new`
+    pub fn size_log2(&self) -> u32 {
+        64 - self.shift
+    }
+
+    fn index(&self, hash: u64) -> usize {
+        // Fibonacci hashing, the board hash is perfect but far from uniformly distributed
+        (hash.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> self.shift) as usize
+    }
+
+    pub fn probe(&self, hash: u64) -> Option<TTEntry> {
+        let i = self.index(hash);
+        let data = self.data[i].load(Ordering::Relaxed);
+        let key = self.keys[i].load(Ordering::Relaxed);
+        (data != 0 && key ^ data == hash).then(|| TTEntry::unpack(data))
+    }
+
+    /// Stores an entry, always replacing whatever was stored at the same index before
+    pub fn store(&self, hash: u64, entry: TTEntry) {
+        let i = self.index(hash);
+        let data = entry.pack();
+        self.data[i].store(data, Ordering::Relaxed);
+        self.keys[i].store(hash ^ data, Ordering::Relaxed);
+    }
+
+    pub fn clear(&self) {
+        for (key, data) in self.keys.iter().zip(self.data.iter()) {
+            key.store(0, Ordering::Relaxed);
+            data.store(0, Ordering::Relaxed);
+        }
+    }
+}
@@ -0,0 +1,195 @@
+//! Texel-style tuning of `Evaluator` weights from finished games
+//!
+//! Quiet positions are extracted from finished games and labelled with the game result. The evaluation is
+//! linear in its weights, so the raw term values of every position are computed once up front, after which
+//! the weights are fitted with a local search minimising the squared error between the game result and
+//! the win probability predicted from the evaluation.
+use super::evaluation::{EvalTerm, Evaluator, Features};
+use crate::game::{Board, GameStatus};
+use strum::{EnumCount, IntoEnumIterator};
+use tinyrand::{RandRange, Seeded, StdRand};
+
+/// A quiet position with its precomputed term values and the result of the game it came from
+pub struct TuningPosition {
+    features: Features,
+    /// Pieces per square from red's perspective, blue pieces are mirrored and counted negatively
+    occupancy: [i8; 25],
+    /// 1.0 for a red win, 0.0 for a blue win, 0.5 for a draw
+    result: f64,
+}
+
+/// Extracts all quiet positions from a finished game, skipping the first `skip_plies` positions
+/// Positions are quiet when the player to move has no captures available
+/// Unfinished games yield no positions, as there is no result to learn from
+pub fn extract_positions(game: &Board, skip_plies: usize) -> Vec<TuningPosition> {
+    let result = match game.status() {
+        GameStatus::RedWon => 1.0,
+        GameStatus::BlueWon => 0.0,
+        GameStatus::Stalemate => 0.5,
+        GameStatus::Playing => return vec![],
+    };
+    let mut feature_evaluator = Evaluator::new();
+    for term in EvalTerm::iter() {
+        feature_evaluator = feature_evaluator.with_weight(term, 1);
+    }
+
+    let mut positions = vec![];
+    let mut board = game.clone();
+    while board.game_length() > skip_plies {
+        board.undo_move();
+        let quiet = board
+            .legal_moves()
+            .iter()
+            .all(|game_move| game_move.captured_piece.is_none());
+        if !quiet {
+            continue;
+        }
+        let mut occupancy = [0; 25];
+        for (piece, pos) in board.pieces() {
+            if piece.is_red() {
+                occupancy[pos.to_index()] += 1;
+            } else {
+                occupancy[24 - pos.to_index()] -= 1;
+            }
+        }
+        positions.push(TuningPosition {
+            features: feature_evaluator.features(&board),
+            occupancy,
+            result,
+        });
+    }
+    positions
+}
+
+/// Tunable parameters, the weight of every term except `PieceSquare` followed by the piece-square table
+/// The `PieceSquare` weight is held fixed, since it only scales the table
+const NUM_PARAMS: usize = EvalTerm::COUNT + 25;
+
+pub struct Tuner {
+    positions: Vec<TuningPosition>,
+    params: [i32; NUM_PARAMS],
+    base: Evaluator,
+    scaling: f64,
+    rng: StdRand,
+}
+impl Tuner {
+    /// Sets up tuning starting from the weights of `start`, the seed decides the order parameters are visited in
+    pub fn new(positions: Vec<TuningPosition>, start: Evaluator, seed: u64) -> Self {
+        let mut params = [0; NUM_PARAMS];
+        for term in EvalTerm::iter() {
+            params[term as usize] = start.weight(term);
+        }
+        params[EvalTerm::COUNT..].copy_from_slice(start.piece_square_table());
+        let mut tuner = Self {
+            positions,
+            params,
+            base: start,
+            scaling: 1.0,
+            rng: StdRand::seed(seed),
+        };
+        tuner.scaling = tuner.fit_scaling();
+        tuner
+    }
+
+    pub fn num_positions(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Mean squared error of the predicted results with the current parameters
+    pub fn error(&self) -> f64 {
+        self.error_with(&self.params, self.scaling)
+    }
+
+    fn error_with(&self, params: &[i32; NUM_PARAMS], scaling: f64) -> f64 {
+        if self.positions.is_empty() {
+            return 0.0;
+        }
+        let piece_square_weight = params[EvalTerm::PieceSquare as usize];
+        let total: f64 = self
+            .positions
+            .iter()
+            .map(|position| {
+                let mut score = 0;
+                for term in EvalTerm::iter().filter(|term| *term != EvalTerm::PieceSquare) {
+                    score += params[term as usize] * position.features[term as usize];
+                }
+                let placement: i32 = params[EvalTerm::COUNT..]
+                    .iter()
+                    .zip(position.occupancy)
+                    .map(|(value, count)| value * count as i32)
+                    .sum();
+                score += piece_square_weight * placement;
+                let predicted = 1.0 / (1.0 + 10f64.powf(-scaling * score as f64 / 400.0));
+                (position.result - predicted).powi(2)
+            })
+            .sum();
+        total / self.positions.len() as f64
+    }
+
+    /// Finds the scaling of evaluations to win probabilities that best fits the starting parameters
+    fn fit_scaling(&self) -> f64 {
+        let mut best = (1.0, self.error_with(&self.params, 1.0));
+        for i in 1..=100 {
+            let scaling = i as f64 * 0.05;
+            let error = self.error_with(&self.params, scaling);
+            if error < best.1 {
+                best = (scaling, error);
+            }
+        }
+        best.0
+    }
+
+    /// Runs one pass of local search over all parameters in a random order, trying to nudge each up and down
+    /// Returns whether any parameter was changed
+    pub fn iterate(&mut self, step: i32) -> bool {
+        let mut order: Vec<usize> = (0..NUM_PARAMS)
+            .filter(|&i| i != EvalTerm::PieceSquare as usize)
+            .collect();
+        for i in (1..order.len()).rev() {
+            let j = self.rng.next_range(0..i + 1);
+            order.swap(i, j);
+        }
+
+        let mut improved = false;
+        let mut best_error = self.error();
+        for i in order {
+            for delta in [step, -step] {
+                let mut candidate = self.params;
+                candidate[i] += delta;
+                let error = self.error_with(&candidate, self.scaling);
+                if error < best_error {
+                    best_error = error;
+                    self.params = candidate;
+                    improved = true;
+                    break;
+                }
+            }
+        }
+        improved
+    }
+
+    /// Tunes until no single step improves the error or `max_iterations` passes have been made,
+    /// halving the step size whenever a pass makes no progress. Reports progress through `log`
+    pub fn tune(&mut self, max_iterations: u32, mut log: impl FnMut(u32, i32, f64)) {
+        let mut step = 8;
+        for iteration in 1..=max_iterations {
+            let improved = self.iterate(step);
+            log(iteration, step, self.error());
+            if !improved {
+                if step == 1 {
+                    break;
+                }
+                step /= 2;
+            }
+        }
+    }
+
+    /// The evaluator described by the current parameters
+    pub fn evaluator(&self) -> Evaluator {
+        let mut evaluator = self.base.clone();
+        for term in EvalTerm::iter() {
+            evaluator = evaluator.with_weight(term, self.params[term as usize]);
+        }
+        evaluator.with_piece_square_table(self.params[EvalTerm::COUNT..].try_into().unwrap())
+    }
+}
@@ -1,18 +1,34 @@
 use std::{
-    io::{BufRead, Write},
-    sync::Arc,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, TryRecvError},
+    },
     time::{Duration, Instant},
 };
 
 use egui::Ui;
 use onitama::{
-    ai::{AIVersion, AsyncAI, Dummy},
-    game::{Board, GameMove, GameStatus},
+    ai::{
+        AIOpponent, AIVersion, AsyncAI, BotSettings, DeterministicSettings, Dummy, Fault,
+        analysis::{PositionAnalysis, analyse_game, blunders},
+        evaluation::Evaluator,
+        external::ExternalEngine,
+        network::Network,
+        opening_book::{BookOpponent, OpeningBook},
+        opening_suite::{SuiteConfig, SuitePosition, generate_suite},
+        records::{GameRecord, load_records, save_records},
+        statistics::{Score, Sprt, SprtResult},
+        tablebase::Tablebase,
+        tournament::{Forfeit, MoveLimit, OWN_LIMITS_TIMEOUT, STOP_GRACE, random_start},
+    },
+    cards,
+    game::{Board, GameMove, GameStatus, Piece},
     graphics::{renderer::TexHandle, GFXState},
     gui::GameGraphics,
 };
-use strum::IntoEnumIterator;
-use tinyrand::{RandRange, StdRand};
+use tinyrand::{Rand, Seeded, StdRand};
+use tinyrand_std::ClockSeed;
 use winit::{
     application::ApplicationHandler,
     dpi::LogicalSize,
@@ -23,6 +39,8 @@ use winit::{
 
 const WIDTH: u32 = 1200;
 const HEIGHT: u32 = 800;
+// Scores beyond this are drawn at the edge of the evaluation graph
+const GRAPH_RANGE: i32 = 500;
 
 fn main() {
     env_logger::init();
@@ -118,6 +136,7 @@ impl ApplicationHandler for Application<'_> {
 
                     // Update & render game
                     arena.update_match();
+                    arena.update_replay();
                     let game_rect = from_egui_rect(leftover_rect);
                     let game_graphics = arena.game_graphics(game_rect);
                     game_graphics.draw(&mut gfx_state.renderer, arena.red_to_move()); // Draw game
@@ -173,22 +192,50 @@ impl ApplicationHandler for Application<'_> {
     }
 }
 
-type Match = (String, GameStatus);
 struct Arena {
     game: Board,
     disciple_tex: TexHandle,
     sensei_tex: TexHandle,
     position_generation: PositionGeneration,
-    stored_matches: Vec<Match>,
+    stored_matches: Vec<GameRecord>,
     current_match_index: usize,
     ai_selection: (AIVersion, AIVersion),
+    ai_params: (SettingsFile<Evaluator>, SettingsFile<Evaluator>),
+    /// Networks for MinMaxV4 and the difficulty levels
+    ai_networks: (SettingsFile<Arc<Network>>, SettingsFile<Arc<Network>>),
+    /// Endgame tablebases for MinMaxV4 and the difficulty levels
+    ai_tablebases: (SettingsFile<Arc<Tablebase>>, SettingsFile<Arc<Tablebase>>),
+    /// Whether a selected MinMaxV4 uses quiescence search, for red and blue
+    quiescence: (bool, bool),
+    engines: (EngineCommand, EngineCommand),
+    book: BookFile,
     ai_opps: (AsyncAI, AsyncAI), // red and blue
     ai_playing: bool,
     play_one_move: bool,
     play_all_matches: bool,
     started_search: bool,
     last_move_time: Instant,
+    /// Moves the bots played with how long each took, by ply. Only recorded if they match the game's moves, as the
+    /// game can also be undone or replaced between moves
+    move_times: Vec<Option<(GameMove, Duration)>>,
+    /// When the current bot was told to stop, it forfeits if it hasn't stopped `STOP_GRACE` later
+    stop_requested: Option<Instant>,
     time_per_move_ms: u64,
+    /// Threads each bot searches with when limited by time
+    search_threads: usize,
+    /// Limit bots by depth and nodes instead of time, so matches can be replayed exactly
+    deterministic: Option<DeterministicSettings>,
+    /// Play every position a second time with the bots swapping sides
+    both_colours: bool,
+    /// The red selection is playing blue and vice versa
+    sides_swapped: bool,
+    /// Results of the red selection in the current run of all matches, whichever side it played
+    match_score: Score,
+    /// Stops a run of all matches once the test is decided
+    sprt: Option<Sprt>,
+    /// Stored match being stepped through, shown on the board instead of `game` being played
+    replay: Option<Replay>,
+    editor: BoardEditor,
 }
 impl Arena {
     fn new(disciple_tex: TexHandle, sensei_tex: TexHandle) -> Self {
@@ -199,9 +246,15 @@ impl Arena {
             disciple_tex,
             sensei_tex,
             position_generation: PositionGeneration::new(),
-            stored_matches: vec![(game_str, GameStatus::Playing)],
+            stored_matches: vec![GameRecord::unplayed(game_str)],
             current_match_index: 0,
             ai_selection: (AIVersion::Dummy, AIVersion::Dummy),
+            ai_params: (SettingsFile::new(load_params), SettingsFile::new(load_params)),
+            ai_networks: (SettingsFile::new(load_network), SettingsFile::new(load_network)),
+            ai_tablebases: (SettingsFile::new(load_tablebase), SettingsFile::new(load_tablebase)),
+            quiescence: (true, true),
+            engines: (EngineCommand::new(), EngineCommand::new()),
+            book: BookFile::new(),
             ai_opps: (
                 AsyncAI::new(Arc::new(Dummy)),
                 AsyncAI::new(Arc::new(Dummy)),
@@ -211,7 +264,17 @@ impl Arena {
             play_all_matches: false,
             started_search: false,
             last_move_time: Instant::now(),
+            move_times: vec![],
+            stop_requested: None,
             time_per_move_ms: 100,
+            search_threads: 1,
+            deterministic: None,
+            both_colours: false,
+            sides_swapped: false,
+            match_score: Score::default(),
+            sprt: None,
+            replay: None,
+            editor: BoardEditor::new(),
         }
     }
 
@@ -224,8 +287,8 @@ impl Arena {
                     let (red_wins, blue_wins, draws) =
                         self.stored_matches
                             .iter()
-                            .fold((0, 0, 0), |mut acc, (_, game_status)| {
-                                match game_status {
+                            .fold((0, 0, 0), |mut acc, record| {
+                                match record.result {
                                     GameStatus::RedWon => acc.0 += 1,
                                     GameStatus::BlueWon => acc.1 += 1,
                                     GameStatus::Stalemate => acc.2 += 1,
@@ -236,12 +299,29 @@ impl Arena {
                     ui.label(format!(
                         "Red: {red_wins} - Blue: {blue_wins} - Draw: {draws}"
                     ));
+                    if self.match_score.games() > 0 {
+                        ui.label(format!(
+                            "{}: {}, Elo {}, LOS {:.1}%",
+                            self.ai_name(0),
+                            self.match_score,
+                            self.match_score.elo(),
+                            self.match_score.los() * 100.0
+                        ));
+                        if let Some(sprt) = &self.sprt {
+                            let (lower, upper) = sprt.bounds();
+                            ui.label(format!(
+                                "SPRT: LLR {:.2} ({lower:.2}, {upper:.2}), {}",
+                                sprt.llr(&self.match_score),
+                                sprt.test(&self.match_score)
+                            ));
+                        }
+                    }
                     ui.separator();
                     ui.label("AI match");
                     egui::ComboBox::from_label("Red AI")
                         .selected_text(self.ai_selection.0.to_string())
                         .show_ui(ui, |ui| {
-                            for variant in AIVersion::iter() {
+                            for variant in AIVersion::all() {
                                 ui.selectable_value(
                                     &mut self.ai_selection.0,
                                     variant,
@@ -252,7 +332,7 @@ impl Arena {
                     egui::ComboBox::from_label("Blue AI")
                         .selected_text(self.ai_selection.1.to_string())
                         .show_ui(ui, |ui| {
-                            for variant in AIVersion::iter() {
+                            for variant in AIVersion::all() {
                                 ui.selectable_value(
                                     &mut self.ai_selection.1,
                                     variant,
@@ -260,28 +340,87 @@ impl Arena {
                                 );
                             }
                         });
+                    self.ai_params.0.make_ui(ui, "Red params");
+                    self.ai_params.1.make_ui(ui, "Blue params");
+                    self.ai_networks.0.make_ui(ui, "Red network");
+                    self.ai_networks.1.make_ui(ui, "Blue network");
+                    self.ai_tablebases.0.make_ui(ui, "Red tablebase");
+                    self.ai_tablebases.1.make_ui(ui, "Blue tablebase");
                     ui.horizontal(|ui| {
-                        ui.add(
-                            egui::DragValue::new(&mut self.time_per_move_ms)
-                                .suffix("ms")
-                                .range(0..=10_000),
-                        );
-                        ui.label("Time per move");
+                        ui.checkbox(&mut self.quiescence.0, "Red");
+                        ui.checkbox(&mut self.quiescence.1, "Blue");
+                        ui.label("MinMaxV4 quiescence");
                     });
+                    self.engines.0.make_ui(ui, "Red engine");
+                    self.engines.1.make_ui(ui, "Blue engine");
+                    self.book.make_ui(ui);
+                    let mut deterministic = self.deterministic.is_some();
+                    ui.checkbox(&mut deterministic, "Deterministic");
+                    match (deterministic, &mut self.deterministic) {
+                        (true, Some(settings)) => {
+                            ui.horizontal(|ui| {
+                                ui.add(egui::DragValue::new(&mut settings.depth).range(1..=20));
+                                ui.label("Depth");
+                                ui.add(egui::DragValue::new(&mut settings.node_limit).range(1..=100_000_000));
+                                ui.label("Nodes");
+                                ui.add(egui::DragValue::new(&mut settings.seed));
+                                ui.label("Seed");
+                            });
+                        }
+                        (true, None) => self.deterministic = Some(DeterministicSettings::default()),
+                        (false, _) => {
+                            self.deterministic = None;
+                            ui.horizontal(|ui| {
+                                ui.add(
+                                    egui::DragValue::new(&mut self.time_per_move_ms)
+                                        .suffix("ms")
+                                        .range(0..=10_000),
+                                );
+                                ui.label("Time per move");
+                            });
+                            ui.horizontal(|ui| {
+                                ui.add(egui::DragValue::new(&mut self.search_threads).range(1..=64));
+                                ui.label("Search threads");
+                            });
+                        }
+                    }
+                    ui.checkbox(&mut self.both_colours, "Play both colours");
+                    let mut use_sprt = self.sprt.is_some();
+                    ui.checkbox(&mut use_sprt, "Stop early with SPRT");
+                    match (use_sprt, &mut self.sprt) {
+                        (true, Some(sprt)) => {
+                            ui.horizontal(|ui| {
+                                ui.add(egui::DragValue::new(&mut sprt.elo0).speed(0.5));
+                                ui.label("Elo0");
+                                ui.add(egui::DragValue::new(&mut sprt.elo1).speed(0.5));
+                                ui.label("Elo1");
+                            });
+                            ui.horizontal(|ui| {
+                                ui.add(egui::DragValue::new(&mut sprt.alpha).speed(0.001).range(0.001..=0.5));
+                                ui.label("Alpha");
+                                ui.add(egui::DragValue::new(&mut sprt.beta).speed(0.001).range(0.001..=0.5));
+                                ui.label("Beta");
+                            });
+                        }
+                        (true, None) => self.sprt = Some(Sprt::new(0.0, 10.0)),
+                        (false, _) => self.sprt = None,
+                    }
                     ui.horizontal(|ui| {
                         if ui.button("Play").clicked() {
-                            self.ai_opps.0 = self.ai_selection.0.make_ai();
-                            self.ai_opps.1 = self.ai_selection.1.make_ai();
+                            self.replay = None;
+                            self.sides_swapped = false;
+                            self.make_ais();
                             self.game =
-                                Board::load_game(&self.stored_matches[self.current_match_index].0)
-                                    .unwrap();
+                                self.stored_matches[self.current_match_index].start_board();
                             self.ai_playing = true;
                         }
                         if ui.button("Play All").clicked() {
-                            self.ai_opps.0 = self.ai_selection.0.make_ai();
-                            self.ai_opps.1 = self.ai_selection.1.make_ai();
+                            self.replay = None;
+                            self.sides_swapped = false;
+                            self.match_score = Score::default();
+                            self.make_ais();
                             self.current_match_index = 0;
-                            self.game = Board::load_game(&self.stored_matches[0].0).unwrap();
+                            self.game = self.stored_matches[0].start_board();
                             self.ai_playing = true;
                             self.play_all_matches = true;
                         }
@@ -291,27 +430,131 @@ impl Arena {
                             self.game.undo_move();
                         }
                         if ui.button("Next >>").clicked() && !self.game.finished() {
-                            self.ai_opps.0 = self.ai_selection.0.make_ai();
-                            self.ai_opps.1 = self.ai_selection.1.make_ai();
+                            self.replay = None;
+                            self.make_ais();
                             self.ai_playing = true;
                             self.play_one_move = true;
                         }
                     });
+                    ui.collapsing("Evaluation", |ui| {
+                        let breakdown =
+                            Evaluator::default().breakdown(&self.game, self.game.red_to_move());
+                        ui.monospace(breakdown.to_string());
+                        if ui.button("Print").clicked() {
+                            println!("{}\n{breakdown}", self.game.save_game(false));
+                        }
+                    });
+                    if ui.button("Edit board").clicked() {
+                        self.editor.load(&self.game);
+                        self.editor.open = true;
+                    }
                     ui.separator();
-                    self.position_generation.make_ui(
-                        ui,
-                        &mut self.game,
-                        &mut self.stored_matches,
-                        &mut self.current_match_index,
-                    );
+                    // Picking or adding matches would swap the board under the match being played
+                    let clicked = ui
+                        .add_enabled_ui(!self.ai_playing, |ui| {
+                            self.position_generation.make_ui(
+                                ui,
+                                &mut self.game,
+                                &mut self.stored_matches,
+                                &mut self.current_match_index,
+                            )
+                        })
+                        .inner;
+                    if let Some(index) = clicked {
+                        let record = &self.stored_matches[index];
+                        self.replay = record.is_played().then(|| Replay::new(record));
+                    }
                 });
             });
+        if let Some(replay) = &mut self.replay {
+            let mut open = true;
+            egui::SidePanel::right("replay panel")
+                .resizable(true)
+                .default_width(250.0)
+                .show(ctx, |ui| {
+                    if replay.make_ui(ui) {
+                        self.game = replay.board();
+                    }
+                    if ui.button("Close").clicked() {
+                        open = false;
+                    }
+                });
+            if !open {
+                self.replay = None;
+            }
+        }
+        if self.editor.open && !self.ai_playing {
+            let mut open = true;
+            let mut added = None;
+            egui::Window::new("Board editor")
+                .open(&mut open)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    added = self.editor.make_ui(ui, &self.game);
+                });
+            self.editor.open = open;
+            if let Some(board) = added {
+                self.stored_matches.push(GameRecord::unplayed(board.save_game(false)));
+                self.current_match_index = self.stored_matches.len() - 1;
+                self.replay = None;
+                self.game = board;
+            }
+        }
         egui::TopBottomPanel::bottom("bottom panel")
             .show(ctx, |ui| {
                 ui.label(self.game.save_game(true));
             });
     }
 
+    fn make_ais(&mut self) {
+        let red = match &self.engines.0.engine {
+            Some(engine) => engine.clone(),
+            None => self.make_opponent(self.ai_selection.0, &self.bot_settings(0)),
+        };
+        let blue = match &self.engines.1.engine {
+            Some(engine) => engine.clone(),
+            None => self.make_opponent(self.ai_selection.1, &self.bot_settings(1)),
+        };
+        let (red, blue) = if self.sides_swapped { (blue, red) } else { (red, blue) };
+        let seed = self.deterministic.map(|settings| settings.seed);
+        self.ai_opps.0 = AsyncAI::new(self.book.wrap(red, seed));
+        self.ai_opps.1 = AsyncAI::new(self.book.wrap(blue, seed));
+    }
+
+    /// Settings of the bot selected for red (0) or blue (1), regardless of swapped sides
+    fn bot_settings(&self, selection: usize) -> BotSettings {
+        let (params, network, tablebase, quiescence) = if selection == 0 {
+            (&self.ai_params.0, &self.ai_networks.0, &self.ai_tablebases.0, self.quiescence.0)
+        } else {
+            (&self.ai_params.1, &self.ai_networks.1, &self.ai_tablebases.1, self.quiescence.1)
+        };
+        let mut settings = BotSettings::default().with_threads(self.search_threads).with_quiescence(quiescence);
+        settings.evaluator = params.value.clone();
+        settings.network = network.value.clone();
+        settings.tablebase = tablebase.value.clone();
+        settings
+    }
+
+    fn make_opponent(&self, ai_version: AIVersion, settings: &BotSettings) -> Arc<dyn AIOpponent> {
+        match &self.deterministic {
+            Some(deterministic) => ai_version.make_deterministic_opponent(settings, deterministic),
+            None => ai_version.make_opponent(settings),
+        }
+    }
+
+    /// Name of the bot or engine selected for red (0) or blue (1), regardless of swapped sides
+    fn ai_name(&self, selection: usize) -> String {
+        let (engine, version) = if selection == 0 {
+            (&self.engines.0, self.ai_selection.0)
+        } else {
+            (&self.engines.1, self.ai_selection.1)
+        };
+        match &engine.engine {
+            Some(engine) => engine.name().to_string(),
+            None => version.to_string(),
+        }
+    }
+
     fn update_match(&mut self) {
         if !self.ai_playing {
             return;
@@ -323,40 +566,118 @@ impl Arena {
             &mut self.ai_opps.1
         };
         let mut finish_early = false;
+        let mut forfeit = None;
 
         // Start a search for a move
         if !self.started_search {
             self.started_search = true;
             self.last_move_time = Instant::now();
             current_ai.start_search(game.clone(), None);
-        // Stop search, get next move
-        } else if !current_ai.is_thinking()
-            || self.last_move_time.elapsed() > Duration::from_millis(self.time_per_move_ms)
-        {
-            self.started_search = false;
-            let game_move = current_ai.stop_search();
-            game.make_move(game_move.used_card, game_move.start_pos, game_move.end_pos)
-                .expect("Illegal move!");
+        } else {
+            // Deterministic bots are never stopped early, they always finish by themselves
+            let elapsed = self.last_move_time.elapsed();
+            if self.deterministic.is_none()
+                && self.stop_requested.is_none()
+                && elapsed > Duration::from_millis(self.time_per_move_ms)
+            {
+                current_ai.request_stop();
+                self.stop_requested = Some(Instant::now());
+            }
+            // Checked every frame rather than waiting, so a slow bot doesn't freeze the window
+            let give_up = self.stop_requested.is_some_and(|requested| requested.elapsed() > STOP_GRACE)
+                || (self.deterministic.is_some() && elapsed > OWN_LIMITS_TIMEOUT);
+            if current_ai.search_finished() || give_up {
+                self.started_search = false;
+                self.stop_requested = None;
+                // Returns at once, with `Fault::Timeout` if the bot is still searching
+                let game_move = current_ai.try_wait_for_move(Some(Duration::ZERO));
+                // A faulty bot loses the game rather than taking the arena down with it
+                match game_move.and_then(|game_move| Fault::check_move(game, game_move)) {
+                    Ok(game_move) => {
+                        let ply = game.game_length();
+                        self.move_times.resize(ply, None);
+                        self.move_times.push(Some((game_move.clone(), self.last_move_time.elapsed())));
+                        game.make_move_unchecked(game_move);
+                    }
+                    Err(fault) => {
+                        log::error!("{} forfeits: {fault}", if game.red_to_move() { "Red" } else { "Blue" });
+                        forfeit = Some(Forfeit { red: game.red_to_move(), fault });
+                    }
+                }
 
-            // If we only wanted one move, stop here
-            if self.play_one_move {
-                self.play_one_move = false;
-                finish_early = true;
+                // If we only wanted one move, stop here
+                if self.play_one_move {
+                    self.play_one_move = false;
+                    finish_early = true;
+                }
             }
         }
 
-        if game.finished() || finish_early {
-            self.stored_matches[self.current_match_index].1 = game.status();
+        if self.game.finished() || finish_early || forfeit.is_some() {
+            let status = match &forfeit {
+                Some(forfeit) => forfeit.status(),
+                None => self.game.status(),
+            };
+            let (red, blue) = if self.sides_swapped { (1, 0) } else { (0, 1) };
+            let (red, blue) = (self.ai_name(red), self.ai_name(blue));
+            let record = &mut self.stored_matches[self.current_match_index];
+            record.red = red;
+            record.blue = blue;
+            record.time_limit = match self.deterministic {
+                Some(_) => None,
+                None => Some(Duration::from_millis(self.time_per_move_ms)),
+            };
+            record.result = status;
+            record.game = self.game.save_game(false);
+            let start = record.start_board().game_length();
+            let moves = &self.game.move_history()[start..];
+            let timed = self.move_times.get(start..).unwrap_or_default();
+            record.move_times = timed
+                .iter()
+                .zip(moves)
+                .map(|(timed, game_move)| timed.as_ref().filter(|(timed, _)| timed == game_move).map(|(_, time)| *time))
+                .collect::<Option<Vec<_>>>()
+                .filter(|times| times.len() == moves.len())
+                .unwrap_or_default();
+            record.forfeit = forfeit.map(|forfeit| forfeit.to_string());
 
-            if self.play_all_matches && self.current_match_index < self.stored_matches.len() {
-                self.current_match_index += 1;
-                if self.current_match_index == self.stored_matches.len() {
-                    self.current_match_index -= 1;
+            if self.play_all_matches {
+                match status {
+                    GameStatus::RedWon => self.match_score.add(Some(!self.sides_swapped)),
+                    GameStatus::BlueWon => self.match_score.add(Some(self.sides_swapped)),
+                    GameStatus::Stalemate => self.match_score.add(None),
+                    GameStatus::Playing => (),
+                }
+                let decided = self
+                    .sprt
+                    .is_some_and(|sprt| sprt.test(&self.match_score) != SprtResult::Continue);
+                if decided {
                     self.ai_playing = false;
                     self.play_all_matches = false;
+                } else if self.both_colours && !self.sides_swapped {
+                    // The game with swapped sides gets its own record, right after this one
+                    // It may already be there from an earlier run
+                    let record = &self.stored_matches[self.current_match_index];
+                    let next = self.current_match_index + 1;
+                    let has_pair = self.stored_matches.get(next).is_some_and(|next| {
+                        next.start == record.start && next.red == record.blue && next.blue == record.red
+                    });
+                    if !has_pair {
+                        let start = record.start.clone();
+                        self.stored_matches.insert(next, GameRecord::unplayed(start));
+                    }
+                    self.sides_swapped = true;
+                    self.make_ais();
+                    self.current_match_index = next;
+                    self.game = self.stored_matches[next].start_board();
+                } else if self.current_match_index + 1 < self.stored_matches.len() {
+                    self.sides_swapped = false;
+                    self.make_ais();
+                    self.current_match_index += 1;
+                    self.game = self.stored_matches[self.current_match_index].start_board();
                 } else {
-                    *game =
-                        Board::load_game(&self.stored_matches[self.current_match_index].0).unwrap();
+                    self.ai_playing = false;
+                    self.play_all_matches = false;
                 }
             } else {
                 self.ai_playing = false;
@@ -364,6 +685,15 @@ impl Arena {
         }
     }
 
+    /// Steps an autoplaying replay
+    fn update_replay(&mut self) {
+        if let Some(replay) = &mut self.replay
+            && replay.update()
+        {
+            self.game = replay.board();
+        }
+    }
+
     fn game_graphics(&self, rect: onitama::graphics::Rect) -> GameGraphics {
         GameGraphics::new(rect, &self.game, self.disciple_tex, self.sensei_tex)
     }
@@ -373,56 +703,676 @@ impl Arena {
     }
 }
 
+fn load_params(path: &str) -> Result<Evaluator, String> {
+    Evaluator::load(path).map_err(|err| err.to_string())
+}
+
+fn load_network(path: &str) -> Result<Arc<Network>, String> {
+    Network::load(path).map(Arc::new).map_err(|err| err.to_string())
+}
+
+fn load_tablebase(path: &str) -> Result<Arc<Tablebase>, String> {
+    Tablebase::load(path).map(Arc::new).map_err(|err| err.to_string())
+}
+
+/// An optional setting for one side loaded from a file, like evaluator parameters, a network or a tablebase
+struct SettingsFile<T> {
+    path: String,
+    value: Option<T>,
+    error: Option<String>,
+    load: fn(&str) -> Result<T, String>,
+}
+impl<T> SettingsFile<T> {
+    fn new(load: fn(&str) -> Result<T, String>) -> Self {
+        Self {
+            path: String::new(),
+            value: None,
+            error: None,
+            load,
+        }
+    }
+
+    fn make_ui(&mut self, ui: &mut Ui, label: &str) {
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.path).desired_width(140.0));
+            if ui.button("Load").clicked() {
+                match (self.load)(&self.path) {
+                    Ok(value) => {
+                        self.value = Some(value);
+                        self.error = None;
+                    }
+                    Err(err) => {
+                        self.value = None;
+                        self.error = Some(err);
+                    }
+                }
+            }
+            if ui.button("Clear").clicked() {
+                self.value = None;
+                self.error = None;
+            }
+            ui.label(label);
+        });
+        if let Some(err) = &self.error {
+            ui.colored_label(egui::Color32::RED, err);
+        } else if self.value.is_some() {
+            ui.label(format!("Using {}", self.path));
+        }
+    }
+}
+
+/// Optional external engine for one side, replacing the selected bot while it is running
+struct EngineCommand {
+    command: String,
+    engine: Option<Arc<ExternalEngine>>,
+    error: Option<String>,
+}
+impl EngineCommand {
+    fn new() -> Self {
+        Self {
+            command: String::new(),
+            engine: None,
+            error: None,
+        }
+    }
+
+    fn make_ui(&mut self, ui: &mut Ui, label: &str) {
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.command).desired_width(140.0));
+            if ui.button("Start").clicked() {
+                let mut words = self.command.split_whitespace();
+                let program = words.next().unwrap_or("");
+                let args: Vec<String> = words.map(str::to_string).collect();
+                match ExternalEngine::spawn(program, &args) {
+                    Ok(engine) => {
+                        self.engine = Some(Arc::new(engine));
+                        self.error = None;
+                    }
+                    Err(err) => {
+                        self.engine = None;
+                        self.error = Some(err.to_string());
+                    }
+                }
+            }
+            if ui.button("Stop").clicked() {
+                self.engine = None;
+                self.error = None;
+            }
+            ui.label(label);
+        });
+        if let Some(err) = &self.error {
+            ui.colored_label(egui::Color32::RED, err);
+        } else if let Some(engine) = &self.engine {
+            ui.label(format!("Using {}", engine.name()));
+        }
+    }
+}
+
+/// Optional opening book used by both sides, loaded from a file
+struct BookFile {
+    path: String,
+    book: Option<Arc<OpeningBook>>,
+    varied: bool,
+    error: Option<String>,
+}
+impl BookFile {
+    fn new() -> Self {
+        Self {
+            path: String::new(),
+            book: None,
+            varied: true,
+            error: None,
+        }
+    }
+
+    fn make_ui(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.path).desired_width(140.0));
+            if ui.button("Load").clicked() {
+                match OpeningBook::load(&self.path) {
+                    Ok(book) => {
+                        self.book = Some(Arc::new(book));
+                        self.error = None;
+                    }
+                    Err(err) => {
+                        self.book = None;
+                        self.error = Some(err.to_string());
+                    }
+                }
+            }
+            if ui.button("Clear").clicked() {
+                self.book = None;
+                self.error = None;
+            }
+            ui.label("Opening book");
+        });
+        if let Some(err) = &self.error {
+            ui.colored_label(egui::Color32::RED, err);
+        } else if let Some(book) = &self.book {
+            ui.horizontal(|ui| {
+                ui.label(format!("{} positions", book.len()));
+                ui.checkbox(&mut self.varied, "Vary book moves");
+            });
+        }
+    }
+
+    /// Makes the opponent play from the book first, if one is loaded
+    /// Varied book moves are picked with the given seed, or seeded from the clock
+    fn wrap(&self, ai_opponent: Arc<dyn AIOpponent>, seed: Option<u64>) -> Arc<dyn AIOpponent> {
+        match &self.book {
+            Some(book) => {
+                let book_opponent = BookOpponent::new(book.clone(), ai_opponent).with_variety(self.varied);
+                match seed {
+                    Some(seed) => Arc::new(book_opponent.with_seed(seed)),
+                    None => Arc::new(book_opponent),
+                }
+            }
+            None => ai_opponent,
+        }
+    }
+}
+
+/// Steps through the moves of a stored match, one ply at a time
+struct Replay {
+    title: String,
+    start: Board,
+    /// Moves played from the start position
+    moves: Vec<GameMove>,
+    /// Number of moves made on the board shown
+    ply: usize,
+    autoplay: bool,
+    /// Time between moves when autoplaying
+    interval_ms: u64,
+    last_step: Instant,
+    analysis_ai: AIVersion,
+    analysis_ms: u64,
+    /// Score lost by a move for it to be flagged as a blunder
+    blunder_threshold: i32,
+    /// Analysis of the position at every ply, filled in as the background analysis finishes them
+    analysis: Vec<Option<PositionAnalysis>>,
+    analysis_run: Option<AnalysisRun>,
+}
+impl Replay {
+    fn new(record: &GameRecord) -> Self {
+        let start = record.start_board();
+        let moves = record.game_board().move_history()[start.game_length()..].to_vec();
+        let result = match (&record.forfeit, record.result) {
+            (Some(reason), _) => format!("forfeit, {reason}"),
+            (None, GameStatus::RedWon) => "red won".to_string(),
+            (None, GameStatus::BlueWon) => "blue won".to_string(),
+            (None, GameStatus::Stalemate) => "stalemate".to_string(),
+            (None, GameStatus::Playing) => "unfinished".to_string(),
+        };
+        Self {
+            title: format!("{} (red) vs {} (blue), {result}", record.red, record.blue),
+            start,
+            moves,
+            ply: 0,
+            autoplay: false,
+            interval_ms: 500,
+            last_step: Instant::now(),
+            analysis_ai: AIVersion::MinMaxV4,
+            analysis_ms: 200,
+            blunder_threshold: 100,
+            analysis: vec![],
+            analysis_run: None,
+        }
+    }
+
+    /// Analyses the game on a background thread, replacing any earlier analysis
+    fn start_analysis(&mut self) {
+        let opponent = self.analysis_ai.make_opponent(&BotSettings::default());
+        let (start, moves) = (self.start.clone(), self.moves.clone());
+        let limit = MoveLimit::Time(Duration::from_millis(self.analysis_ms));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (sender, receiver) = mpsc::channel();
+        let thread_cancel = cancel.clone();
+        std::thread::spawn(move || {
+            analyse_game(opponent.as_ref(), &start, &moves, limit, &thread_cancel, |ply, analysis| {
+                let _ = sender.send((ply, analysis));
+            });
+        });
+        self.analysis = vec![None; self.moves.len() + 1];
+        self.analysis_run = Some(AnalysisRun { receiver, cancel });
+    }
+
+    /// Collects the positions analysed so far
+    fn update_analysis(&mut self) {
+        let Some(run) = &self.analysis_run else {
+            return;
+        };
+        loop {
+            match run.receiver.try_recv() {
+                Ok((ply, analysis)) => self.analysis[ply] = Some(analysis),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.analysis_run = None;
+                    break;
+                }
+            }
+        }
+    }
+
+    fn analysis_ui(&mut self, ui: &mut Ui) {
+        ui.label("Analysis");
+        ui.add_enabled_ui(self.analysis_run.is_none(), |ui| {
+            egui::ComboBox::from_label("Bot")
+                .selected_text(self.analysis_ai.to_string())
+                .show_ui(ui, |ui| {
+                    for variant in AIVersion::all() {
+                        ui.selectable_value(&mut self.analysis_ai, variant, variant.to_string());
+                    }
+                });
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut self.analysis_ms).suffix("ms").range(10..=10000));
+                ui.label("Per position");
+            });
+        });
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut self.blunder_threshold).range(1..=10000));
+            ui.label("Blunder threshold");
+        });
+        match &self.analysis_run {
+            Some(_) => {
+                let done = self.analysis.iter().filter(|analysis| analysis.is_some()).count();
+                ui.label(format!("Analysing {done}/{}", self.analysis.len()));
+                if ui.button("Stop").clicked() {
+                    self.analysis_run = None;
+                }
+            }
+            None => {
+                if ui.button("Analyse").clicked() {
+                    self.start_analysis();
+                }
+            }
+        }
+        if self.analysis.is_empty() {
+            return;
+        }
+        self.graph_ui(ui);
+        let score = self.analysis[self.ply].as_ref().and_then(|analysis| analysis.score);
+        match score {
+            Some(score) => ui.label(format!("Score for red: {score:+}")),
+            None => ui.label("Score for red: -"),
+        };
+    }
+
+    /// Plots red's score at every ply, with blunders marked, and jumps to the ply clicked
+    fn graph_ui(&mut self, ui: &mut Ui) {
+        let (response, painter) =
+            ui.allocate_painter(egui::vec2(ui.available_width(), 120.0), egui::Sense::click());
+        let rect = response.rect;
+        painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+        let plies = self.moves.len().max(1) as f32;
+        let x = |ply: usize| rect.left() + rect.width() * ply as f32 / plies;
+        let y = |score: i32| {
+            rect.center().y - rect.height() / 2.0 * score.clamp(-GRAPH_RANGE, GRAPH_RANGE) as f32 / GRAPH_RANGE as f32
+        };
+        let axis_stroke = egui::Stroke::new(1.0, ui.visuals().weak_text_color());
+        painter.line_segment([egui::pos2(rect.left(), y(0)), egui::pos2(rect.right(), y(0))], axis_stroke);
+        painter.line_segment([egui::pos2(x(self.ply), rect.top()), egui::pos2(x(self.ply), rect.bottom())], axis_stroke);
+
+        let points: Vec<Option<egui::Pos2>> = self
+            .analysis
+            .iter()
+            .enumerate()
+            .map(|(ply, analysis)| Some(egui::pos2(x(ply), y(analysis.as_ref()?.score?))))
+            .collect();
+        let line_stroke = egui::Stroke::new(1.5, ui.visuals().strong_text_color());
+        for pair in points.windows(2) {
+            if let [Some(from), Some(to)] = *pair {
+                painter.line_segment([from, to], line_stroke);
+            }
+        }
+        // Marked on the position the blunder led to
+        for ply in blunders(&self.analysis, self.blunder_threshold) {
+            if let Some(point) = points[ply + 1] {
+                painter.circle_filled(point, 3.0, egui::Color32::RED);
+            }
+        }
+
+        if response.clicked()
+            && let Some(pointer) = response.interact_pointer_pos()
+        {
+            let ply = ((pointer.x - rect.left()) / rect.width() * plies).round();
+            self.ply = (ply.max(0.0) as usize).min(self.moves.len());
+        }
+    }
+
+    fn board(&self) -> Board {
+        let mut board = self.start.clone();
+        for game_move in &self.moves[..self.ply] {
+            board.make_move_unchecked(game_move.clone());
+        }
+        board
+    }
+
+    /// Returns whether the ply changed
+    fn make_ui(&mut self, ui: &mut Ui) -> bool {
+        let old_ply = self.ply;
+        ui.label("Replay");
+        ui.label(&self.title);
+        ui.horizontal(|ui| {
+            if ui.button("|<").clicked() {
+                self.ply = 0;
+            }
+            if ui.button("<").clicked() {
+                self.ply = self.ply.saturating_sub(1);
+            }
+            if ui.button(">").clicked() {
+                self.ply = (self.ply + 1).min(self.moves.len());
+            }
+            if ui.button(">|").clicked() {
+                self.ply = self.moves.len();
+            }
+        });
+        ui.add(egui::Slider::new(&mut self.ply, 0..=self.moves.len()).text("Ply"));
+        ui.horizontal(|ui| {
+            if ui.checkbox(&mut self.autoplay, "Autoplay").changed() {
+                self.last_step = Instant::now();
+            }
+            ui.add(
+                egui::DragValue::new(&mut self.interval_ms)
+                    .suffix("ms")
+                    .range(50..=5000),
+            );
+            ui.label("Per move");
+        });
+        ui.separator();
+        self.analysis_ui(ui);
+        ui.separator();
+        let blunders = blunders(&self.analysis, self.blunder_threshold);
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            if ui.selectable_label(self.ply == 0, "Start").clicked() {
+                self.ply = 0;
+            }
+            let red_starts = self.start.red_to_move();
+            for (i, game_move) in self.moves.iter().enumerate() {
+                let red = (i % 2 == 0) == red_starts;
+                let mut text = format!("{}. {}: {game_move}", i + 1, if red { "Red" } else { "Blue" });
+                let best_move = self.analysis.get(i).and_then(|analysis| analysis.as_ref()?.best_move.as_ref());
+                if let Some(best_move) = best_move
+                    && best_move != game_move
+                {
+                    text.push_str(&format!(" (best {best_move})"));
+                }
+                let text = if blunders.contains(&i) {
+                    egui::RichText::new(format!("{text} ??")).color(egui::Color32::RED)
+                } else {
+                    egui::RichText::new(text)
+                };
+                if ui.selectable_label(self.ply == i + 1, text).clicked() {
+                    self.ply = i + 1;
+                }
+            }
+        });
+        self.ply != old_ply
+    }
+
+    /// Plays the next move when autoplaying and it is time to, returning whether the ply changed
+    fn update(&mut self) -> bool {
+        self.update_analysis();
+        if !self.autoplay || self.last_step.elapsed() < Duration::from_millis(self.interval_ms) {
+            return false;
+        }
+        self.last_step = Instant::now();
+        if self.ply >= self.moves.len() {
+            self.autoplay = false;
+            return false;
+        }
+        self.ply += 1;
+        true
+    }
+}
+
+/// Sets up a custom position to add as a stored match
+struct BoardEditor {
+    open: bool,
+    squares: [Option<Piece>; 25],
+    /// Indices into `cards::ALL_CARDS`, in .oni order: red's two cards, blue's two cards and the transfer card
+    cards: [usize; 5],
+    red_to_move: bool,
+    /// Piece placed by clicking a square, `None` to remove pieces
+    brush: Option<Piece>,
+    error: Option<String>,
+}
+impl BoardEditor {
+    fn new() -> Self {
+        let mut editor = Self {
+            open: false,
+            squares: [None; 25],
+            cards: [0; 5],
+            red_to_move: true,
+            brush: Some(Piece::RedDisciple),
+            error: None,
+        };
+        editor.load(&Board::default());
+        editor
+    }
+
+    /// Starts editing from the position on the board
+    fn load(&mut self, board: &Board) {
+        self.squares = *board.squares();
+        self.cards = board.cards().map(|card| cards::index_of_card(&card));
+        self.red_to_move = board.red_to_move();
+        self.error = None;
+    }
+
+    /// Returns the position when it is valid and added as a match
+    fn make_ui(&mut self, ui: &mut Ui, game: &Board) -> Option<Board> {
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.brush, Some(Piece::RedDisciple), "Red disciple");
+            ui.selectable_value(&mut self.brush, Some(Piece::RedSensei), "Red sensei");
+        });
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.brush, Some(Piece::BlueDisciple), "Blue disciple");
+            ui.selectable_value(&mut self.brush, Some(Piece::BlueSensei), "Blue sensei");
+            ui.selectable_value(&mut self.brush, None, "Remove");
+        });
+        ui.separator();
+        ui.label("Blue side");
+        egui::Grid::new("editor squares").spacing([2.0, 2.0]).show(ui, |ui| {
+            for row in 0..5 {
+                for col in 0..5 {
+                    let square = &mut self.squares[row * 5 + col];
+                    let text = match square {
+                        None => egui::RichText::new("·"),
+                        Some(Piece::RedDisciple) => egui::RichText::new("d").color(egui::Color32::RED),
+                        Some(Piece::RedSensei) => egui::RichText::new("S").color(egui::Color32::RED).strong(),
+                        Some(Piece::BlueDisciple) => egui::RichText::new("d").color(egui::Color32::LIGHT_BLUE),
+                        Some(Piece::BlueSensei) => {
+                            egui::RichText::new("S").color(egui::Color32::LIGHT_BLUE).strong()
+                        }
+                    };
+                    if ui.add(egui::Button::new(text.monospace()).min_size(egui::vec2(28.0, 28.0))).clicked() {
+                        // Clicking a square holding the brush's piece empties it
+                        *square = if *square == self.brush { None } else { self.brush };
+                    }
+                }
+                ui.end_row();
+            }
+        });
+        ui.label("Red side");
+        ui.separator();
+        let labels = ["Red card", "Red card", "Blue card", "Blue card", "Transfer card"];
+        for (i, label) in labels.into_iter().enumerate() {
+            egui::ComboBox::from_id_salt(("editor card", i))
+                .selected_text(cards::ALL_CARDS[self.cards[i]].to_string())
+                .show_ui(ui, |ui| {
+                    for (index, card) in cards::ALL_CARDS.iter().enumerate() {
+                        ui.selectable_value(&mut self.cards[i], index, card.to_string());
+                    }
+                });
+            ui.label(label);
+        }
+        ui.horizontal(|ui| {
+            ui.label("To move");
+            ui.radio_value(&mut self.red_to_move, true, "Red");
+            ui.radio_value(&mut self.red_to_move, false, "Blue");
+        });
+        ui.separator();
+        let mut added = None;
+        ui.horizontal(|ui| {
+            if ui.button("From board").clicked() {
+                self.load(game);
+            }
+            if ui.button("Clear").clicked() {
+                self.squares = [None; 25];
+            }
+            if ui.button("Add match").clicked() {
+                let cards = self.cards.map(|index| cards::ALL_CARDS[index]);
+                match Board::from_position(self.squares, cards, self.red_to_move) {
+                    Ok(board) => {
+                        self.error = None;
+                        added = Some(board);
+                    }
+                    Err(err) => self.error = Some(err.to_string()),
+                }
+            }
+        });
+        if let Some(err) = &self.error {
+            ui.colored_label(egui::Color32::RED, err);
+        }
+        added
+    }
+}
+
+/// Analysis running on a background thread, cancelled when dropped
+struct AnalysisRun {
+    receiver: Receiver<(usize, PositionAnalysis)>,
+    cancel: Arc<AtomicBool>,
+}
+impl Drop for AnalysisRun {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+enum SuiteMessage {
+    /// Positions found and candidates tried so far
+    Progress(usize, usize),
+    Done(Vec<SuitePosition>),
+}
+
+/// Balanced position generation running on a background thread, cancelled when dropped
+struct SuiteRun {
+    receiver: Receiver<SuiteMessage>,
+    cancel: Arc<AtomicBool>,
+    found: usize,
+    attempts: usize,
+}
+impl Drop for SuiteRun {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
 struct PositionGeneration {
     bulk_number: u32,
     rng: StdRand,
+    /// Settings for balanced positions, the number of positions comes from `bulk_number`
+    suite_config: SuiteConfig,
+    suite_run: Option<SuiteRun>,
+    /// File the matches are loaded from and saved to, as game records
+    path: String,
+    error: Option<String>,
 }
 impl PositionGeneration {
     fn new() -> Self {
         Self {
             bulk_number: 1,
-            rng: StdRand::default(),
+            rng: StdRand::seed(ClockSeed.next_u64()),
+            suite_config: SuiteConfig::default(),
+            suite_run: None,
+            path: PREGEN_PATH.to_string(),
+            error: None,
         }
     }
 
+    /// Returns the index of the stored match clicked, if any. Nothing can be clicked while `ui` is disabled
     pub fn make_ui(
         &mut self,
         ui: &mut Ui,
         game: &mut Board,
-        stored_matches: &mut Vec<Match>,
+        stored_matches: &mut Vec<GameRecord>,
         current_match_index: &mut usize,
-    ) {
+    ) -> Option<usize> {
+        let mut clicked = None;
         let mut generate_match = |new_board: Board| {
             *game = new_board;
             *current_match_index = stored_matches.len();
-            stored_matches.push((game.save_game(false), GameStatus::Playing));
+            stored_matches.push(GameRecord::unplayed(game.save_game(false)));
         };
         ui.label("Starting positions");
         if ui.button("Random position").clicked() {
-            generate_match(self.generate_random_position());
+            generate_match(random_start(&mut self.rng, RANDOM_POSITION_PLIES));
         }
         ui.horizontal(|ui| {
             if ui.button("Bulk generate").clicked() {
                 for _ in 0..self.bulk_number {
-                    generate_match(self.generate_random_position());
+                    generate_match(random_start(&mut self.rng, RANDOM_POSITION_PLIES));
                 }
             }
             ui.add(egui::DragValue::new(&mut self.bulk_number).range(1..=1000));
         });
-        ui.label("Pregenerated matches");
+        // A suite finished during a match is added once the match is over
+        if ui.is_enabled()
+            && let Some(suite) = self.update_suite()
+        {
+            for position in suite {
+                generate_match(position.board);
+            }
+        }
+        match &self.suite_run {
+            Some(run) => {
+                let text = format!(
+                    "Generating {}/{} after {} candidates",
+                    run.found, self.suite_config.positions, run.attempts
+                );
+                ui.horizontal(|ui| {
+                    ui.label(text);
+                    if ui.button("Stop").clicked() {
+                        self.suite_run = None;
+                    }
+                });
+            }
+            None => {
+                ui.horizontal(|ui| {
+                    if ui.button("Balanced generate").clicked() {
+                        self.start_suite();
+                    }
+                    ui.add(egui::DragValue::new(&mut self.suite_config.window).range(0..=1000));
+                    ui.label("Window");
+                    ui.add(egui::DragValue::new(&mut self.suite_config.depth).range(1..=10));
+                    ui.label("Depth");
+                });
+            }
+        }
+        ui.label("Matches");
         ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.path).desired_width(140.0));
             if ui.button("Load").clicked() {
-                *stored_matches = load_matches_from_file();
-                *current_match_index = 0;
-                *game = Board::load_game(&stored_matches[0].0).unwrap();
+                match load_records(&self.path) {
+                    Ok(records) if records.is_empty() => self.error = Some("no matches in file".to_string()),
+                    Ok(records) => {
+                        *stored_matches = records;
+                        *current_match_index = 0;
+                        *game = stored_matches[0].start_board();
+                        self.error = None;
+                    }
+                    Err(err) => self.error = Some(err.to_string()),
+                }
             }
             if ui.button("Save").clicked() {
-                save_matches_to_file(stored_matches);
+                self.error = save_records(&self.path, stored_matches).err().map(|err| err.to_string());
             }
         });
+        if let Some(err) = &self.error {
+            ui.colored_label(egui::Color32::RED, err);
+        }
         ui.group(|ui| {
-            for (i, (game_str, game_status)) in stored_matches.iter().enumerate() {
-                match game_status {
+            for (i, record) in stored_matches.iter().enumerate() {
+                match record.result {
                     GameStatus::RedWon => {
                         ui.visuals_mut().override_text_color = Some(egui::Color32::RED)
                     }
@@ -436,82 +1386,66 @@ impl PositionGeneration {
                     GameStatus::Playing => ui.reset_style(),
                 }
 
-                let mut label_response = ui.label(i.to_string() + ": " + game_str);
+                let text = if let Some(reason) = &record.forfeit {
+                    format!("{i}: {} vs {}, {} (forfeit, {reason})", record.red, record.blue, record.start)
+                } else if record.is_played() {
+                    format!("{i}: {} vs {}, {}", record.red, record.blue, record.start)
+                } else {
+                    format!("{i}: {}", record.start)
+                };
+                let mut label_response = ui.label(text);
                 if label_response.hovered() {
                     label_response = label_response.highlight()
                 }
                 if label_response.clicked() {
-                    *game = Board::load_game(game_str).unwrap();
+                    *game = record.start_board();
                     *current_match_index = i;
+                    clicked = Some(i);
                 }
             }
         });
+        clicked
     }
 
-    fn generate_random_position(&mut self) -> Board {
-        const MAX_MOVES: u32 = 10;
-        let n = self.rng.next_range(0..MAX_MOVES);
-        let mut board;
-        loop {
-            board = Board::random_cards();
-            for _ in 0..n {
-                let legal_moves: Vec<GameMove> = board
-                    .piece_positions()
-                    .into_iter()
-                    .flat_map(|pos| board.legal_moves_from_pos(pos))
-                    .collect();
-                let game_move = legal_moves[self.rng.next_range(0..legal_moves.len())].clone();
-                board.make_move_unchecked(game_move);
-            }
-            if !Self::one_move_from_winning(&mut board) {
-                break;
-            }
-        }
-        board
+    /// Generates `bulk_number` balanced positions on a background thread
+    fn start_suite(&mut self) {
+        self.suite_config.positions = self.bulk_number as usize;
+        let (config, seed) = (self.suite_config.clone(), self.rng.next_u64());
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (sender, receiver) = mpsc::channel();
+        let thread_cancel = cancel.clone();
+        std::thread::spawn(move || {
+            let suite = generate_suite(&config, seed, &thread_cancel, |found, attempts| {
+                let _ = sender.send(SuiteMessage::Progress(found, attempts));
+            });
+            let _ = sender.send(SuiteMessage::Done(suite));
+        });
+        self.suite_run = Some(SuiteRun { receiver, cancel, found: 0, attempts: 0 });
     }
 
-    /// Doesn't modify board despite the mutable borrow
-    fn one_move_from_winning(board: &mut Board) -> bool {
-        let legal_moves: Vec<GameMove> = board
-            .piece_positions()
-            .into_iter()
-            .flat_map(|pos| board.legal_moves_from_pos(pos))
-            .collect();
-        for game_move in legal_moves {
-            board.make_move_unchecked(game_move);
-            let winning =
-                board.status() == GameStatus::RedWon || board.status() == GameStatus::BlueWon;
-            board.undo_move();
-            if winning {
-                return true;
+    /// Collects the progress of the generation, returning the suite once it is done
+    fn update_suite(&mut self) -> Option<Vec<SuitePosition>> {
+        let run = self.suite_run.as_mut()?;
+        loop {
+            match run.receiver.try_recv() {
+                Ok(SuiteMessage::Progress(found, attempts)) => (run.found, run.attempts) = (found, attempts),
+                Ok(SuiteMessage::Done(suite)) => {
+                    self.suite_run = None;
+                    return Some(suite);
+                }
+                Err(TryRecvError::Empty) => return None,
+                Err(TryRecvError::Disconnected) => {
+                    self.suite_run = None;
+                    return None;
+                }
             }
         }
-        false
     }
 }
 
 const PREGEN_PATH: &str = "assets/arena_pregens.oni.txt";
-fn load_matches_from_file() -> Vec<Match> {
-    let reader = std::io::BufReader::new(
-        std::fs::File::open(PREGEN_PATH).expect("failed to open pregens file"),
-    );
-    let mut out = vec![];
-    for line in reader.lines() {
-        out.push((line.expect("failed to read line"), GameStatus::Playing));
-    }
-    out
-}
-fn save_matches_to_file(matches: &[Match]) {
-    let mut writer = std::io::BufWriter::new(
-        std::fs::File::create(PREGEN_PATH).expect("failed to open pregens file"),
-    );
-    for (game_str, _) in matches {
-        writer
-            .write_fmt(format_args!("{game_str}\n"))
-            .expect("failed to write line");
-    }
-}
-
+/// Most random moves played from the cards of a generated position
+const RANDOM_POSITION_PLIES: usize = 9;
 fn from_egui_rect(rect: egui::Rect) -> onitama::graphics::Rect {
     let (min_x, min_y) = (rect.left_top().x, rect.left_top().y);
     let (max_x, max_y) = (rect.right_bottom().x, rect.right_bottom().y);
@@ -0,0 +1,134 @@
+//! Node-count benchmark for the MinMaxV4 search enhancements
+//!
+//! Usage: bench [options]
+//!   --positions <file>   positions to search, one game in .oni format per line (default: bench/positions.oni.txt)
+//!   --depth <n>          search depth (default: 6)
+//!   --threads <n>        search threads (default: 1)
+//!
+//! Every position is searched once per configuration, each enabling one more enhancement than the last,
+//! so the node counts show what each enhancement contributes on top of the previous ones.
+use std::{
+    process::exit,
+    sync::{Arc, atomic::AtomicBool},
+    time::{Duration, Instant},
+};
+
+use onitama::{
+    ai::{AIOpponent, MinMaxV4, SearchOptions, move_ordering::OrderingOptions},
+    game::Board,
+};
+
+struct Options {
+    positions: String,
+    depth: u32,
+    threads: usize,
+}
+
+fn configurations() -> Vec<(&'static str, SearchOptions)> {
+    let mut options = SearchOptions {
+        quiescence: true,
+        transposition_table: false,
+        ordering: OrderingOptions {
+            hash_move: false,
+            killer_moves: false,
+            history: false,
+        },
+        late_move_reductions: false,
+    };
+    let mut configurations = vec![("Captures first", options)];
+    options.transposition_table = true;
+    configurations.push(("+ Transposition table", options));
+    options.ordering.hash_move = true;
+    configurations.push(("+ Hash move", options));
+    options.ordering.killer_moves = true;
+    configurations.push(("+ Killer moves", options));
+    options.ordering.history = true;
+    configurations.push(("+ History", options));
+    options.late_move_reductions = true;
+    configurations.push(("+ Late move reductions", options));
+    configurations
+}
+
+fn main() {
+    let options = parse_args().unwrap_or_else(|err| {
+        eprintln!("{err}");
+        eprintln!("usage: bench [--positions <file>] [--depth <n>] [--threads <n>]");
+        exit(1)
+    });
+
+    let text = std::fs::read_to_string(&options.positions).unwrap_or_else(|err| {
+        eprintln!("{}: {err}", options.positions);
+        exit(1)
+    });
+    let mut positions = vec![];
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        match Board::load_game(line) {
+            Ok(board) if !board.finished() => positions.push(board),
+            Ok(_) => eprintln!("{}:{}: skipping finished game", options.positions, i + 1),
+            Err(err) => eprintln!("{}:{}: skipping game, {err}", options.positions, i + 1),
+        }
+    }
+    println!(
+        "{} positions at depth {} with {} threads",
+        positions.len(),
+        options.depth,
+        options.threads
+    );
+    println!(
+        "{:<24}{:>14}{:>12}{:>10}",
+        "Configuration", "Nodes", "Time", "Same move"
+    );
+
+    let mut baseline_moves = vec![];
+    for (name, search_options) in configurations() {
+        let ai = MinMaxV4::new(options.depth)
+            .with_options(search_options)
+            .with_threads(options.threads);
+        let mut nodes = 0;
+        let mut time = Duration::ZERO;
+        let mut same_moves = 0;
+        for (i, board) in positions.iter().enumerate() {
+            let start = Instant::now();
+            let result = ai.search_with_info(Arc::new(AtomicBool::new(false)), board.clone(), None);
+            time += start.elapsed();
+            nodes += result.nodes;
+            if baseline_moves.len() < positions.len() {
+                baseline_moves.push(result.best_move.clone());
+            }
+            if baseline_moves[i] == result.best_move {
+                same_moves += 1;
+            }
+        }
+        println!(
+            "{:<24}{:>14}{:>11.2}s{:>10}",
+            name,
+            nodes,
+            time.as_secs_f64(),
+            format!("{same_moves}/{}", positions.len())
+        );
+    }
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        positions: "bench/positions.oni.txt".to_string(),
+        depth: 6,
+        threads: 1,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {arg}"));
+        match arg.as_str() {
+            "--positions" => options.positions = value()?,
+            "--depth" => options.depth = value()?.parse().map_err(|_| "invalid depth")?,
+            "--threads" => {
+                options.threads = value()?.parse().map_err(|_| "invalid thread count")?
+            }
+            _ => return Err(format!("unknown option {arg}")),
+        }
+    }
+    Ok(options)
+}
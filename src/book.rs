@@ -0,0 +1,139 @@
+//! Builds an opening book from finished games and deep searches
+//!
+//! Usage: book [options] [games file]...
+//!   --out <file>            where to write the book (default: book.txt)
+//!   --book <file>           existing book to add to
+//!   --plies <n>             number of moves added from the start of every game (default: 12)
+//!   --search <cards>        card set to search openings for, as 5 card identifiers in .oni order, can be repeated
+//!   --search-plies <n>      how many plies from the start to search every position of (default: 2)
+//!   --depth <n>             depth of the opening searches (default: 8)
+//!
+//! Games files hold one game in .oni format per line, lines starting with '#' are ignored.
+use std::{
+    process::exit,
+    sync::{Arc, atomic::AtomicBool},
+};
+
+use onitama::{
+    ai::{AIOpponent, MinMaxV4, opening_book::OpeningBook},
+    game::Board,
+};
+
+// A searched move counts as much as this many won games
+const SEARCH_WEIGHT: u32 = 4;
+
+struct Options {
+    games_files: Vec<String>,
+    out: String,
+    book: Option<String>,
+    plies: usize,
+    search: Vec<String>,
+    search_plies: usize,
+    depth: u32,
+}
+
+fn main() {
+    let options = parse_args().unwrap_or_else(|err| {
+        eprintln!("{err}");
+        eprintln!(
+            "usage: book [--out <file>] [--book <file>] [--plies <n>] [--search <cards>] [--search-plies <n>] [--depth <n>] [games file]..."
+        );
+        exit(1)
+    });
+
+    let mut book = match &options.book {
+        Some(path) => OpeningBook::load(path).unwrap_or_else(|err| {
+            eprintln!("{path}: {err}");
+            exit(1)
+        }),
+        None => OpeningBook::new(),
+    };
+
+    let mut num_games = 0;
+    for path in &options.games_files {
+        let text = std::fs::read_to_string(path).unwrap_or_else(|err| {
+            eprintln!("{path}: {err}");
+            exit(1)
+        });
+        for (i, line) in text.lines().enumerate() {
+            if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                continue;
+            }
+            match Board::load_game(line) {
+                Ok(game) => {
+                    if book.add_game(&game, options.plies) {
+                        num_games += 1;
+                    }
+                }
+                Err(err) => eprintln!("{path}:{}: skipping game, {err}", i + 1),
+            }
+        }
+    }
+    println!("Added {num_games} finished games");
+
+    let ai = MinMaxV4::new(options.depth);
+    for cards in &options.search {
+        let start = Board::load_game(cards).unwrap_or_else(|err| {
+            eprintln!("--search {cards}: {err}");
+            exit(1)
+        });
+        // Every position up to the given number of plies, breadth first
+        let mut positions = vec![start];
+        for ply in 0..=options.search_plies {
+            let mut next_positions = vec![];
+            for board in &positions {
+                let game_move = ai.search(Arc::new(AtomicBool::new(false)), board.clone(), None);
+                book.add_move(board, &game_move, SEARCH_WEIGHT);
+                if ply < options.search_plies {
+                    for game_move in board.legal_moves() {
+                        let mut next = board.clone();
+                        next.make_move_unchecked(game_move);
+                        if !next.finished() {
+                            next_positions.push(next);
+                        }
+                    }
+                }
+            }
+            println!("{cards}: searched {} positions at ply {ply}", positions.len());
+            positions = next_positions;
+        }
+    }
+
+    if let Err(err) = book.save(&options.out) {
+        eprintln!("{}: {err}", options.out);
+        exit(1);
+    }
+    println!("Wrote {} positions to {}", book.len(), options.out);
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        games_files: vec![],
+        out: "book.txt".to_string(),
+        book: None,
+        plies: 12,
+        search: vec![],
+        search_plies: 2,
+        depth: 8,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {arg}"));
+        match arg.as_str() {
+            "--out" => options.out = value()?,
+            "--book" => options.book = Some(value()?),
+            "--plies" => options.plies = value()?.parse().map_err(|_| "invalid ply count")?,
+            "--search" => options.search.push(value()?),
+            "--search-plies" => {
+                options.search_plies = value()?.parse().map_err(|_| "invalid ply count")?
+            }
+            "--depth" => options.depth = value()?.parse().map_err(|_| "invalid depth")?,
+            _ if arg.starts_with("--") => return Err(format!("unknown option {arg}")),
+            _ => options.games_files.push(arg),
+        }
+    }
+    if options.games_files.is_empty() && options.search.is_empty() {
+        return Err("no games files or card sets given".to_string());
+    }
+    Ok(options)
+}
@@ -0,0 +1,183 @@
+//! Runs one of the built-in bots as an engine, talking the text protocol described in engine_protocol.md
+//!
+//! Usage: engine [options]
+//!   --ai <name>           bot to run, as named in the arena, eg. MinMaxV4 or "Level: Hard" (default: MinMaxV4)
+//!   --params <file>       evaluator parameters for bots that use them
+//!   --network <file>      evaluates positions in MinMaxV4 and the difficulty levels with a trained network
+//!   --tablebase <file>    endgame tablebase probed by MinMaxV4 and the difficulty levels
+//!   --threads <n>         threads MinMaxV4 and the difficulty levels search with (default: 1)
+//!   --hash <MiB>          transposition table size of MinMaxV4 and the difficulty levels (default: 16)
+//!   --no-quiescence       turns off the quiescence search of MinMaxV4
+use std::{
+    io::{BufRead, Write},
+    process::exit,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use onitama::{
+    ai::{AIOpponent, AIVersion, BotSettings, evaluation::Evaluator, network::Network, tablebase::Tablebase},
+    game::Board,
+};
+
+struct Options {
+    ai: AIVersion,
+    settings: BotSettings,
+}
+
+/// A search running on its own thread, which prints its result when done
+struct Search {
+    cancel_signal: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+impl Search {
+    fn stop(self) {
+        self.cancel_signal.store(true, Ordering::Relaxed);
+        self.handle.join().expect("Search thread panicked");
+    }
+}
+
+fn main() {
+    let options = parse_args().unwrap_or_else(|err| {
+        eprintln!("{err}");
+        eprintln!("usage: engine [--ai <name>] [--params <file>] [--network <file>] [--tablebase <file>] [--threads <n>] [--hash <MiB>] [--no-quiescence]");
+        exit(1)
+    });
+    let mut ai = options.ai.make_opponent(&options.settings);
+    // `None` after an invalid position, so the old position isn't searched by mistake
+    let mut board = Some(Board::default());
+    let mut search: Option<Search> = None;
+
+    for line in std::io::stdin().lock().lines() {
+        let Ok(line) = line else { break };
+        let line = line.trim();
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        match command {
+            "oni" => {
+                respond(&format!("id name {}", options.ai));
+                respond("oniok");
+            }
+            "isready" => respond("readyok"),
+            "newgame" => {
+                if let Some(search) = search.take() {
+                    search.stop();
+                }
+                // Fresh bot, so nothing carries over from the previous game
+                ai = options.ai.make_opponent(&options.settings);
+            }
+            "position" => match Board::load_game(rest) {
+                Ok(new_board) => board = Some(new_board),
+                Err(err) => {
+                    eprintln!("invalid position: {err}");
+                    board = None;
+                }
+            },
+            "go" => {
+                if let Some(search) = search.take() {
+                    search.stop();
+                }
+                let Some(board) = &board else {
+                    eprintln!("no valid position to search");
+                    continue;
+                };
+                if board.finished() || board.legal_moves().is_empty() {
+                    eprintln!("no moves to search");
+                    continue;
+                }
+                let mut words = rest.split_whitespace();
+                let mut move_time = None;
+                while let Some(word) = words.next() {
+                    if word == "movetime" {
+                        move_time = words.next().and_then(|ms| ms.parse().ok()).map(Duration::from_millis);
+                    }
+                }
+                search = Some(start_search(ai.clone(), board.clone(), move_time));
+            }
+            "stop" => {
+                if let Some(search) = search.take() {
+                    search.stop();
+                }
+            }
+            "quit" => break,
+            _ => (),
+        }
+    }
+    if let Some(search) = search.take() {
+        search.stop();
+    }
+}
+
+fn start_search(ai: Arc<dyn AIOpponent>, board: Board, move_time: Option<Duration>) -> Search {
+    let cancel_signal = Arc::new(AtomicBool::new(false));
+    if let Some(move_time) = move_time {
+        // The timer only ever touches this search's signal, so it can't stop a later search
+        let cancel_signal = cancel_signal.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(move_time);
+            cancel_signal.store(true, Ordering::Relaxed);
+        });
+    }
+    let thread_signal = cancel_signal.clone();
+    let handle = std::thread::spawn(move || {
+        let result = ai.search_with_info(thread_signal, board, move_time);
+        let mut info = format!("info depth {} nodes {}", result.depth, result.nodes);
+        if let Some(score) = result.score {
+            info.push_str(&format!(" score {score}"));
+        }
+        respond(&info);
+        respond(&format!("bestmove {}", String::from_utf8_lossy(&result.best_move.as_encoded_bytes())));
+    });
+    Search { cancel_signal, handle }
+}
+
+fn respond(line: &str) {
+    let mut stdout = std::io::stdout().lock();
+    let _ = writeln!(stdout, "{line}");
+    let _ = stdout.flush();
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        ai: AIVersion::MinMaxV4,
+        settings: BotSettings::default(),
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {arg}"));
+        match arg.as_str() {
+            "--ai" => {
+                let name = value()?;
+                options.ai = AIVersion::from_name(&name).ok_or(format!("unknown bot {name}"))?;
+            }
+            "--params" => {
+                let path = value()?;
+                options.settings.evaluator = Some(Evaluator::load(&path).map_err(|err| format!("{path}: {err}"))?);
+            }
+            "--network" => {
+                let path = value()?;
+                let network = Network::load(&path).map_err(|err| format!("{path}: {err}"))?;
+                options.settings = options.settings.with_network(Arc::new(network));
+            }
+            "--tablebase" => {
+                let path = value()?;
+                let tablebase = Tablebase::load(&path).map_err(|err| format!("{path}: {err}"))?;
+                options.settings = options.settings.with_tablebase(Arc::new(tablebase));
+            }
+            "--threads" => {
+                let threads = value()?.parse().map_err(|_| "invalid thread count")?;
+                options.settings = options.settings.with_threads(threads);
+            }
+            "--hash" => {
+                let mib = value()?.parse().map_err(|_| "invalid hash size")?;
+                options.settings = options.settings.with_hash_size(mib);
+            }
+            "--no-quiescence" => options.settings.quiescence = false,
+            _ => return Err(format!("unknown option {arg}")),
+        }
+    }
+    Ok(options)
+}
@@ -54,6 +54,24 @@ impl GameMove {
     }
 }
 
+/// Readable notation, the card followed by the squares moved from and to, eg. `BOAR c1-c2` or `TIGER b1xb3` for a
+/// capture. Squares are named like in chess from red's side: files `a` to `e` left to right, ranks 1 to 5 with
+/// red's temple on rank 1, and sensei moves are marked with an `S`
+impl std::fmt::Display for GameMove {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let square = |pos: Pos| format!("{}{}", (b'a' + pos.1 as u8) as char, 5 - pos.0);
+        let sensei = if matches!(self.moved_piece, Piece::RedSensei | Piece::BlueSensei) { "S" } else { "" };
+        let separator = if self.captured_piece.is_some() { 'x' } else { '-' };
+        write!(
+            f,
+            "{} {sensei}{}{separator}{}",
+            self.used_card,
+            square(self.start_pos),
+            square(self.end_pos)
+        )
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Board {
     squares: [Option<Piece>; 25],
@@ -66,6 +84,8 @@ pub struct Board {
     state_history: Vec<u64>,
     default_start: bool,
     initial_cards: [Card; 5],
+    initial_squares: [Option<Piece>; 25],
+    initial_red_to_move: bool,
 }
 impl Default for Board {
     /// Default board setup with no moves taken and using the first five cards of `cards::ALL_CARDS`,
@@ -84,6 +104,8 @@ impl Default for Board {
             state_history: Vec::with_capacity(20),
             default_start: true,
             initial_cards,
+            initial_squares: squares,
+            initial_red_to_move: true,
         };
         board.state_history.push(board.state_hash());
         board
@@ -104,10 +126,57 @@ impl Board {
             state_history: Vec::with_capacity(20),
             default_start: true,
             initial_cards: rand_cards,
+            initial_squares: squares,
+            initial_red_to_move: true,
         };
         board.state_history.push(board.state_hash());
         board
     }
+
+    /// Sets up a custom position, with `cards` in the same order as in .oni format: red's two cards, blue's two
+    /// cards and the transfer card. Fails if it isn't a position a game could continue from
+    pub fn from_position(
+        squares: [Option<Piece>; 25],
+        cards: [Card; 5],
+        red_to_move: bool,
+    ) -> Result<Self, PositionError> {
+        for sensei in [Piece::RedSensei, Piece::BlueSensei] {
+            if squares.iter().filter(|&&square| square == Some(sensei)).count() != 1 {
+                return Err(PositionError::SenseiCount);
+            }
+        }
+        // `state_hash` only has room for the four disciples a side starts with
+        for disciple in [Piece::RedDisciple, Piece::BlueDisciple] {
+            if squares.iter().filter(|&&square| square == Some(disciple)).count() > 4 {
+                return Err(PositionError::TooManyPieces);
+            }
+        }
+        // A sensei on the enemy temple has already won
+        if squares[Pos(0, 2).to_index()] == Some(Piece::RedSensei)
+            || squares[Pos(4, 2).to_index()] == Some(Piece::BlueSensei)
+        {
+            return Err(PositionError::SenseiOnTemple);
+        }
+        if (1..5).any(|i| cards[..i].contains(&cards[i])) {
+            return Err(PositionError::DuplicateCard);
+        }
+        let mut board = Board {
+            squares,
+            red_to_move,
+            red_cards: (cards[0], cards[1]),
+            blue_cards: (cards[2], cards[3]),
+            transfer_card: cards[4],
+            game_status: GameStatus::Playing,
+            move_history: Vec::with_capacity(20),
+            state_history: Vec::with_capacity(20),
+            default_start: red_to_move && squares == Self::default_squares(),
+            initial_cards: cards,
+            initial_squares: squares,
+            initial_red_to_move: red_to_move,
+        };
+        board.state_history.push(board.state_hash());
+        Ok(board)
+    }
     #[rustfmt::skip]
     fn default_squares() -> [Option<Piece>; 25] {
         use Piece::*;
@@ -254,6 +323,9 @@ impl Board {
     pub fn game_length(&self) -> usize {
         self.move_history.len()
     }
+    pub fn move_history(&self) -> &[GameMove] {
+        &self.move_history
+    }
 
     pub fn legal_moves_from_pos(&self, start_pos: Pos) -> Vec<GameMove> {
         let mut legal_moves = Vec::with_capacity(2 * cards::LARGEST_CARD);
@@ -333,9 +405,25 @@ impl Board {
     pub fn save_game(&self, with_whitespace: bool) -> String {
         let mut save_game_str = String::new();
 
-        // Save initial board position
+        // Save initial board position, and who moves first if it isn't red
         if !self.default_start {
-            todo!("save game history from non-default start")
+            for row in self.initial_squares.chunks(5) {
+                for square in row {
+                    save_game_str.push(match square {
+                        None => '.',
+                        Some(Piece::RedDisciple) => '0',
+                        Some(Piece::BlueDisciple) => '1',
+                        Some(Piece::RedSensei) => '2',
+                        Some(Piece::BlueSensei) => '3',
+                    });
+                }
+                if with_whitespace {
+                    save_game_str.push('\n')
+                }
+            }
+            if !self.initial_red_to_move {
+                save_game_str.push('4');
+            }
         }
         if with_whitespace {
             save_game_str.push_str("\n\n")
@@ -409,6 +497,9 @@ impl Board {
             (squares, false)
         };
 
+        // Blue moves first if the board is followed by a '4'
+        let blue_first = !default_start && filtered_bytes.get(25) == Some(&b'4');
+
         fn byte_to_card(byte: u8) -> Card {
             cards::ALL_CARDS[cards::index_of_card_by_identifier(byte.to_ascii_uppercase())]
         }
@@ -416,6 +507,8 @@ impl Board {
         // Load cards
         let remaining_bytes = if default_start {
             &filtered_bytes[0..]
+        } else if blue_first {
+            &filtered_bytes[26..]
         } else {
             &filtered_bytes[25..]
         };
@@ -432,7 +525,7 @@ impl Board {
         // Make game board
         let mut game_board = Board {
             squares,
-            red_to_move: true,
+            red_to_move: !blue_first,
             red_cards,
             blue_cards,
             transfer_card,
@@ -447,8 +540,12 @@ impl Board {
                 blue_cards.1,
                 transfer_card,
             ],
+            initial_squares: squares,
+            initial_red_to_move: !blue_first,
         };
 
+        game_board.state_history.push(game_board.state_hash());
+
         // Load zero or more moves to move history, and execute those moves on the board
         let remaining_bytes = &remaining_bytes[5..];
         if remaining_bytes.len() % 3 != 0 {
@@ -488,6 +585,34 @@ impl Board {
                     && self.blue_cards.1 == other.blue_cards.0))
     }
 
+    /// Hashes the position itself, ignoring move history and the order of cards in hand
+    /// Unlike `state_hash`, this can be compared between boards that started with different cards,
+    /// but it is not perfect, so different positions may collide
+    pub fn position_hash(&self) -> u64 {
+        // FNV-1a, to stay stable between runs and builds
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut feed = |byte: u8| {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        };
+        for square in self.squares {
+            feed(square.map_or(0, |piece| piece as u8 + 1));
+        }
+        let sorted_pair = |(a, b): (Card, Card)| {
+            let (a, b) = (cards::index_of_card(&a) as u8, cards::index_of_card(&b) as u8);
+            [a.min(b), a.max(b)]
+        };
+        for index in sorted_pair(self.red_cards)
+            .into_iter()
+            .chain(sorted_pair(self.blue_cards))
+        {
+            feed(index);
+        }
+        feed(cards::index_of_card(&self.transfer_card) as u8);
+        feed(self.red_to_move as u8);
+        hash
+    }
+
     /// Creates perfect hash of the board state by packing all necessary info into a single value
     /// Only uniquely identifies the state given the same initial board as it relies on initial conditions
     /// to compress information, so hashes cannot be compared/reused with new or even superficially identical boards
@@ -578,7 +703,7 @@ impl Board {
         }
 
         let mut out = 0;
-        for piece in red_pieces.into_iter().chain(blue_pieces.into_iter()) {
+        for piece in red_pieces.into_iter().chain(blue_pieces) {
             out <<= 5;
             out |= piece;
         }
@@ -602,6 +727,30 @@ pub enum GameStatus {
     BlueWon,
 }
 
+/// Why a custom position can't be set up
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PositionError {
+    /// Red or blue doesn't have exactly one sensei
+    SenseiCount,
+    /// Red or blue has more than the four disciples a side starts with
+    TooManyPieces,
+    /// A sensei already stands on the enemy temple
+    SenseiOnTemple,
+    /// The same card is used more than once
+    DuplicateCard,
+}
+
+impl std::fmt::Display for PositionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SenseiCount => write!(f, "each side needs exactly one sensei"),
+            Self::TooManyPieces => write!(f, "each side can have at most four disciples"),
+            Self::SenseiOnTemple => write!(f, "a sensei is already on the enemy temple"),
+            Self::DuplicateCard => write!(f, "the five cards must be different"),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum LoadGameError {
     BoardParse,
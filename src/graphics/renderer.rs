@@ -120,7 +120,7 @@ impl SimpleRenderer {
                 vertex: wgpu::VertexState {
                     module: &shader_module,
                     entry_point: Some("vs_main"),
-                    buffers: &[vert_buffer_layout.clone()],
+                    buffers: std::slice::from_ref(&vert_buffer_layout),
                     compilation_options: wgpu::PipelineCompilationOptions::default(),
                 },
                 primitive: wgpu::PrimitiveState {
@@ -1,13 +1,17 @@
 use std::error::Error;
 use std::fs::File;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use glam::{Vec2, vec2};
-use onitama::ai::{AsyncAI, MinMaxV0};
+use onitama::ai::difficulty::Difficulty;
+use onitama::ai::opening_book::{BookOpponent, OpeningBook};
+use onitama::ai::{AIOpponent, AsyncAI, BotSettings};
 use onitama::game::{Board, GameMove, GameStatus};
 use onitama::graphics::{GFXState, Rect};
 use onitama::gui::GameGraphics;
 use rodio::{Decoder, OutputStream, OutputStreamBuilder, Sink, Source, source::Buffered};
+use strum::IntoEnumIterator;
 use winit::application::ApplicationHandler;
 use winit::dpi::LogicalSize;
 use winit::event::{ElementState, KeyEvent};
@@ -18,7 +22,6 @@ use winit::window::Window;
 const WIDTH: u32 = 1200;
 const HEIGHT: u32 = 800;
 const ANIM_TIME: f32 = 0.25;
-const MAX_AI_MOVE_TIME: Duration = Duration::from_millis(1000);
 
 #[derive(Clone)]
 pub struct Inputs {
@@ -29,6 +32,10 @@ pub struct Inputs {
 struct Application<'a> {
     gfx_state: Option<GFXState<'a>>,
     game: Option<OnitamaGame>,
+    difficulty: Difficulty,
+    book: Option<Arc<OpeningBook>>,
+    ponder: bool,
+    threads: usize,
     inputs: Inputs,
     timer: Instant,
 }
@@ -54,7 +61,8 @@ impl ApplicationHandler for Application<'_> {
             disciple_tex,
             sensei_tex,
         );
-        let game = OnitamaGame::new(game_graphics, game_board);
+        let game = OnitamaGame::new(game_graphics, game_board, self.difficulty, self.book.clone(), self.threads)
+            .with_pondering(self.ponder);
 
         self.gfx_state = Some(gfx_state);
         self.game = Some(game);
@@ -156,10 +164,66 @@ impl Application<'_> {
 fn main() {
     env_logger::init();
 
+    // --difficulty <level>: strength of the AI, one of Beginner, Easy, Medium, Hard, Expert or Master
+    // --book <file>: opening book for the AI, picking book moves at random by weight
+    // --ponder: let the AI think during the player's turn
+    // --threads <n>: threads the AI searches with
+    let mut difficulty = Difficulty::Medium;
+    let mut book = None;
+    let mut ponder = false;
+    let mut threads = 1;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--ponder" => ponder = true,
+            "--threads" => {
+                let value = args.next().unwrap_or_default();
+                match value.parse() {
+                    Ok(value) => threads = value,
+                    Err(_) => {
+                        eprintln!("invalid thread count '{value}'");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            "--book" => {
+                let path = args.next().unwrap_or_default();
+                match OpeningBook::load(&path) {
+                    Ok(loaded) => book = Some(Arc::new(loaded)),
+                    Err(err) => {
+                        eprintln!("{path}: {err}");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            "--difficulty" => {
+                let level = args.next().unwrap_or_default();
+                match Difficulty::iter().find(|d| d.to_string().eq_ignore_ascii_case(&level)) {
+                    Some(level) => difficulty = level,
+                    None => {
+                        let levels: Vec<String> = Difficulty::iter().map(|d| d.to_string()).collect();
+                        eprintln!("unknown difficulty '{level}', expected one of {}", levels.join(", "));
+                        std::process::exit(1);
+                    }
+                }
+            }
+            _ => {
+                eprintln!(
+                    "unknown option {arg}, usage: game [--difficulty <level>] [--book <file>] [--ponder] [--threads <n>]"
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+
     let event_loop = EventLoop::new().unwrap();
     let mut app = Application {
         gfx_state: None,
         game: None,
+        difficulty,
+        book,
+        ponder,
+        threads,
         inputs: Inputs {
             mouse_pressed: false,
             mouse_pos: vec2(0.0, 0.0),
@@ -176,21 +240,40 @@ pub struct OnitamaGame {
     pub board: Board,
     audio_player: Option<AudioPlayer>,
     ai_opponent: AsyncAI,
+    ai_move_time: Duration,
     last_ai_move: Option<GameMove>,
     animator: Option<MoveAnimator>,
+    ponder: bool,
 }
 impl OnitamaGame {
-    pub fn new(graphics: GameGraphics, board: Board) -> Self {
+    pub fn new(
+        graphics: GameGraphics,
+        board: Board,
+        difficulty: Difficulty,
+        book: Option<Arc<OpeningBook>>,
+        threads: usize,
+    ) -> Self {
         let audio_player = AudioPlayer::new().ok();
+        let mut ai_opponent: Arc<dyn AIOpponent> = Arc::new(difficulty.make_opponent().with_settings(&BotSettings::default().with_threads(threads)));
+        if let Some(book) = book {
+            ai_opponent = Arc::new(BookOpponent::new(book, ai_opponent).with_variety(true));
+        }
         OnitamaGame {
             graphics,
             board,
             audio_player,
-            ai_opponent: AsyncAI::new(std::sync::Arc::new(MinMaxV0::new(6))),
+            ai_opponent: AsyncAI::new(ai_opponent),
+            ai_move_time: difficulty.settings().move_time,
             last_ai_move: None,
             animator: None,
+            ponder: false,
         }
     }
+    /// Whether the AI should search on a guess of the player's move while the player is thinking
+    pub fn with_pondering(mut self, ponder: bool) -> Self {
+        self.ponder = ponder;
+        self
+    }
     /// Updates game, outputs true if another update loop should be run
     pub fn update(&mut self, delta_time: f32, inputs: Inputs) -> bool {
         // Player is red, AI is blue
@@ -214,13 +297,16 @@ impl OnitamaGame {
                 if let Some(player) = &mut self.audio_player {
                     player.play_sound()
                 }
+                if self.ponder {
+                    self.ai_opponent.start_pondering(self.board.clone());
+                }
             }
             true
         } else {
             // Blocks, but that's fine for now, nothing's happening on-screen while the AI thinks anyway
             self.ai_opponent.start_search(self.board.clone(), None);
             let search_start_time = Instant::now();
-            while self.ai_opponent.is_thinking() && search_start_time.elapsed() < MAX_AI_MOVE_TIME {
+            while self.ai_opponent.is_thinking() && search_start_time.elapsed() < self.ai_move_time {
                 std::thread::sleep(std::time::Duration::from_millis(50));
             }
             let ai_move = self.ai_opponent.stop_search();
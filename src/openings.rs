@@ -0,0 +1,95 @@
+//! Generates a suite of balanced start positions for testing bots, see `onitama::ai::opening_suite`
+//!
+//! Usage: openings [options]
+//!   --out <file>           where to write the suite (default: assets/opening_suite.oni.txt)
+//!   --positions <n>        number of positions (default: 100)
+//!   --min-plies <n>        fewest random moves played from the start (default: 2)
+//!   --max-plies <n>        most random moves played from the start (default: 8)
+//!   --depth <n>            depth of the search scoring the positions (default: 5)
+//!   --window <n>           largest absolute score a position may have (default: 60)
+//!   --per-card-set <n>     most positions with the same five cards (default: 2)
+//!   --seed <n>             seed for the random positions (default: 0)
+//!
+//! The suite can be loaded in the arena, or given to the `tournament` binary with `--pregens`.
+use std::{io::BufWriter, process::exit, sync::atomic::AtomicBool};
+
+use onitama::{
+    ai::opening_suite::{SuiteConfig, generate_suite, write_suite},
+    cards,
+};
+
+struct Options {
+    out: String,
+    config: SuiteConfig,
+    seed: u64,
+}
+
+fn main() {
+    let options = parse_args().unwrap_or_else(|err| {
+        eprintln!("{err}");
+        eprintln!(
+            "usage: openings [--out <file>] [--positions <n>] [--min-plies <n>] [--max-plies <n>] [--depth <n>] [--window <n>] [--per-card-set <n>] [--seed <n>]"
+        );
+        exit(1)
+    });
+
+    let config = &options.config;
+    let suite = generate_suite(config, options.seed, &AtomicBool::new(false), |found, attempts| {
+        if attempts.is_multiple_of(100) {
+            println!("{found}/{} positions after {attempts} candidates", config.positions);
+        }
+    });
+    if suite.len() < config.positions {
+        eprintln!("Only found {} positions, try a wider window or more plies", suite.len());
+    }
+
+    let mut card_usage = [0; 16];
+    for position in &suite {
+        for card in position.board.cards() {
+            card_usage[cards::index_of_card(&card)] += 1;
+        }
+    }
+    let mean_score = suite.iter().map(|position| position.score as f64).sum::<f64>() / suite.len().max(1) as f64;
+    println!(
+        "{} positions, mean score {mean_score:.1}, each card used {} to {} times",
+        suite.len(),
+        card_usage.iter().min().unwrap(),
+        card_usage.iter().max().unwrap()
+    );
+
+    let written = std::fs::File::create(&options.out)
+        .and_then(|file| write_suite(&mut BufWriter::new(file), config, &suite));
+    if let Err(err) = written {
+        eprintln!("{}: {err}", options.out);
+        exit(1);
+    }
+    println!("Suite written to {}", options.out);
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        out: "assets/opening_suite.oni.txt".to_string(),
+        config: SuiteConfig::default(),
+        seed: 0,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {arg}"));
+        let config = &mut options.config;
+        match arg.as_str() {
+            "--out" => options.out = value()?,
+            "--positions" => config.positions = value()?.parse().map_err(|_| "invalid position count")?,
+            "--min-plies" => config.min_plies = value()?.parse().map_err(|_| "invalid ply count")?,
+            "--max-plies" => config.max_plies = value()?.parse().map_err(|_| "invalid ply count")?,
+            "--depth" => config.depth = value()?.parse().map_err(|_| "invalid depth")?,
+            "--window" => config.window = value()?.parse().map_err(|_| "invalid window")?,
+            "--per-card-set" => config.max_per_card_set = value()?.parse().map_err(|_| "invalid count")?,
+            "--seed" => options.seed = value()?.parse().map_err(|_| "invalid seed")?,
+            _ => return Err(format!("unknown option {arg}")),
+        }
+    }
+    if options.config.min_plies > options.config.max_plies {
+        return Err("--min-plies is larger than --max-plies".to_string());
+    }
+    Ok(options)
+}
@@ -0,0 +1,217 @@
+//! Generates training data by letting `MinMaxV4` play against itself, headless and across several processes
+//!
+//! Usage: selfplay [options]
+//!   --out <dir>               directory to write the shards to, one per worker (default: selfplay)
+//!   --games <n>               total number of games (default: 100)
+//!   --workers <n>             number of worker processes (default: number of CPUs)
+//!   --depth <n>               search depth (default: 6)
+//!   --nodes <n>               node limit per search
+//!   --params <file>           evaluator parameters (default: built-in MinMaxV4 weights)
+//!   --temperature <x>         softmax temperature for move noise, in evaluation units (default: 0)
+//!   --temperature-plies <n>   number of plies played with move noise (default: 8)
+//!   --random-plies <n>        random moves played from the start of random games (default: 4)
+//!   --starts <file>           start from the final positions of these games instead of random ones
+//!   --seed <n>                seed for random starts and move noise (default: 0)
+//!   --worker <i>              play only worker i's share of the games, writing only its shard. Used for the
+//!                             worker processes started by --workers, or to spread the games over several machines
+//!
+//! Random games start with random cards and a few random moves. The starts file holds one game in .oni
+//! format per line, lines starting with '#' are ignored, and games are assigned to them in turn.
+//! Shards are named `shard-<worker>.jsonl`, see `onitama::ai::self_play` for the record format.
+use std::{
+    io::BufWriter,
+    process::{Command, exit},
+};
+
+use onitama::{
+    ai::{
+        evaluation::Evaluator,
+        self_play::{SelfPlayConfig, play_game, write_shard},
+    },
+    cards,
+    game::Board,
+};
+use tinyrand::{RandRange, Seeded, StdRand};
+
+struct Options {
+    out: String,
+    games: usize,
+    workers: usize,
+    config: SelfPlayConfig,
+    random_plies: usize,
+    starts: Option<String>,
+    seed: u64,
+    /// Set for worker processes, which play every `workers`th game starting from this one
+    worker: Option<usize>,
+}
+
+fn main() {
+    let options = parse_args().unwrap_or_else(|err| {
+        eprintln!("{err}");
+        eprintln!(
+            "usage: selfplay [--out <dir>] [--games <n>] [--workers <n>] [--depth <n>] [--nodes <n>] [--params <file>] [--temperature <x>] [--temperature-plies <n>] [--random-plies <n>] [--starts <file>] [--seed <n>] [--worker <i>]"
+        );
+        exit(1)
+    });
+    if let Err(err) = std::fs::create_dir_all(&options.out) {
+        eprintln!("{}: {err}", options.out);
+        exit(1);
+    }
+
+    match options.worker {
+        Some(worker) => run_worker(&options, worker),
+        None if options.workers == 1 => run_worker(&options, 0),
+        None => {
+            // Workers are copies of this process, given the same arguments and their index
+            let exe = std::env::current_exe().unwrap_or_else(|err| {
+                eprintln!("failed to find own executable: {err}");
+                exit(1)
+            });
+            let args: Vec<String> = std::env::args().skip(1).collect();
+            let children: Vec<_> = (0..options.workers)
+                .map(|worker| {
+                    Command::new(&exe)
+                        .args(&args)
+                        .args(["--worker", &worker.to_string()])
+                        .spawn()
+                        .unwrap_or_else(|err| {
+                            eprintln!("failed to start worker {worker}: {err}");
+                            exit(1)
+                        })
+                })
+                .collect();
+            let mut failed = false;
+            for (worker, mut child) in children.into_iter().enumerate() {
+                if !child.wait().is_ok_and(|status| status.success()) {
+                    eprintln!("worker {worker} failed");
+                    failed = true;
+                }
+            }
+            if failed {
+                exit(1);
+            }
+            println!("Wrote {} games to {}", options.games, options.out);
+        }
+    }
+}
+
+fn run_worker(options: &Options, worker: usize) {
+    let starts = match &options.starts {
+        Some(path) => load_starts(path),
+        None => vec![],
+    };
+    let path = format!("{}/shard-{worker:03}.jsonl", options.out);
+    let file = std::fs::File::create(&path).unwrap_or_else(|err| {
+        eprintln!("{path}: {err}");
+        exit(1)
+    });
+    let mut writer = BufWriter::new(file);
+    let workers = options.workers.max(1);
+    let mut num_positions = 0;
+    for game in (worker..options.games).step_by(workers) {
+        let seed = options.seed.wrapping_add(game as u64);
+        let start = if starts.is_empty() {
+            random_start(seed, options.random_plies)
+        } else {
+            starts[game % starts.len()].clone()
+        };
+        let records = play_game(&options.config, start, seed);
+        num_positions += records.len();
+        if let Err(err) = write_shard(&mut writer, &records) {
+            eprintln!("{path}: {err}");
+            exit(1);
+        }
+        println!("worker {worker}: game {} finished after {} plies", game + 1, records.len());
+    }
+    drop(writer);
+    println!("worker {worker}: wrote {num_positions} positions to {path}");
+}
+
+/// Random cards followed by random moves, stopping early if the game ends
+fn random_start(seed: u64, plies: usize) -> Board {
+    let mut rng = StdRand::seed(seed);
+    let mut identifiers = cards::ALL_CARDS_IDENT.to_vec();
+    let card_set: String = (0..5)
+        .map(|_| identifiers.swap_remove(rng.next_range(0..identifiers.len())) as char)
+        .collect();
+    let mut board = Board::load_game(&card_set).unwrap();
+    for _ in 0..plies {
+        let legal_moves = board.legal_moves();
+        if legal_moves.is_empty() {
+            break;
+        }
+        let game_move = legal_moves[rng.next_range(0..legal_moves.len())].clone();
+        board.make_move_unchecked(game_move);
+        if board.finished() {
+            board.undo_move();
+            break;
+        }
+    }
+    board
+}
+
+fn load_starts(path: &str) -> Vec<Board> {
+    let text = std::fs::read_to_string(path).unwrap_or_else(|err| {
+        eprintln!("{path}: {err}");
+        exit(1)
+    });
+    let mut starts = vec![];
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        match Board::load_game(line) {
+            Ok(board) if !board.finished() => starts.push(board),
+            Ok(_) => eprintln!("{path}:{}: skipping finished game", i + 1),
+            Err(err) => eprintln!("{path}:{}: skipping game, {err}", i + 1),
+        }
+    }
+    if starts.is_empty() {
+        eprintln!("{path}: no usable start positions");
+        exit(1);
+    }
+    starts
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        out: "selfplay".to_string(),
+        games: 100,
+        workers: std::thread::available_parallelism().map_or(1, |n| n.get()),
+        config: SelfPlayConfig::default(),
+        random_plies: 4,
+        starts: None,
+        seed: 0,
+        worker: None,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {arg}"));
+        match arg.as_str() {
+            "--out" => options.out = value()?,
+            "--games" => options.games = value()?.parse().map_err(|_| "invalid game count")?,
+            "--workers" => options.workers = value()?.parse().map_err(|_| "invalid worker count")?,
+            "--depth" => options.config.depth = value()?.parse().map_err(|_| "invalid depth")?,
+            "--nodes" => options.config.node_limit = Some(value()?.parse().map_err(|_| "invalid node limit")?),
+            "--params" => {
+                let path = value()?;
+                options.config.evaluator = Evaluator::load(&path).map_err(|err| format!("{path}: {err}"))?;
+            }
+            "--temperature" => {
+                options.config.temperature = value()?.parse().map_err(|_| "invalid temperature")?
+            }
+            "--temperature-plies" => {
+                options.config.temperature_plies = value()?.parse().map_err(|_| "invalid ply count")?
+            }
+            "--random-plies" => options.random_plies = value()?.parse().map_err(|_| "invalid ply count")?,
+            "--starts" => options.starts = Some(value()?),
+            "--seed" => options.seed = value()?.parse().map_err(|_| "invalid seed")?,
+            "--worker" => options.worker = Some(value()?.parse().map_err(|_| "invalid worker index")?),
+            _ => return Err(format!("unknown option {arg}")),
+        }
+    }
+    if options.workers == 0 {
+        return Err("at least one worker is needed".to_string());
+    }
+    Ok(options)
+}
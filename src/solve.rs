@@ -0,0 +1,96 @@
+//! Solves the game for one set of cards, proving the value of the starting position
+//!
+//! Usage: solve [options]
+//!   --cards <cards>         card set to solve, as 5 card identifiers in .oni order
+//!   --depth <n>             give up after proving there is no forced win within this many plies (default: 40)
+//!   --checkpoint <file>     where to save progress, resuming from it if it already exists
+//!   --interval <seconds>    how often to save progress during an iteration (default: 300)
+//!
+//! Either `--cards` or an existing `--checkpoint` is required.
+use std::{process::exit, sync::atomic::AtomicBool, time::{Duration, Instant}};
+
+use onitama::{
+    ai::solver::{Outcome, Solver},
+    game::Board,
+};
+
+struct Options {
+    cards: Option<String>,
+    depth: u32,
+    checkpoint: Option<String>,
+    interval: u64,
+}
+
+fn main() {
+    let options = parse_args().unwrap_or_else(|err| {
+        eprintln!("{err}");
+        eprintln!("usage: solve [--cards <cards>] [--depth <n>] [--checkpoint <file>] [--interval <seconds>]");
+        exit(1)
+    });
+
+    let resume_path = options.checkpoint.as_ref().filter(|path| std::path::Path::new(path).exists());
+    let mut solver = match (resume_path, &options.cards) {
+        (Some(path), _) => {
+            let solver = Solver::resume(path).unwrap_or_else(|err| {
+                eprintln!("{path}: {err}");
+                exit(1)
+            });
+            println!(
+                "Resuming {} from depth {}, {} proven positions",
+                solver.start().save_game(false),
+                solver.completed_depth(),
+                solver.num_proven()
+            );
+            solver
+        }
+        (None, Some(cards)) => Solver::new(Board::load_game(cards).unwrap_or_else(|err| {
+            eprintln!("--cards {cards}: {err}");
+            exit(1)
+        })),
+        (None, None) => {
+            eprintln!("no card set given, and no checkpoint to resume from");
+            exit(1)
+        }
+    };
+    if let Some(path) = &options.checkpoint {
+        solver = solver.with_checkpoint(path, Duration::from_secs(options.interval));
+    }
+
+    let start = Instant::now();
+    let result = solver
+        .solve(options.depth, &AtomicBool::new(false), |result| {
+            println!("depth {}: {} nodes ({:.1}s)", result.depth, result.nodes, start.elapsed().as_secs_f64());
+        })
+        .unwrap_or_else(|err| {
+            eprintln!("{err}");
+            exit(1)
+        });
+
+    let best_move = result.best_move.map(|game_move| String::from_utf8_lossy(&game_move.as_encoded_bytes()).into_owned());
+    match result.outcome {
+        Outcome::Win(plies) => println!("Red wins in {plies} plies, starting with {}", best_move.unwrap()),
+        Outcome::Loss(plies) => println!("Blue wins within {plies} plies, red's longest defence starts with {}", best_move.unwrap()),
+        Outcome::Unknown => println!("No forced win for either side within {} plies, the game may be drawn", result.depth),
+    }
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        cards: None,
+        depth: 40,
+        checkpoint: None,
+        interval: 300,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {arg}"));
+        match arg.as_str() {
+            "--cards" => options.cards = Some(value()?),
+            "--depth" => options.depth = value()?.parse().map_err(|_| "invalid depth")?,
+            "--checkpoint" => options.checkpoint = Some(value()?),
+            "--interval" => options.interval = value()?.parse().map_err(|_| "invalid interval")?,
+            _ => return Err(format!("unknown option {arg}")),
+        }
+    }
+    Ok(options)
+}
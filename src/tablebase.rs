@@ -0,0 +1,72 @@
+//! Generates an endgame tablebase for one set of cards
+//!
+//! Usage: tablebase --cards <cards> [options]
+//!   --cards <cards>         card set to solve, as 5 card identifiers in .oni order
+//!   --pieces <n>            largest number of pieces on the board, senseis included (default: 3)
+//!   --out <file>            where to write the tablebase (default: tablebase.bin)
+//!
+//! The tablebase is probed by MinMaxV4 and the difficulty levels with `engine --tablebase <file>` or
+//! `tournament --tablebase <file>`, or loaded for either side in the arena.
+use std::{process::exit, time::Instant};
+
+use onitama::{ai::tablebase::Tablebase, game::Board};
+
+struct Options {
+    cards: String,
+    pieces: u32,
+    out: String,
+}
+
+fn main() {
+    let options = parse_args().unwrap_or_else(|err| {
+        eprintln!("{err}");
+        eprintln!("usage: tablebase --cards <cards> [--pieces <n>] [--out <file>]");
+        exit(1)
+    });
+    let board = Board::load_game(&options.cards).unwrap_or_else(|err| {
+        eprintln!("--cards {}: {err}", options.cards);
+        exit(1)
+    });
+
+    let start = Instant::now();
+    let tablebase = Tablebase::generate(board.cards(), options.pieces, |red, blue, sweeps, values| {
+        let wins = values.iter().filter(|&&value| value > 0).count();
+        let losses = values.iter().filter(|&&value| value < 0).count();
+        let longest = values.iter().map(|value| value.unsigned_abs()).max().unwrap_or(0);
+        println!(
+            "{red} red, {blue} blue disciples: {} positions, {wins} wins, {losses} losses, longest {longest} plies, {sweeps} sweeps ({:.1}s)",
+            values.len(),
+            start.elapsed().as_secs_f64(),
+        );
+    });
+
+    if let Err(err) = tablebase.save(&options.out) {
+        eprintln!("{}: {err}", options.out);
+        exit(1);
+    }
+    println!("Wrote tablebase to {}", options.out);
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut cards = None;
+    let mut options = Options {
+        cards: String::new(),
+        pieces: 3,
+        out: "tablebase.bin".to_string(),
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {arg}"));
+        match arg.as_str() {
+            "--cards" => cards = Some(value()?),
+            "--pieces" => options.pieces = value()?.parse().map_err(|_| "invalid piece count")?,
+            "--out" => options.out = value()?,
+            _ => return Err(format!("unknown option {arg}")),
+        }
+    }
+    options.cards = cards.ok_or("no card set given")?;
+    if !(2..=10).contains(&options.pieces) {
+        return Err("piece count must be between 2 and 10".to_string());
+    }
+    Ok(options)
+}
@@ -0,0 +1,353 @@
+//! Plays matches and tournaments between bots without a window, for running on machines without a display
+//!
+//! Usage: tournament [options] <bot>...
+//!   --engine <command>     adds an external engine, started with the given command line
+//!   --network <file>       adds MinMaxV4 evaluating positions with a trained network
+//!   --all                  adds every built-in bot
+//!   --gauntlet             the first bot plays every other bot, instead of everyone playing everyone
+//!   --starts <n>           number of start positions, each played once with each colour (default: 10)
+//!   --pregens <file>       start positions, one game in .oni format per line (default: random positions)
+//!   --seed <n>             seed for random start positions (default: 0)
+//!   --time <ms>            time limit per move (default: 100)
+//!   --deterministic        limit bots by depth and nodes instead of time, see --depth and --nodes
+//!   --depth <n>            search depth of deterministic bots (default: 6)
+//!   --nodes <n>            node limit of deterministic bots (default: 100000)
+//!   --params <file>        evaluator parameters for both bots
+//!   --tablebase <file>     endgame tablebase probed by every MinMaxV4 or difficulty level bot
+//!   --threads <n>          games played in parallel (default: number of CPUs)
+//!   --search-threads <n>   threads each MinMaxV4 or difficulty level bot searches with when limited by time
+//!                          (default: 1)
+//!   --hash <MiB>           transposition table size of each MinMaxV4 or difficulty level bot (default: 16)
+//!   --no-quiescence        turns off the quiescence search of MinMaxV4
+//!   --max-plies <n>        games still going after this many plies are counted as draws (default: 200)
+//!   --out <file>           where to write the game results (default: tournament.txt)
+//!   --table <file>         where to write the ranking and crosstable, as Markdown for .md files and CSV otherwise
+//!   --sprt <elo0> <elo1>   with two bots, stop once a sequential probability ratio test decides whether the
+//!                          first bot is better
+//!   --alpha <p>            chance of the SPRT wrongly accepting elo1 (default: 0.05)
+//!   --beta <p>             chance of the SPRT wrongly accepting elo0 (default: 0.05)
+//!
+//! Bots are given by their names in the arena, eg. MinMaxV4 or "Level: Hard".
+//! Every pair of bots plays each start position twice, once with each colour. Ratings are fitted to all the
+//! results at once, so they are comparable across the whole tournament.
+//! The results file holds a game record per line, as described in `onitama::ai::records`, and can be loaded
+//! in the arena.
+use std::{
+    io::{BufWriter, Write},
+    process::exit,
+    sync::Arc,
+    time::Duration,
+};
+
+use onitama::{
+    ai::{
+        AIVersion, BotSettings, DeterministicSettings,
+        evaluation::Evaluator,
+        network::Network,
+        records::GameRecord,
+        statistics::{Sprt, SprtResult},
+        tablebase::Tablebase,
+        tournament::{
+            Crosstable, MoveLimit, OWN_LIMITS_TIMEOUT, Player, TableFormat, pairings_gauntlet, pairings_round_robin,
+            random_start, run_games,
+        },
+    },
+    game::{Board, GameStatus},
+};
+use tinyrand::{Seeded, StdRand};
+
+// Random start positions are up to this many plies into the game
+const RANDOM_START_PLIES: usize = 10;
+
+enum Entrant {
+    Bot(AIVersion),
+    /// Command line of an external engine
+    Engine(String),
+    /// `MinMaxV4` with a network loaded from the given file
+    Network(String),
+}
+
+struct Options {
+    entrants: Vec<Entrant>,
+    gauntlet: bool,
+    starts: usize,
+    pregens: Option<String>,
+    seed: u64,
+    time: u64,
+    deterministic: Option<DeterministicSettings>,
+    /// Evaluator parameters and search threads of the built-in bots
+    bot_settings: BotSettings,
+    threads: usize,
+    max_plies: usize,
+    out: String,
+    table: Option<String>,
+    sprt: Option<Sprt>,
+}
+
+fn main() {
+    let options = parse_args().unwrap_or_else(|err| {
+        eprintln!("{err}");
+        eprintln!(
+            "usage: tournament [--starts <n>] [--pregens <file>] [--seed <n>] [--time <ms>] [--deterministic] [--depth <n>] [--nodes <n>] [--params <file>] [--tablebase <file>] [--threads <n>] [--search-threads <n>] [--hash <MiB>] [--no-quiescence] [--max-plies <n>] [--out <file>] [--table <file>] [--sprt <elo0> <elo1>] [--alpha <p>] [--beta <p>] [--engine <command>]... [--network <file>]... [--all] [--gauntlet] <bot>..."
+        );
+        exit(1)
+    });
+
+    let starts = match &options.pregens {
+        Some(path) => load_starts(path),
+        None => {
+            let mut rng = StdRand::seed(options.seed);
+            (0..options.starts).map(|_| random_start(&mut rng, RANDOM_START_PLIES)).collect()
+        }
+    };
+    let players = make_players(&options);
+    let pairings = if options.gauntlet {
+        pairings_gauntlet(players.len(), starts.len())
+    } else {
+        pairings_round_robin(players.len(), starts.len())
+    };
+    let limit = match options.deterministic {
+        Some(_) => MoveLimit::OwnLimits(OWN_LIMITS_TIMEOUT),
+        None => MoveLimit::Time(Duration::from_millis(options.time)),
+    };
+
+    let file = std::fs::File::create(&options.out).unwrap_or_else(|err| {
+        eprintln!("{}: {err}", options.out);
+        exit(1)
+    });
+    let mut out = BufWriter::new(file);
+    let mut crosstable = Crosstable::new(&players);
+    let mut sprt_result = SprtResult::Continue;
+    let mut num_games = 0;
+    run_games(&players, &starts, &pairings, limit, options.max_plies, options.threads, |result| {
+        num_games += 1;
+        let (red, blue) = (players[result.pairing.red].name(), players[result.pairing.blue].name());
+        let start = &starts[result.pairing.start_index];
+        let outcome = match (result.status(), &result.forfeit) {
+            (_, Some(forfeit)) => format!("forfeit, {forfeit}"),
+            (GameStatus::RedWon, None) => "red won".to_string(),
+            (GameStatus::BlueWon, None) => "blue won".to_string(),
+            (GameStatus::Stalemate, None) => "stalemate".to_string(),
+            (GameStatus::Playing, None) => "unfinished".to_string(),
+        };
+        crosstable.add(result);
+        println!(
+            "game {num_games}/{}: {red} (red) vs {blue} (blue), {outcome} after {} plies",
+            pairings.len(),
+            result.game.game_length() - start.game_length()
+        );
+        let record = GameRecord {
+            start: start.save_game(false),
+            red: red.to_string(),
+            blue: blue.to_string(),
+            time_limit: match limit {
+                MoveLimit::Time(time) => Some(time),
+                MoveLimit::OwnLimits(_) => None,
+            },
+            result: result.status(),
+            game: result.game.save_game(false),
+            move_times: result.move_times.clone(),
+            forfeit: result.forfeit.as_ref().map(|forfeit| forfeit.to_string()),
+        };
+        let written = writeln!(out, "{}", record.to_line());
+        if let Err(err) = written.and_then(|_| out.flush()) {
+            eprintln!("{}: {err}", options.out);
+            exit(1);
+        }
+        if let Some(sprt) = &options.sprt {
+            sprt_result = sprt.test(&crosstable.score(0, 1));
+            if sprt_result != SprtResult::Continue {
+                println!("SPRT finished after {num_games} games, stopping");
+                return false;
+            }
+        }
+        true
+    });
+
+    println!();
+    println!("{}", crosstable.ranking_table(TableFormat::Markdown));
+    println!("{}", crosstable.results_table(TableFormat::Markdown));
+    if players.len() == 2 {
+        let score = crosstable.score(0, 1);
+        println!(
+            "{} against {}: {score}, Elo difference {}, likelihood of superiority {:.1}%",
+            players[0].name(),
+            players[1].name(),
+            score.elo(),
+            score.los() * 100.0
+        );
+        if let Some(sprt) = &options.sprt {
+            let (lower, upper) = sprt.bounds();
+            println!(
+                "SPRT [{}, {}]: LLR {:.2} ({lower:.2}, {upper:.2}), {sprt_result}",
+                sprt.elo0,
+                sprt.elo1,
+                sprt.llr(&score)
+            );
+        }
+    }
+    println!("Results written to {}", options.out);
+    if let Some(path) = &options.table {
+        let format = TableFormat::from_path(path);
+        let tables = format!("{}\n{}", crosstable.ranking_table(format), crosstable.results_table(format));
+        if let Err(err) = std::fs::write(path, tables) {
+            eprintln!("{path}: {err}");
+            exit(1);
+        }
+        println!("Tables written to {path}");
+    }
+}
+
+fn make_players(options: &Options) -> Vec<Player> {
+    let players: Vec<Player> = options
+        .entrants
+        .iter()
+        .map(|entrant| match entrant {
+            Entrant::Bot(version) => Player::builtin(*version, options.bot_settings.clone(), options.deterministic),
+            Entrant::Network(path) => {
+                let network = Network::load(path).unwrap_or_else(|err| {
+                    eprintln!("{path}: {err}");
+                    exit(1)
+                });
+                let settings = options.bot_settings.clone().with_network(Arc::new(network));
+                Player::builtin(AIVersion::MinMaxV4, settings, options.deterministic)
+                    .with_name(format!("MinMaxV4 ({path})"))
+            }
+            Entrant::Engine(command) => {
+                let mut words = command.split_whitespace();
+                let program = words.next().unwrap_or("");
+                let args: Vec<String> = words.map(str::to_string).collect();
+                Player::external(program, &args).unwrap_or_else(|err| {
+                    eprintln!("{command}: {err}");
+                    exit(1)
+                })
+            }
+        })
+        .collect();
+    // The same bot entered twice still needs telling apart in the results
+    let names: Vec<String> = players.iter().map(|player| player.name().to_string()).collect();
+    players
+        .into_iter()
+        .enumerate()
+        .map(|(i, player)| {
+            let name = &names[i];
+            if names.iter().filter(|other| *other == name).count() > 1 {
+                let number = names[..=i].iter().filter(|other| *other == name).count();
+                player.with_name(format!("{name} ({number})"))
+            } else {
+                player
+            }
+        })
+        .collect()
+}
+
+fn load_starts(path: &str) -> Vec<Board> {
+    let text = std::fs::read_to_string(path).unwrap_or_else(|err| {
+        eprintln!("{path}: {err}");
+        exit(1)
+    });
+    let mut starts = vec![];
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        match Board::load_game(line) {
+            Ok(board) if !board.finished() => starts.push(board),
+            Ok(_) => eprintln!("{path}:{}: skipping finished game", i + 1),
+            Err(err) => eprintln!("{path}:{}: skipping game, {err}", i + 1),
+        }
+    }
+    if starts.is_empty() {
+        eprintln!("{path}: no usable start positions");
+        exit(1);
+    }
+    starts
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut deterministic = false;
+    let mut settings = DeterministicSettings::default();
+    let mut options = Options {
+        entrants: vec![],
+        gauntlet: false,
+        starts: 10,
+        pregens: None,
+        seed: 0,
+        time: 100,
+        deterministic: None,
+        bot_settings: BotSettings::default(),
+        threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+        max_plies: 200,
+        out: "tournament.txt".to_string(),
+        table: None,
+        sprt: None,
+    };
+    let (mut alpha, mut beta) = (0.05, 0.05);
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {arg}"));
+        match arg.as_str() {
+            "--starts" => options.starts = value()?.parse().map_err(|_| "invalid start count")?,
+            "--pregens" => options.pregens = Some(value()?),
+            "--seed" => options.seed = value()?.parse().map_err(|_| "invalid seed")?,
+            "--time" => options.time = value()?.parse().map_err(|_| "invalid time")?,
+            "--deterministic" => deterministic = true,
+            "--depth" => settings.depth = value()?.parse().map_err(|_| "invalid depth")?,
+            "--nodes" => settings.node_limit = value()?.parse().map_err(|_| "invalid node limit")?,
+            "--params" => {
+                let path = value()?;
+                options.bot_settings.evaluator =
+                    Some(Evaluator::load(&path).map_err(|err| format!("{path}: {err}"))?);
+            }
+            "--tablebase" => {
+                let path = value()?;
+                let tablebase = Tablebase::load(&path).map_err(|err| format!("{path}: {err}"))?;
+                options.bot_settings = options.bot_settings.with_tablebase(Arc::new(tablebase));
+            }
+            "--threads" => options.threads = value()?.parse().map_err(|_| "invalid thread count")?,
+            "--search-threads" => {
+                let threads = value()?.parse().map_err(|_| "invalid search thread count")?;
+                options.bot_settings = options.bot_settings.with_threads(threads);
+            }
+            "--hash" => {
+                let mib = value()?.parse().map_err(|_| "invalid hash size")?;
+                options.bot_settings = options.bot_settings.with_hash_size(mib);
+            }
+            "--no-quiescence" => options.bot_settings.quiescence = false,
+            "--max-plies" => options.max_plies = value()?.parse().map_err(|_| "invalid ply count")?,
+            "--out" => options.out = value()?,
+            "--table" => options.table = Some(value()?),
+            "--engine" => options.entrants.push(Entrant::Engine(value()?)),
+            "--network" => options.entrants.push(Entrant::Network(value()?)),
+            "--all" => options.entrants.extend(AIVersion::all().into_iter().map(Entrant::Bot)),
+            "--gauntlet" => options.gauntlet = true,
+            "--sprt" => {
+                let elo0 = value()?.parse().map_err(|_| "invalid elo0")?;
+                let elo1 = value()?.parse().map_err(|_| "invalid elo1")?;
+                options.sprt = Some(Sprt::new(elo0, elo1));
+            }
+            "--alpha" => alpha = value()?.parse().map_err(|_| "invalid alpha")?,
+            "--beta" => beta = value()?.parse().map_err(|_| "invalid beta")?,
+            _ if arg.starts_with("--") => return Err(format!("unknown option {arg}")),
+            _ => options
+                .entrants
+                .push(Entrant::Bot(AIVersion::from_name(&arg).ok_or(format!("unknown bot {arg}"))?)),
+        }
+    }
+    if options.entrants.len() < 2 {
+        return Err("at least two bots are needed".to_string());
+    }
+    if options.sprt.is_some() && options.entrants.len() != 2 {
+        return Err("the SPRT needs exactly two bots".to_string());
+    }
+    let valid_rate = |rate: f64| rate > 0.0 && rate < 0.5;
+    if !valid_rate(alpha) || !valid_rate(beta) {
+        return Err("alpha and beta must be between 0 and 0.5".to_string());
+    }
+    options.sprt = options.sprt.map(|sprt| sprt.with_error_rates(alpha, beta));
+    if deterministic {
+        settings.seed = options.seed;
+        options.deterministic = Some(settings);
+    }
+    Ok(options)
+}
@@ -0,0 +1,129 @@
+//! Trains a neural network evaluator on positions recorded by self-play
+//!
+//! Usage: train [options] <shard>...
+//!   --network <file>          network to continue training (default: a new random network)
+//!   --out <file>              where to write the trained network (default: params/network.txt)
+//!   --hidden <n>              hidden units of a new network (default: 32)
+//!   --epochs <n>              passes over the training positions (default: 20)
+//!   --learning-rate <x>       step size of gradient descent (default: 0.01)
+//!   --result-weight <x>       weight of the game result against the search score in the targets (default: 0.5)
+//!   --seed <n>                seed for initialisation and shuffling (default: 0)
+//!
+//! Every tenth position is held out to measure the loss on positions the network wasn't trained on.
+//! A trained network is played with `engine --network <file>`, entered in a tournament with
+//! `tournament --network <file>`, or loaded for either side in the arena.
+use std::process::exit;
+
+use onitama::ai::{
+    network::{Network, TrainingSample},
+    self_play::load_shard,
+};
+use tinyrand::{Seeded, StdRand};
+
+struct Options {
+    shards: Vec<String>,
+    network: Option<String>,
+    out: String,
+    hidden: usize,
+    epochs: u32,
+    learning_rate: f32,
+    result_weight: f32,
+    seed: u64,
+}
+
+fn main() {
+    let options = parse_args().unwrap_or_else(|err| {
+        eprintln!("{err}");
+        eprintln!(
+            "usage: train [--network <file>] [--out <file>] [--hidden <n>] [--epochs <n>] [--learning-rate <x>] [--result-weight <x>] [--seed <n>] <shard>..."
+        );
+        exit(1)
+    });
+
+    let mut training = vec![];
+    let mut validation = vec![];
+    for path in &options.shards {
+        let records = load_shard(path).unwrap_or_else(|err| {
+            eprintln!("{path}: {err}");
+            exit(1)
+        });
+        for record in &records {
+            let Some(sample) = TrainingSample::from_record(record, options.result_weight) else {
+                continue;
+            };
+            if (training.len() + validation.len()) % 10 == 9 {
+                validation.push(sample);
+            } else {
+                training.push(sample);
+            }
+        }
+    }
+    if training.is_empty() {
+        eprintln!("no positions to train on");
+        exit(1);
+    }
+    println!("Training on {} positions, validating on {}", training.len(), validation.len());
+
+    let mut network = match &options.network {
+        Some(path) => Network::load(path).unwrap_or_else(|err| {
+            eprintln!("{path}: {err}");
+            exit(1)
+        }),
+        None => Network::new(options.hidden, options.seed),
+    };
+    let mut rng = StdRand::seed(options.seed);
+    println!("epoch 0: training loss {:.4}, validation loss {:.4}", network.loss(&training), network.loss(&validation));
+    for epoch in 1..=options.epochs {
+        network.train_epoch(&training, options.learning_rate, &mut rng);
+        println!(
+            "epoch {epoch}: training loss {:.4}, validation loss {:.4}",
+            network.loss(&training),
+            network.loss(&validation)
+        );
+    }
+
+    if let Err(err) = network.save(&options.out) {
+        eprintln!("{}: {err}", options.out);
+        exit(1);
+    }
+    println!("Wrote network to {}", options.out);
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        shards: vec![],
+        network: None,
+        out: "params/network.txt".to_string(),
+        hidden: 32,
+        epochs: 20,
+        learning_rate: 0.01,
+        result_weight: 0.5,
+        seed: 0,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {arg}"));
+        match arg.as_str() {
+            "--network" => options.network = Some(value()?),
+            "--out" => options.out = value()?,
+            "--hidden" => options.hidden = value()?.parse().map_err(|_| "invalid hidden unit count")?,
+            "--epochs" => options.epochs = value()?.parse().map_err(|_| "invalid epoch count")?,
+            "--learning-rate" => {
+                options.learning_rate = value()?.parse().map_err(|_| "invalid learning rate")?
+            }
+            "--result-weight" => {
+                options.result_weight = value()?.parse().map_err(|_| "invalid result weight")?
+            }
+            "--seed" => options.seed = value()?.parse().map_err(|_| "invalid seed")?,
+            _ if arg.starts_with("--") => return Err(format!("unknown option {arg}")),
+            _ => options.shards.push(arg),
+        }
+    }
+    if options.shards.is_empty() {
+        return Err("no shards given".to_string());
+    }
+    if options.hidden == 0 {
+        return Err("at least one hidden unit is needed".to_string());
+    }
+    Ok(options)
+}
@@ -0,0 +1,120 @@
+//! Headless tuning of evaluation weights from finished games
+//!
+//! Usage: tune [options] <games file>...
+//!   --params <file>      parameter file to start from (default: built-in MinMaxV4 weights)
+//!   --out <file>         where to write the tuned parameters (default: params/tuned.toml)
+//!   --seed <n>           seed for the parameter visiting order (default: 0)
+//!   --iterations <n>     maximum number of local search passes (default: 100)
+//!   --skip-plies <n>     ignore the first n positions of every game (default: 4)
+//!
+//! Games files hold one game in .oni format per line, lines starting with '#' are ignored.
+//! Only finished games are used, since positions are labelled with the game result.
+use std::process::exit;
+
+use onitama::{
+    ai::{
+        evaluation::Evaluator,
+        tuning::{Tuner, extract_positions},
+    },
+    game::Board,
+};
+
+struct Options {
+    games_files: Vec<String>,
+    params: Option<String>,
+    out: String,
+    seed: u64,
+    iterations: u32,
+    skip_plies: usize,
+}
+
+fn main() {
+    let options = parse_args().unwrap_or_else(|err| {
+        eprintln!("{err}");
+        eprintln!("usage: tune [--params <file>] [--out <file>] [--seed <n>] [--iterations <n>] [--skip-plies <n>] <games file>...");
+        exit(1)
+    });
+
+    let start = match &options.params {
+        Some(path) => Evaluator::load(path).unwrap_or_else(|err| {
+            eprintln!("{path}: {err}");
+            exit(1)
+        }),
+        None => Evaluator::default(),
+    };
+
+    let mut positions = vec![];
+    let mut num_games = 0;
+    for path in &options.games_files {
+        let text = std::fs::read_to_string(path).unwrap_or_else(|err| {
+            eprintln!("{path}: {err}");
+            exit(1)
+        });
+        for (i, line) in text.lines().enumerate() {
+            if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                continue;
+            }
+            match Board::load_game(line) {
+                Ok(game) if game.finished() => {
+                    num_games += 1;
+                    positions.extend(extract_positions(&game, options.skip_plies));
+                }
+                Ok(_) => (),
+                Err(err) => eprintln!("{path}:{}: skipping game, {err}", i + 1),
+            }
+        }
+    }
+    if positions.is_empty() {
+        eprintln!("no quiet positions found in finished games");
+        exit(1);
+    }
+
+    let mut tuner = Tuner::new(positions, start, options.seed);
+    println!(
+        "Tuning on {} positions from {num_games} games, initial error {:.6}",
+        tuner.num_positions(),
+        tuner.error()
+    );
+    tuner.tune(options.iterations, |iteration, step, error| {
+        println!("Iteration {iteration}, step {step}, error {error:.6}");
+    });
+
+    let evaluator = tuner.evaluator();
+    if let Err(err) = evaluator.save(&options.out) {
+        eprintln!("{}: {err}", options.out);
+        exit(1);
+    }
+    println!("Wrote tuned parameters to {}", options.out);
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        games_files: vec![],
+        params: None,
+        out: "params/tuned.toml".to_string(),
+        seed: 0,
+        iterations: 100,
+        skip_plies: 4,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {arg}"));
+        match arg.as_str() {
+            "--params" => options.params = Some(value()?),
+            "--out" => options.out = value()?,
+            "--seed" => options.seed = value()?.parse().map_err(|_| "invalid seed")?,
+            "--iterations" => {
+                options.iterations = value()?.parse().map_err(|_| "invalid iteration count")?
+            }
+            "--skip-plies" => {
+                options.skip_plies = value()?.parse().map_err(|_| "invalid ply count")?
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option {arg}")),
+            _ => options.games_files.push(arg),
+        }
+    }
+    if options.games_files.is_empty() {
+        return Err("no games files given".to_string());
+    }
+    Ok(options)
+}
@@ -0,0 +1,79 @@
+use std::{sync::atomic::AtomicBool, time::Duration};
+
+use onitama::{
+    ai::{
+        MinMaxV4,
+        analysis::{PositionAnalysis, analyse_game, blunders},
+        evaluation::{Evaluator, WIN_SCORE},
+        tournament::{MoveLimit, OWN_LIMITS_TIMEOUT},
+    },
+    game::{Board, GameMove},
+};
+
+fn analyse(limit: MoveLimit) -> (Board, Vec<GameMove>, Vec<Option<PositionAnalysis>>) {
+    analyse_with(&MinMaxV4::new(3).without_move_time(), limit)
+}
+
+fn analyse_with(opponent: &MinMaxV4, limit: MoveLimit) -> (Board, Vec<GameMove>, Vec<Option<PositionAnalysis>>) {
+    let start = Board::load_game("11.11..3.........2..00.00BXLUT").unwrap();
+    let game = Board::load_game("11.11..3.........2..00.00BXLUTBrsLhlTvl").unwrap();
+    let moves = game.move_history()[start.game_length()..].to_vec();
+    let mut positions = vec![None; moves.len() + 1];
+    analyse_game(opponent, &start, &moves, limit, &AtomicBool::new(false), |ply, analysis| {
+        positions[ply] = Some(analysis);
+    });
+    (start, moves, positions)
+}
+
+#[test]
+fn analyses_every_position() {
+    let (start, moves, positions) = analyse(MoveLimit::OwnLimits(OWN_LIMITS_TIMEOUT));
+    let mut board = start;
+    for (ply, analysis) in positions.iter().enumerate() {
+        let analysis = analysis.as_ref().unwrap();
+        assert_eq!(analysis.red_to_move, board.red_to_move());
+        assert!(analysis.score.is_some());
+        match &analysis.best_move {
+            Some(best_move) => assert!(board.legal_moves().contains(best_move)),
+            None => assert!(board.finished()),
+        }
+        if let Some(game_move) = moves.get(ply) {
+            board.make_move_unchecked(game_move.clone());
+        }
+    }
+    // Red captured blue's sensei with the last move
+    assert_eq!(positions.last().unwrap().as_ref().unwrap().score, Some(WIN_SCORE));
+}
+
+#[test]
+fn finished_positions_use_bots_win_score() {
+    let evaluator = Evaluator::default().with_win_score(5000);
+    let opponent = MinMaxV4::new(3).without_move_time().with_evaluator(evaluator);
+    let (_, _, positions) = analyse_with(&opponent, MoveLimit::OwnLimits(OWN_LIMITS_TIMEOUT));
+    assert_eq!(positions.last().unwrap().as_ref().unwrap().score, Some(5000));
+}
+
+#[test]
+fn time_limited_analysis_finishes() {
+    let (_, _, positions) = analyse(MoveLimit::Time(Duration::from_millis(20)));
+    assert!(positions.iter().all(Option::is_some));
+}
+
+#[test]
+fn blunders_are_losses_for_the_mover() {
+    let position = |red_to_move, score| Some(PositionAnalysis { red_to_move, score: Some(score), best_move: None });
+    let positions = [
+        position(true, 0),
+        // Red gave away 200
+        position(false, -200),
+        // Blue gave away 150
+        position(true, -50),
+        // Blue's score isn't known, so neither move around it can be judged
+        Some(PositionAnalysis { red_to_move: false, score: None, best_move: None }),
+        position(true, -500),
+        // Red improved
+        position(false, 100),
+    ];
+    assert_eq!(blunders(&positions, 100), [0, 1]);
+    assert_eq!(blunders(&positions, 160), [0]);
+}
@@ -0,0 +1,63 @@
+use std::sync::{Arc, atomic::AtomicBool};
+
+use onitama::{
+    ai::{AIOpponent, AIVersion, AsyncAI, BotSettings, DeterministicSettings, MinMaxV4},
+    game::Board,
+};
+
+const SETTINGS: DeterministicSettings = DeterministicSettings {
+    depth: 3,
+    node_limit: 2_000,
+    seed: 5,
+};
+
+/// Start positions with different cards, a few plies into the game
+fn positions() -> Vec<Board> {
+    ["BCQKD", "XLUTB", "HMRDO"]
+        .iter()
+        .enumerate()
+        .map(|(i, cards)| {
+            let mut board = Board::load_game(cards).unwrap();
+            for _ in 0..i * 2 {
+                board.make_move_unchecked(board.legal_moves()[0].clone());
+            }
+            board
+        })
+        .collect()
+}
+
+#[test]
+fn same_position_same_move() {
+    for version in AIVersion::all() {
+        for board in positions() {
+            let a = version.make_deterministic_opponent(&BotSettings::default(), &SETTINGS);
+            let b = version.make_deterministic_opponent(&BotSettings::default(), &SETTINGS);
+            let first = a.search(Arc::new(AtomicBool::new(false)), board.clone(), None);
+            // Searching again with the same bot must not be affected by the earlier search
+            let again = a.search(Arc::new(AtomicBool::new(false)), board.clone(), None);
+            let fresh = b.search(Arc::new(AtomicBool::new(false)), board.clone(), None);
+            assert_eq!(first, again, "{version}");
+            assert_eq!(first, fresh, "{version}");
+        }
+    }
+}
+#[test]
+fn node_limited_statistics_repeat() {
+    let ai = MinMaxV4::new(20).with_node_limit(5_000);
+    for board in positions() {
+        let a = ai.search_with_info(Arc::new(AtomicBool::new(false)), board.clone(), None);
+        let b = ai.search_with_info(Arc::new(AtomicBool::new(false)), board, None);
+        assert_eq!((a.best_move, a.score, a.depth, a.nodes), (b.best_move, b.score, b.depth, b.nodes));
+    }
+}
+#[test]
+fn async_search_finishes_by_itself() {
+    let board = positions().remove(0);
+    let mut ai = AsyncAI::new(AIVersion::MinMaxV4.make_deterministic_opponent(&BotSettings::default(), &SETTINGS));
+    ai.start_search(board.clone(), None);
+    let game_move = ai.wait_for_move();
+    let direct = AIVersion::MinMaxV4
+        .make_deterministic_opponent(&BotSettings::default(), &SETTINGS)
+        .search(Arc::new(AtomicBool::new(false)), board, None);
+    assert_eq!(game_move, direct);
+}
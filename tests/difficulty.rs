@@ -0,0 +1,75 @@
+use std::sync::{Arc, atomic::AtomicBool};
+
+use onitama::{
+    ai::{
+        AIOpponent, AIVersion, BotSettings, DeterministicSettings, MinMaxV4,
+        difficulty::Difficulty,
+        evaluation::WIN_SCORE,
+        tournament::{
+            Crosstable, MoveLimit, OWN_LIMITS_TIMEOUT, Player, pairings_both_colours, random_start, run_games,
+        },
+    },
+    game::Board,
+};
+use strum::IntoEnumIterator;
+use tinyrand::{Seeded, StdRand};
+
+#[test]
+fn levels_get_stronger() {
+    let levels: Vec<_> = Difficulty::iter().map(|level| level.settings()).collect();
+    for pair in levels.windows(2) {
+        assert!(pair[0].depth <= pair[1].depth);
+        assert!(pair[0].move_time <= pair[1].move_time);
+        assert!(pair[0].blunder_rate >= pair[1].blunder_rate);
+        assert!(pair[1].temperature == 0.0 || pair[0].temperature > pair[1].temperature);
+    }
+    assert_eq!(AIVersion::Level(Difficulty::Hard).to_string(), "Level: Hard");
+}
+#[test]
+fn seeded_levels_repeat_moves() {
+    let board = Board::load_game("BCQKDBvqQch").unwrap();
+    for level in [Difficulty::Beginner, Difficulty::Easy] {
+        let search = || {
+            level
+                .make_opponent()
+                .with_seed(5)
+                .search(Arc::new(AtomicBool::new(false)), board.clone(), None)
+        };
+        let game_move = search();
+        assert!(board.legal_moves().contains(&game_move));
+        assert_eq!(game_move, search());
+    }
+}
+#[test]
+fn tempered_level_plays_proven_win() {
+    // Red wins in two moves, which a one ply search doesn't see
+    let board = Board::load_game("GRXBTRxtBdiGwqXbfTyo").unwrap();
+    assert!(Difficulty::Hard.settings().temperature > 0.0);
+    for seed in 0..10 {
+        let game_move = Difficulty::Hard
+            .make_opponent()
+            .with_seed(seed)
+            .search(Arc::new(AtomicBool::new(false)), board.clone(), None);
+        let mut after = board.clone();
+        after.make_move_unchecked(game_move.clone());
+        let reply = MinMaxV4::new(4).search_with_info(Arc::new(AtomicBool::new(false)), after, None);
+        assert!(reply.score.is_some_and(|score| score <= -WIN_SCORE), "seed {seed}: {game_move:?} doesn't win");
+    }
+}
+#[test]
+fn easy_beats_beginner() {
+    // The first half of the Beginner and Easy match in the calibration described in `onitama::ai::difficulty`
+    let settings = DeterministicSettings { seed: 1, ..DeterministicSettings::default() };
+    let players = [Difficulty::Beginner, Difficulty::Easy]
+        .map(|level| Player::builtin(AIVersion::Level(level), BotSettings::default(), Some(settings)));
+    let mut rng = StdRand::seed(1);
+    let starts: Vec<Board> = (0..10).map(|_| random_start(&mut rng, 10)).collect();
+    let mut crosstable = Crosstable::new(&players);
+    let limit = MoveLimit::OwnLimits(OWN_LIMITS_TIMEOUT);
+    run_games(&players, &starts, &pairings_both_colours(starts.len(), 0, 1), limit, 200, 1, |result| {
+        crosstable.add(result);
+        true
+    });
+    let score = crosstable.score(1, 0);
+    assert!(score.wins >= 3 * score.losses, "Easy against Beginner: {score}");
+}
@@ -0,0 +1,44 @@
+use onitama::{
+    ai::evaluation::{EvalTerm, Evaluator, WIN_SCORE},
+    game::{Board, GameStatus},
+};
+
+#[test]
+fn default_board_is_even() {
+    let board = Board::default();
+    let evaluator = Evaluator::default();
+    assert_eq!(evaluator.evaluate(&board, true), -evaluator.evaluate(&board, false));
+    assert_eq!(Evaluator::classic().evaluate(&board, true), 0);
+}
+#[test]
+fn disabled_terms_are_zero() {
+    let board = Board::load_game("BCQKDBvqQch").unwrap();
+    let evaluator = Evaluator::new().with_weight(EvalTerm::Material, 100);
+    let features = evaluator.features(&board);
+    for (i, feature) in features.into_iter().enumerate() {
+        if i != EvalTerm::Material as usize {
+            assert_eq!(feature, 0);
+        }
+    }
+}
+#[test]
+fn breakdown_sums_to_total() {
+    let mut board = Board::default();
+    let evaluator = Evaluator::default();
+    for _ in 0..6 {
+        let game_move = board.legal_moves()[0].clone();
+        board.make_move_unchecked(game_move);
+        if board.finished() {
+            break;
+        }
+        let breakdown = evaluator.breakdown(&board, board.red_to_move());
+        let sum: i32 = breakdown.terms.iter().map(|(_, value, weight)| value * weight).sum();
+        assert_eq!(sum, breakdown.total);
+    }
+}
+#[test]
+fn finished_game_is_lost_for_player_to_move() {
+    let board = Board::load_game("11.11..3.........2..00.00BXLUTBrsLhlTvl").unwrap();
+    assert_eq!(board.status(), GameStatus::RedWon);
+    assert_eq!(Evaluator::default().evaluate(&board, board.red_to_move()), -WIN_SCORE);
+}
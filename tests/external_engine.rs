@@ -0,0 +1,73 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    sync::{Arc, atomic::AtomicBool},
+};
+
+use onitama::{
+    ai::{AIOpponent, AIVersion, external::ExternalEngine},
+    game::Board,
+};
+
+fn spawn(ai: &str) -> ExternalEngine {
+    ExternalEngine::spawn(env!("CARGO_BIN_EXE_engine"), &["--ai".to_string(), ai.to_string()]).unwrap()
+}
+
+#[test]
+fn names_match_arena() {
+    for version in AIVersion::all() {
+        assert!(AIVersion::from_name(&version.to_string()) == Some(version));
+    }
+    assert!(AIVersion::from_name("hard") == AIVersion::from_name("Level: Hard"));
+    assert!(AIVersion::from_name("nonsense").is_none());
+}
+#[test]
+fn engine_plays_legal_moves() {
+    let engine = spawn("MinMaxV4");
+    assert_eq!(engine.name(), "MinMaxV4");
+    let mut board = Board::default();
+    for _ in 0..4 {
+        let result = engine.search_with_info(
+            Arc::new(AtomicBool::new(false)),
+            board.clone(),
+            Some(std::time::Duration::from_millis(100)),
+        );
+        assert!(board.legal_moves().contains(&result.best_move));
+        assert!(result.depth > 0);
+        board.make_move_unchecked(result.best_move);
+    }
+}
+#[test]
+fn engine_stops_when_cancelled() {
+    let engine = spawn("Mcts");
+    let cancel_signal = Arc::new(AtomicBool::new(false));
+    let stopper = cancel_signal.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        stopper.store(true, std::sync::atomic::Ordering::Relaxed);
+    });
+    let board = Board::default();
+    let game_move = engine.search(cancel_signal, board.clone(), None);
+    assert!(board.legal_moves().contains(&game_move));
+}
+#[test]
+fn invalid_position_isnt_searched() {
+    let mut engine = std::process::Command::new(env!("CARGO_BIN_EXE_engine"))
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    let commands = "position BCQKDBvq\nposition BCQKDBaa\ngo movetime 50\nisready\nposition BCQKDBvq\ngo movetime 50\n";
+    let mut stdin = engine.stdin.take().unwrap();
+    stdin.write_all(commands.as_bytes()).unwrap();
+    let mut lines = BufReader::new(engine.stdout.take().unwrap()).lines().map(Result::unwrap);
+    // Nothing is searched before `readyok`, only the valid position after it
+    assert_eq!(lines.next().unwrap(), "readyok");
+    let bestmove = lines.find(|line| line.starts_with("bestmove")).unwrap();
+    let board = Board::load_game("BCQKDBvq").unwrap();
+    assert!(board.legal_moves().iter().any(|game_move| {
+        bestmove == format!("bestmove {}", String::from_utf8_lossy(&game_move.as_encoded_bytes()))
+    }));
+    stdin.write_all(b"quit\n").unwrap();
+    engine.wait().unwrap();
+}
@@ -0,0 +1,90 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use onitama::{
+    ai::{AIOpponent, AIVersion, BotSettings, MinMaxV4, difficulty::Difficulty},
+    game::Board,
+};
+
+fn search(ai: &MinMaxV4, board: &Board) -> onitama::ai::SearchResult {
+    ai.search_with_info(Arc::new(AtomicBool::new(false)), board.clone(), None)
+}
+
+#[test]
+fn single_thread_is_deterministic() {
+    let board = Board::load_game("BCQKDBvqQch").unwrap();
+    let ai = MinMaxV4::new(6);
+    let first = search(&ai, &board);
+    let second = search(&ai, &board);
+    assert_eq!(first.best_move, second.best_move);
+    assert_eq!(first.score, second.score);
+    assert_eq!(first.nodes, second.nodes);
+}
+#[test]
+fn threads_complete_search() {
+    let board = Board::load_game("BCQKDBvqQch").unwrap();
+    let result = search(&MinMaxV4::new(6).with_threads(4), &board);
+    assert!(board.legal_moves().contains(&result.best_move));
+    assert_eq!(result.depth, 5);
+    assert!(result.score.is_some());
+}
+#[test]
+fn settings_add_helper_threads() {
+    let board = Board::load_game("BCQKDBvqQch").unwrap();
+    // The node limit only applies to the main thread, so the helpers' nodes come on top
+    let engine = || MinMaxV4::new(20).with_node_limit(100_000);
+    let single = search(&engine().with_settings(&BotSettings::default()), &board);
+    let threaded = search(&engine().with_settings(&BotSettings::default().with_threads(3)), &board);
+    assert!(board.legal_moves().contains(&threaded.best_move));
+    assert!(threaded.nodes > single.nodes, "{} <= {}", threaded.nodes, single.nodes);
+}
+#[test]
+fn bots_search_with_threads() {
+    let board = Board::load_game("BCQKDBvqQch").unwrap();
+    let settings = BotSettings::default().with_threads(2);
+    for version in [AIVersion::MinMaxV4, AIVersion::Level(Difficulty::Medium)] {
+        let ai = version.make_opponent(&settings);
+        let cancel_signal = Arc::new(AtomicBool::new(false));
+        let timer_signal = cancel_signal.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            timer_signal.store(true, Ordering::Relaxed);
+        });
+        let game_move = ai.search(cancel_signal, board.clone(), None);
+        assert!(board.legal_moves().contains(&game_move), "{version}");
+    }
+}
+#[test]
+fn hash_size_setting_keeps_search_deterministic() {
+    let board = Board::load_game("BCQKDBvqQch").unwrap();
+    let ai = MinMaxV4::new(6).with_settings(&BotSettings::default().with_hash_size(1));
+    let first = search(&ai, &board);
+    let second = search(&ai, &board);
+    assert!(board.legal_moves().contains(&first.best_move));
+    assert_eq!(first.best_move, second.best_move);
+    assert_eq!(first.nodes, second.nodes);
+}
+#[test]
+fn concurrent_searches_share_table() {
+    let board = Board::load_game("BCQKDBvqQch").unwrap();
+    let ai = MinMaxV4::new(20);
+    let cancel_signal = Arc::new(AtomicBool::new(false));
+    std::thread::scope(|scope| {
+        // Like pondering, one search keeps running while another starts on the same engine
+        let background = scope.spawn(|| ai.search_with_info(cancel_signal.clone(), board.clone(), None));
+        std::thread::sleep(Duration::from_millis(100));
+        let timer_signal = cancel_signal.clone();
+        scope.spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            timer_signal.store(true, Ordering::Relaxed);
+        });
+        let foreground = ai.search_with_info(cancel_signal.clone(), board.clone(), None);
+        assert!(board.legal_moves().contains(&foreground.best_move));
+        assert!(board.legal_moves().contains(&background.join().unwrap().best_move));
+    });
+}
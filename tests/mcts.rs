@@ -0,0 +1,76 @@
+use std::sync::{Arc, atomic::AtomicBool};
+
+use onitama::{
+    ai::{AIOpponent, Mcts, Rollout},
+    game::{Board, GameMove, GameStatus},
+};
+use tinyrand::{RandRange, Seeded, StdRand};
+
+/// Whether the move wins the game for the player making it, rather than ending it in a stalemate
+fn wins(board: &Board, game_move: &GameMove) -> bool {
+    let mut after = board.clone();
+    after.make_move_unchecked(game_move.clone());
+    let winner = if board.red_to_move() { GameStatus::RedWon } else { GameStatus::BlueWon };
+    after.status() == winner
+}
+
+/// Plays random moves until the player to move has a move that wins the game
+fn position_with_winning_move(seed: u64) -> Board {
+    let mut rng = StdRand::seed(seed);
+    loop {
+        let mut board = Board::default();
+        while !board.finished() {
+            let legal_moves = board.legal_moves();
+            if legal_moves.iter().any(|game_move| wins(&board, game_move)) {
+                return board;
+            }
+            let game_move = legal_moves[rng.next_range(0..legal_moves.len())].clone();
+            board.make_move_unchecked(game_move);
+        }
+    }
+}
+
+#[test]
+fn finds_winning_move() {
+    for (seed, rollout) in [(1, Rollout::Random), (2, Rollout::Evaluator(Default::default()))] {
+        let board = position_with_winning_move(seed);
+        let mcts = Mcts::new()
+            .with_rollout(rollout)
+            .with_max_iterations(2000)
+            .with_seed(seed);
+        let cancel_signal = Arc::new(AtomicBool::new(false));
+        let game_move = mcts.search(cancel_signal.clone(), board.clone(), None);
+        assert!(cancel_signal.load(std::sync::atomic::Ordering::Relaxed));
+        assert!(wins(&board, &game_move));
+    }
+}
+#[test]
+fn reused_tree_keeps_visits() {
+    for tree_reuse in [true, false] {
+        let mcts = Mcts::new().with_max_iterations(300).with_tree_reuse(tree_reuse).with_seed(0);
+        let mut board = Board::default();
+        let game_move = mcts.search(Arc::new(AtomicBool::new(false)), board.clone(), None);
+        assert_eq!(mcts.root_visits(), 300);
+        // The most visited move's subtree is kept, with its visits from the first search
+        board.make_move_unchecked(game_move);
+        mcts.search(Arc::new(AtomicBool::new(false)), board.clone(), None);
+        if tree_reuse {
+            assert!(mcts.root_visits() > 300);
+        } else {
+            assert_eq!(mcts.root_visits(), 300);
+        }
+    }
+}
+#[test]
+fn reused_tree_gives_legal_moves() {
+    let mcts = Mcts::new().with_max_iterations(300).with_seed(0);
+    let mut board = Board::default();
+    for _ in 0..10 {
+        let game_move = mcts.search(Arc::new(AtomicBool::new(false)), board.clone(), None);
+        assert!(board.legal_moves().contains(&game_move));
+        board.make_move_unchecked(game_move);
+        if board.finished() {
+            break;
+        }
+    }
+}
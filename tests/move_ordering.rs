@@ -0,0 +1,40 @@
+use onitama::{
+    ai::{
+        move_ordering::{MoveOrderer, OrderingOptions},
+        transposition::{Bound, PackedMove, TTEntry, TranspositionTable},
+    },
+    game::Board,
+};
+
+#[test]
+fn transposition_entry_round_trip() {
+    let board = Board::load_game("BCQKDBvqQch").unwrap();
+    let game_move = board.legal_moves().pop().unwrap();
+    let table = TranspositionTable::new(8);
+    let entry = TTEntry {
+        depth: 5,
+        score: -1234,
+        bound: Bound::Upper,
+        best_move: Some(PackedMove::new(&game_move)),
+    };
+    table.store(board.state_hash(), entry);
+    assert_eq!(table.probe(board.state_hash()), Some(entry));
+    assert!(entry.best_move.unwrap().matches(&game_move));
+    assert_eq!(table.probe(board.state_hash() ^ 1), None);
+    table.clear();
+    assert_eq!(table.probe(board.state_hash()), None);
+}
+#[test]
+fn hash_move_and_killers_come_first() {
+    let board = Board::default();
+    let mut moves = board.legal_moves();
+    let hash_move = moves[5].clone();
+    let killer = moves[7].clone();
+    let mut orderer = MoveOrderer::new(OrderingOptions::default());
+    orderer.record_cutoff(&killer, 2, 4);
+    orderer.order(&mut moves, 2, Some(PackedMove::new(&hash_move)));
+    assert_eq!(moves[0], hash_move);
+    assert_eq!(moves[1], killer);
+    assert!(orderer.is_killer(&killer, 2));
+    assert!(!orderer.is_killer(&killer, 3));
+}
@@ -0,0 +1,97 @@
+use std::sync::{Arc, atomic::AtomicBool};
+
+use onitama::{
+    ai::{
+        AIOpponent, AIVersion, BotSettings, DeterministicSettings, MinMaxV4,
+        network::{Inputs, Network, TrainingSample},
+        self_play::{PositionRecord, SelfPlayConfig, play_game},
+    },
+    game::Board,
+};
+use tinyrand::{Seeded, StdRand};
+
+fn samples() -> Vec<TrainingSample> {
+    let config = SelfPlayConfig {
+        depth: 2,
+        temperature: 50.0,
+        ..SelfPlayConfig::default()
+    };
+    (0..4)
+        .flat_map(|seed| play_game(&config, Board::default(), seed))
+        .map(|record| TrainingSample::from_record(&record, 1.0).unwrap())
+        .collect()
+}
+
+#[test]
+fn training_reduces_loss() {
+    let samples = samples();
+    let mut network = Network::new(8, 1);
+    let initial_loss = network.loss(&samples);
+    let mut rng = StdRand::seed(1);
+    for _ in 0..10 {
+        network.train_epoch(&samples, 0.01, &mut rng);
+    }
+    assert!(network.loss(&samples) < initial_loss);
+}
+#[test]
+fn file_round_trip() {
+    let network = Network::new(4, 2);
+    let loaded = Network::from_network_str(&network.to_network_string()).unwrap();
+    let board = Board::default();
+    assert_eq!(loaded.evaluate(&board, true), network.evaluate(&board, true));
+    assert!(Network::from_network_str("onitama-network 1 148 4\n1 2 3").is_err());
+}
+#[test]
+fn evaluation_is_from_given_perspective() {
+    let network = Network::new(8, 3);
+    let mut board = Board::default();
+    board.make_move_unchecked(board.legal_moves()[0].clone());
+    assert_eq!(network.evaluate(&board, true), network.evaluate(&board, false).map(|eval| -eval));
+}
+#[test]
+fn network_search_plays_legal_moves() {
+    let ai = MinMaxV4::new(3).with_network(Arc::new(Network::new(8, 4)));
+    let board = Board::default();
+    let game_move = ai.search(Arc::new(AtomicBool::new(false)), board.clone(), None);
+    assert!(board.legal_moves().contains(&game_move));
+}
+#[test]
+fn boards_with_too_many_pieces_use_evaluator() {
+    let network = Arc::new(Network::new(8, 4));
+    let board = Board::load_game("113111.............000200BXLUT").unwrap();
+    assert_eq!(network.evaluate(&board, true), None);
+    let ai = MinMaxV4::new(3).with_network(network);
+    let game_move = ai.search(Arc::new(AtomicBool::new(false)), board.clone(), None);
+    assert!(board.legal_moves().contains(&game_move));
+}
+#[test]
+fn settings_use_network() {
+    let network = Arc::new(Network::new(8, 5));
+    let deterministic = DeterministicSettings { depth: 3, ..DeterministicSettings::default() };
+    let board = Board::load_game("BXLUTBwr").unwrap();
+    let search = |settings: &BotSettings| {
+        let ai = AIVersion::MinMaxV4.make_deterministic_opponent(settings, &deterministic);
+        ai.search_with_info(Arc::new(AtomicBool::new(false)), board.clone(), None)
+    };
+    let with_network = search(&BotSettings::default().with_network(network.clone()));
+    let direct = MinMaxV4::new(3).with_network(network).with_node_limit(deterministic.node_limit);
+    let direct = direct.search_with_info(Arc::new(AtomicBool::new(false)), board.clone(), None);
+    assert_eq!(with_network.score, direct.score);
+    assert_eq!(with_network.best_move, direct.best_move);
+    assert_ne!(with_network.score, search(&BotSettings::default()).score);
+}
+#[test]
+fn records_with_too_many_pieces_are_skipped() {
+    let record = |position: &str| PositionRecord {
+        position: position.to_string(),
+        cards: "BXLUT".to_string(),
+        red_to_move: true,
+        ply: 0,
+        score: 0,
+        game_move: String::new(),
+        result: 0,
+    };
+    // Every piece a game starts with, then one more
+    assert!(Inputs::from_record(&record(&format!("11311{}00200", ".".repeat(15)))).is_some());
+    assert!(Inputs::from_record(&record(&format!("113111{}00200", ".".repeat(14)))).is_none());
+}
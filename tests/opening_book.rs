@@ -0,0 +1,41 @@
+use std::sync::{Arc, atomic::AtomicBool};
+
+use onitama::{
+    ai::{
+        AIOpponent, Dummy,
+        opening_book::{BookOpponent, OpeningBook},
+    },
+    game::Board,
+};
+
+const EXAMPLE_GAME: &str = "11.11..3.........2..00.00BXLUTBrsLhlTvl";
+
+#[test]
+fn position_hash_ignores_card_order() {
+    let a = Board::load_game("BCQKD").unwrap();
+    let b = Board::load_game("CBKQD").unwrap();
+    assert_ne!(a.state_hash(), 0);
+    assert_eq!(a.position_hash(), b.position_hash());
+    assert_ne!(a.position_hash(), Board::load_game("BCQDK").unwrap().position_hash());
+}
+#[test]
+fn book_round_trip() {
+    let game = Board::load_game(EXAMPLE_GAME).unwrap();
+    let mut book = OpeningBook::new();
+    assert!(book.add_game(&game, 10));
+    // Red won, so only red's two moves are in the book
+    assert_eq!(book.len(), 2);
+    let text = book.to_book_string();
+    let loaded = OpeningBook::from_book_str(&text).unwrap();
+    assert_eq!(loaded.to_book_string(), text);
+}
+#[test]
+fn book_move_played_before_search() {
+    let game = Board::load_game(EXAMPLE_GAME).unwrap();
+    let mut book = OpeningBook::new();
+    book.add_game(&game, 10);
+    let start = Board::load_game("11.11..3.........2..00.00BXLUT").unwrap();
+    let ai = BookOpponent::new(Arc::new(book), Arc::new(Dummy)).with_variety(true);
+    let game_move = ai.search(Arc::new(AtomicBool::new(false)), start, None);
+    assert_eq!(game_move, game.move_history()[0]);
+}
@@ -0,0 +1,71 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::atomic::AtomicBool,
+};
+
+use onitama::{
+    ai::{
+        AIOpponent, MinMaxV4,
+        opening_suite::{SuiteConfig, generate_suite, write_suite},
+    },
+    cards,
+    game::Board,
+};
+
+fn config() -> SuiteConfig {
+    SuiteConfig {
+        positions: 12,
+        depth: 3,
+        window: 80,
+        max_per_card_set: 1,
+        max_attempts: 5_000,
+        ..SuiteConfig::default()
+    }
+}
+
+#[test]
+fn positions_are_balanced_and_distinct() {
+    let config = config();
+    let suite = generate_suite(&config, 7, &AtomicBool::new(false), |_, _| ());
+    assert_eq!(suite.len(), config.positions);
+    let engine = MinMaxV4::new(config.depth).without_move_time();
+    let mut hashes = HashSet::new();
+    let mut card_sets = HashSet::new();
+    let mut card_usage = HashMap::new();
+    for position in &suite {
+        assert!(!position.board.finished());
+        assert!(position.score.abs() <= config.window);
+        let result = engine.search_with_info(Default::default(), position.board.clone(), None);
+        assert_eq!(result.score, Some(position.score));
+        assert!(hashes.insert(position.board.position_hash()));
+        let mut card_set: Vec<usize> = position.board.cards().iter().map(cards::index_of_card).collect();
+        card_set.sort();
+        assert!(card_sets.insert(card_set.clone()));
+        for card in card_set {
+            *card_usage.entry(card).or_insert(0) += 1;
+        }
+    }
+    // 60 cards dealt over 16 cards, favouring the least used ones each time
+    assert_eq!(card_usage.len(), 16);
+    let (min, max) = (card_usage.values().min().unwrap(), card_usage.values().max().unwrap());
+    assert!(max - min <= 2);
+}
+#[test]
+fn suite_file_loads() {
+    let config = SuiteConfig { positions: 3, ..config() };
+    let suite = generate_suite(&config, 1, &AtomicBool::new(false), |_, _| ());
+    let again = generate_suite(&config, 1, &AtomicBool::new(false), |_, _| ());
+    assert_eq!(again.len(), suite.len());
+    for (first, second) in suite.iter().zip(&again) {
+        assert!(first.board.is_same_board(&second.board));
+        assert_eq!(first.score, second.score);
+    }
+    let mut text = vec![];
+    write_suite(&mut text, &config, &suite).unwrap();
+    let text = String::from_utf8(text).unwrap();
+    let lines: Vec<&str> = text.lines().filter(|line| !line.starts_with('#')).collect();
+    assert_eq!(lines.len(), suite.len());
+    for (line, position) in lines.iter().zip(&suite) {
+        assert!(Board::load_game(line).unwrap().is_same_board(&position.board));
+    }
+}
@@ -0,0 +1,53 @@
+use onitama::ai::{
+    evaluation::{EvalTerm, Evaluator},
+    params::ParamsError,
+};
+
+#[test]
+fn shipped_params_match_builtin() {
+    assert_eq!(Evaluator::load("params/classic.toml").unwrap(), Evaluator::classic());
+    assert_eq!(Evaluator::load("params/default.toml").unwrap(), Evaluator::default());
+}
+#[test]
+fn save_load_round_trip() {
+    let evaluator = Evaluator::default()
+        .with_weight(EvalTerm::CardAttacks, -3)
+        .with_win_score(5000)
+        .with_piece_square_table([7; 25]);
+    let loaded = Evaluator::from_params_str(&evaluator.to_params_string()).unwrap();
+    assert_eq!(evaluator, loaded);
+}
+#[test]
+fn rejects_bad_files() {
+    assert!(matches!(
+        Evaluator::from_params_str("[weights]\nmaterial = 100"),
+        Err(ParamsError::MissingVersion)
+    ));
+    assert!(matches!(
+        Evaluator::from_params_str("version = 2"),
+        Err(ParamsError::UnsupportedVersion(2))
+    ));
+    assert!(matches!(
+        Evaluator::from_params_str("version = 1\n[weights]\nmobility = 3"),
+        Err(ParamsError::UnknownKey(_))
+    ));
+    assert!(matches!(
+        Evaluator::from_params_str("version = 1\npiece_square_table = [1, 2, 3]"),
+        Err(ParamsError::Syntax(2))
+    ));
+    // Doesn't fit in 32 bits
+    assert!(matches!(
+        Evaluator::from_params_str("version = 1\n[weights]\nmaterial = 4294967396"),
+        Err(ParamsError::Syntax(3))
+    ));
+}
+#[test]
+fn rejects_win_score_within_evaluations() {
+    for win_score in [0, -9999, 400] {
+        let text = format!("version = 1\nwin_score = {win_score}\n[weights]\nmaterial = 100");
+        assert!(matches!(Evaluator::from_params_str(&text), Err(ParamsError::WinScoreTooLow(..))), "{win_score}");
+    }
+    let text = Evaluator::default().with_weight(EvalTerm::Material, 5000).to_params_string();
+    assert!(matches!(Evaluator::from_params_str(&text), Err(ParamsError::WinScoreTooLow(..))));
+    assert!(Evaluator::from_params_str("version = 1\nwin_score = 401\n[weights]\nmaterial = 100").is_ok());
+}
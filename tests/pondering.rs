@@ -0,0 +1,55 @@
+use std::{
+    sync::{Arc, atomic::AtomicBool},
+    time::Duration,
+};
+
+use onitama::{
+    ai::{AIOpponent, AsyncAI, MinMaxV3},
+    game::Board,
+};
+
+fn after_guess(board: &Board) -> Board {
+    // Same search the AI uses to guess the reply
+    let guess = MinMaxV3::new(4).search(Arc::new(AtomicBool::new(false)), board.clone(), None);
+    let mut board = board.clone();
+    board.make_move_unchecked(guess);
+    board
+}
+
+#[test]
+fn ponder_hit_keeps_search() {
+    let board = Board::load_game("BCQKDBvqQch").unwrap();
+    let mut ai = AsyncAI::new(Arc::new(MinMaxV3::new(4)));
+    ai.start_pondering(board.clone());
+    assert!(ai.is_pondering());
+    let expected = after_guess(&board);
+    // The reply is guessed on the search thread
+    while ai.pondered_board().is_none() {
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    assert!(ai.pondered_board().unwrap().is_same_board(&expected));
+    ai.start_search(expected.clone(), None);
+    assert!(!ai.is_pondering());
+    let game_move = ai.stop_search();
+    assert!(expected.legal_moves().contains(&game_move));
+}
+#[test]
+fn ponder_miss_restarts_search() {
+    let board = Board::load_game("BCQKDBvqQch").unwrap();
+    let mut ai = AsyncAI::new(Arc::new(MinMaxV3::new(4)));
+    ai.start_pondering(board.clone());
+    let guessed = after_guess(&board);
+    let actual = board
+        .legal_moves()
+        .into_iter()
+        .map(|game_move| {
+            let mut actual = board.clone();
+            actual.make_move_unchecked(game_move);
+            actual
+        })
+        .find(|actual| !actual.is_same_board(&guessed))
+        .unwrap();
+    ai.start_search(actual.clone(), None);
+    let game_move = ai.stop_search();
+    assert!(actual.legal_moves().contains(&game_move));
+}
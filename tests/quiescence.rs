@@ -0,0 +1,34 @@
+use std::sync::{Arc, atomic::AtomicBool};
+
+use onitama::{
+    ai::{AIOpponent, AIVersion, BotSettings, DeterministicSettings, MinMaxV4, SearchResult},
+    game::Board,
+};
+
+// Red's disciple can take a blue disciple that blue's sensei recaptures, one ply past a depth 1 search
+const EXCHANGE: &str = ".......3....1....0..2....BDQKM";
+
+fn search(ai: &dyn AIOpponent, board: &Board) -> SearchResult {
+    ai.search_with_info(Arc::new(AtomicBool::new(false)), board.clone(), None)
+}
+
+#[test]
+fn sees_recapture_past_horizon() {
+    let board = Board::load_game(EXCHANGE).unwrap();
+    let greedy = search(&MinMaxV4::new(2).with_quiescence(false), &board);
+    let quiet = search(&MinMaxV4::new(2).with_quiescence(true), &board);
+    assert!(greedy.best_move.captured_piece.is_some());
+    assert!(quiet.best_move.captured_piece.is_none());
+    assert!(quiet.score.unwrap() < greedy.score.unwrap());
+}
+#[test]
+fn settings_toggle_quiescence() {
+    let board = Board::load_game(EXCHANGE).unwrap();
+    let deterministic = DeterministicSettings { depth: 2, ..DeterministicSettings::default() };
+    let make = |quiescence| {
+        let settings = BotSettings::default().with_quiescence(quiescence);
+        AIVersion::MinMaxV4.make_deterministic_opponent(&settings, &deterministic)
+    };
+    assert!(make(false).search(Arc::new(AtomicBool::new(false)), board.clone(), None).captured_piece.is_some());
+    assert!(make(true).search(Arc::new(AtomicBool::new(false)), board, None).captured_piece.is_none());
+}
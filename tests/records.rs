@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+use onitama::{
+    ai::records::{GameRecord, RecordError, load_records, save_records},
+    game::GameStatus,
+};
+
+fn played() -> GameRecord {
+    GameRecord {
+        start: "11.11..3.........2..00.00BXLUT".to_string(),
+        red: "MinMaxV4".to_string(),
+        blue: "Level: Hard".to_string(),
+        time_limit: Some(Duration::from_millis(250)),
+        result: GameStatus::RedWon,
+        game: "11.11..3.........2..00.00BXLUTBrsLhlTvl".to_string(),
+        move_times: [180, 250, 3].map(Duration::from_millis).to_vec(),
+        forfeit: None,
+    }
+}
+
+#[test]
+fn line_round_trip() {
+    let record = played();
+    assert_eq!(GameRecord::from_line(&record.to_line()).unwrap(), record);
+    let deterministic = GameRecord { time_limit: None, move_times: vec![], ..played() };
+    assert_eq!(GameRecord::from_line(&deterministic.to_line()).unwrap(), deterministic);
+    let forfeit = GameRecord {
+        result: GameStatus::BlueWon,
+        game: "11.11..3.........2..00.00BXLUT".to_string(),
+        move_times: vec![],
+        forfeit: Some("red illegal move 'Baa'".to_string()),
+        ..played()
+    };
+    assert!(forfeit.is_played());
+    assert_eq!(GameRecord::from_line(&forfeit.to_line()).unwrap(), forfeit);
+    // Unplayed games are written as the bare start position, like the pregens file
+    let unplayed = GameRecord::unplayed("BCQKDBvq".to_string());
+    assert_eq!(unplayed.to_line(), "BCQKDBvq");
+    assert_eq!(GameRecord::from_line("BCQKDBvq").unwrap(), unplayed);
+    assert!(!unplayed.is_played());
+}
+#[test]
+fn invalid_lines() {
+    assert!(matches!(GameRecord::from_line("BCQKD\tred\tblue"), Err(RecordError::Syntax)));
+    let bad_result = played().to_line().replace("red won", "red lost");
+    assert!(matches!(GameRecord::from_line(&bad_result), Err(RecordError::Syntax)));
+    assert!(matches!(GameRecord::from_line("BCQKDBaa"), Err(RecordError::Game(_))));
+    // The game has to be played from the start position
+    let other_start = GameRecord { start: "BXLUT".to_string(), ..played() };
+    assert!(matches!(GameRecord::from_line(&other_start.to_line()), Err(RecordError::NotContinued)));
+    let other_moves = GameRecord { start: "BXLUTBwr".to_string(), game: "BXLUTBxs".to_string(), ..played() };
+    assert!(matches!(GameRecord::from_line(&other_moves.to_line()), Err(RecordError::NotContinued)));
+    let shorter = GameRecord { start: "BXLUTBwr".to_string(), game: "BXLUT".to_string(), ..played() };
+    assert!(matches!(GameRecord::from_line(&shorter.to_line()), Err(RecordError::NotContinued)));
+    // Move times are given for every move or none
+    let missing_time = GameRecord { move_times: played().move_times[1..].to_vec(), ..played() };
+    assert!(matches!(GameRecord::from_line(&missing_time.to_line()), Err(RecordError::Syntax)));
+    let bad_time = played().to_line().replace("180,", "fast,");
+    assert!(matches!(GameRecord::from_line(&bad_time), Err(RecordError::Syntax)));
+}
+#[test]
+fn file_round_trip() {
+    let records = vec![played(), GameRecord::unplayed("CBKQD".to_string())];
+    let path = std::env::temp_dir().join("onitama_records_test.txt");
+    save_records(&path, &records).unwrap();
+    let loaded = load_records(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded, records);
+    assert_eq!(loaded[0].game_board().status(), GameStatus::RedWon);
+}
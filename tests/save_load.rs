@@ -105,3 +105,66 @@ mod before_matches_after {
         assert_eq!(board, loaded_board);
     }
 }
+#[test]
+fn readable_notation() {
+    let board = Board::load_game("11.11..3.........2..00.00BXLUTBrsLhlTvl").unwrap();
+    let moves: Vec<String> = board.move_history().iter().map(|game_move| game_move.to_string()).collect();
+    assert_eq!(moves, ["BOAR Sc2-d2", "ELEPHANT Sc4-b3", "TIGER b1xb3"]);
+}
+#[test]
+fn custom_start_round_trip() {
+    let game = "11.11..3.........2..00.00BXLUTBrsLhl";
+    let board = Board::load_game(game).unwrap();
+    assert_eq!(board.save_game(false), game);
+    assert_eq!(Board::load_game(&board.save_game(true)).unwrap(), board);
+}
+#[test]
+fn blue_moves_first() {
+    let board = Board::load_game(
+        "
+        11.11..3.........2..00.00
+        4     # blue moves first
+        BXLUT
+        Lhl   # blue sensei moves down and left using elephant
+        ",
+    )
+    .unwrap();
+    assert!(board.red_to_move());
+    assert_eq!(board.game_length(), 1);
+    assert_eq!(board.save_game(false), "11.11..3.........2..00.004BXLUTLhl");
+    assert_eq!(Board::load_game(&board.save_game(true)).unwrap(), board);
+}
+#[test]
+fn custom_positions_are_validated() {
+    use onitama::game::{Piece, PositionError};
+    let cards = [cards::BOAR, cards::COBRA, cards::CRAB, cards::CRANE, cards::DRAGON];
+    let mut squares = [None; 25];
+    squares[7] = Some(Piece::BlueSensei);
+    squares[17] = Some(Piece::RedSensei);
+    squares[20] = Some(Piece::RedDisciple);
+
+    let board = Board::from_position(squares, cards, false).unwrap();
+    assert!(!board.red_to_move());
+    assert_eq!(board.save_game(false), ".......3.........2..0....4BCQKD");
+    assert_eq!(Board::load_game(&board.save_game(false)).unwrap(), board);
+
+    let mut no_blue_sensei = squares;
+    no_blue_sensei[7] = None;
+    assert_eq!(Board::from_position(no_blue_sensei, cards, true), Err(PositionError::SenseiCount));
+    let mut two_red_senseis = squares;
+    two_red_senseis[20] = Some(Piece::RedSensei);
+    assert_eq!(Board::from_position(two_red_senseis, cards, true), Err(PositionError::SenseiCount));
+    let mut five_blue_disciples = squares;
+    for square in [0, 1, 3, 4] {
+        five_blue_disciples[square] = Some(Piece::BlueDisciple);
+    }
+    assert!(Board::from_position(five_blue_disciples, cards, true).is_ok());
+    five_blue_disciples[5] = Some(Piece::BlueDisciple);
+    assert_eq!(Board::from_position(five_blue_disciples, cards, true), Err(PositionError::TooManyPieces));
+    let mut on_temple = squares;
+    on_temple[7] = None;
+    on_temple[22] = Some(Piece::BlueSensei);
+    assert_eq!(Board::from_position(on_temple, cards, true), Err(PositionError::SenseiOnTemple));
+    let same_cards = [cards::BOAR, cards::COBRA, cards::CRAB, cards::BOAR, cards::DRAGON];
+    assert_eq!(Board::from_position(squares, same_cards, true), Err(PositionError::DuplicateCard));
+}
@@ -0,0 +1,59 @@
+use onitama::{
+    ai::{
+        evaluation::WIN_SCORE,
+        self_play::{PositionRecord, SelfPlayConfig, play_game, position_string},
+    },
+    game::Board,
+};
+
+fn config() -> SelfPlayConfig {
+    SelfPlayConfig {
+        depth: 2,
+        temperature: 50.0,
+        max_plies: 60,
+        ..SelfPlayConfig::default()
+    }
+}
+
+#[test]
+fn records_follow_the_game() {
+    let records = play_game(&config(), Board::default(), 1);
+    assert!(!records.is_empty());
+    let mut board = Board::default();
+    for record in &records {
+        assert_eq!(record.position, position_string(&board));
+        assert_eq!(record.red_to_move, board.red_to_move());
+        assert_eq!(record.ply, board.game_length());
+        board = Board::load_game(&(board.save_game(false) + &record.game_move)).unwrap();
+    }
+    // Results alternate between the players, unless the game was drawn
+    for pair in records.windows(2) {
+        assert_eq!(pair[0].result, -pair[1].result);
+    }
+}
+#[test]
+fn temperature_plays_proven_win() {
+    // Red wins in two moves, found by the three ply search but not the one ply one
+    let start = Board::load_game("GRXBTRxtBdiGwqXbfTyo").unwrap();
+    let config = SelfPlayConfig { depth: 4, ..config() };
+    for seed in 0..10 {
+        let records = play_game(&config, start.clone(), seed);
+        assert!(records[0].score >= WIN_SCORE, "seed {seed}: scored {}", records[0].score);
+        assert_eq!(records[0].result, 1, "seed {seed}: {} doesn't win", records[0].game_move);
+    }
+}
+#[test]
+fn same_seed_same_game() {
+    let a = play_game(&config(), Board::default(), 7);
+    let b = play_game(&config(), Board::default(), 7);
+    assert_eq!(a, b);
+}
+#[test]
+fn json_round_trip() {
+    for record in play_game(&config(), Board::default(), 3) {
+        let line = record.to_json();
+        assert!(!line.contains('\n'));
+        assert_eq!(PositionRecord::from_json(&line), Some(record));
+    }
+    assert_eq!(PositionRecord::from_json(r#"{"position":"...","cards":"BCQKD"}"#), None);
+}
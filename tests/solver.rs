@@ -0,0 +1,65 @@
+use std::sync::atomic::AtomicBool;
+
+use onitama::{
+    ai::{
+        solver::{Outcome, Solver},
+        tablebase::{Tablebase, TablebaseValue},
+    },
+    game::{Board, GameStatus},
+};
+
+// Red's sensei can capture blue's with the Crab
+const CAPTURE_IN_ONE: &str = ".....\n.....\n..3..\n..2..\n.....\nQBCKD";
+
+#[test]
+fn finds_immediate_win() {
+    let start = Board::load_game(CAPTURE_IN_ONE).unwrap();
+    let result = Solver::new(start.clone()).solve(5, &AtomicBool::new(false), |_| ()).unwrap();
+    assert_eq!(result.outcome, Outcome::Win(1));
+    let mut board = start;
+    board.make_move_unchecked(result.best_move.unwrap());
+    assert_eq!(board.status(), GameStatus::RedWon);
+}
+#[test]
+fn agrees_with_tablebase() {
+    let cards = Board::load_game("BCQKD").unwrap().cards();
+    let tablebase = Tablebase::generate(cards, 2, |_, _, _, _| ());
+    let mut checked = 0;
+    for red in (0..25).step_by(3) {
+        for blue in (1..25).step_by(4) {
+            if red == blue || red == 2 || blue == 22 {
+                continue;
+            }
+            let mut squares = ['.'; 25];
+            squares[red] = '2';
+            squares[blue] = '3';
+            let start = Board::load_game(&(squares.iter().collect::<String>() + "BCQKD")).unwrap();
+            let result = Solver::new(start.clone()).solve(5, &AtomicBool::new(false), |_| ()).unwrap();
+            let expected = match tablebase.probe(&start).unwrap() {
+                TablebaseValue::Win(plies) if plies <= 5 => Outcome::Win(plies as u32),
+                TablebaseValue::Loss(plies) if plies <= 5 => Outcome::Loss(plies as u32),
+                _ => Outcome::Unknown,
+            };
+            assert_eq!(result.outcome, expected);
+            checked += 1;
+        }
+    }
+    assert!(checked > 20);
+}
+#[test]
+fn resumes_from_checkpoint() {
+    let path = std::env::temp_dir().join("onitama_solver_test.ckpt");
+    let start = Board::load_game("BCQKD").unwrap();
+    let mut solver = Solver::new(start.clone()).with_checkpoint(&path, std::time::Duration::from_secs(60));
+    let result = solver.solve(3, &AtomicBool::new(false), |_| ()).unwrap();
+    assert_eq!(result.outcome, Outcome::Unknown);
+
+    let mut resumed = Solver::resume(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(resumed.completed_depth(), 3);
+    assert!(resumed.start().is_same_board(&start));
+    let mut depths = vec![];
+    let resumed_result = resumed.solve(4, &AtomicBool::new(false), |result| depths.push(result.depth)).unwrap();
+    assert_eq!(depths, vec![4]);
+    assert!(resumed_result.nodes > result.nodes);
+}
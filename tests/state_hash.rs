@@ -1,4 +1,4 @@
-use onitama::game::Board;
+use onitama::game::{Board, GameMove, GameStatus};
 
 #[test]
 fn hash_default() {
@@ -25,3 +25,34 @@ fn hash_same_state() {
     let hash_2 = board.state_hash();
     assert_eq!(hash_1, hash_2);
 }
+#[test]
+fn loaded_start_counts_for_repetition() {
+    // Returns the moves of a line that comes back to the start position, if there is one within `plies`
+    fn find_cycle(board: &mut Board, start_hash: u64, plies: usize) -> Option<Vec<GameMove>> {
+        if board.game_length() > 0 && board.state_hash() == start_hash {
+            return Some(vec![]);
+        }
+        if plies == 0 || board.finished() {
+            return None;
+        }
+        for game_move in board.legal_moves() {
+            board.make_move_unchecked(game_move.clone());
+            let cycle = find_cycle(board, start_hash, plies - 1);
+            board.undo_move();
+            if let Some(mut cycle) = cycle {
+                cycle.insert(0, game_move);
+                return Some(cycle);
+            }
+        }
+        None
+    }
+    let start = Board::load_game(".......3.........2.......BXLUT").unwrap();
+    let cycle = find_cycle(&mut start.clone(), start.state_hash(), 6).expect("no line returns to the start");
+    let mut board = start.clone();
+    for game_move in cycle {
+        assert!(!board.finished());
+        board.make_move_unchecked(game_move);
+    }
+    assert!(board.is_same_board(&start));
+    assert_eq!(board.status(), GameStatus::Stalemate);
+}
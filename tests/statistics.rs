@@ -0,0 +1,57 @@
+use onitama::ai::statistics::{Score, Sprt, SprtResult, elo_from_score, fit_ratings, score_from_elo};
+
+#[test]
+fn elo_matches_score() {
+    assert_eq!(Score::new(10, 10, 5).elo().difference, 0.0);
+    // A 75% score is the classic 191 Elo
+    let elo = Score::new(70, 20, 10).elo();
+    assert!((elo.difference - 190.8).abs() < 0.1);
+    assert!(elo.error > 0.0 && elo.error < elo.difference);
+    for elo in [-300.0, -25.0, 0.0, 80.0] {
+        assert!((elo_from_score(score_from_elo(elo)) - elo).abs() < 1e-9);
+    }
+    // More games, smaller error bars
+    assert!(Score::new(700, 200, 100).elo().error < elo.error);
+    assert!(Score::new(5, 0, 0).elo().difference.is_infinite());
+}
+#[test]
+fn likelihood_of_superiority() {
+    assert_eq!(Score::new(0, 0, 10).los(), 0.5);
+    assert!((Score::new(10, 10, 0).los() - 0.5).abs() < 1e-6);
+    assert!(Score::new(60, 40, 0).los() > 0.95);
+    let score = Score::new(45, 55, 20);
+    assert!((score.los() + score.reversed().los() - 1.0).abs() < 1e-6);
+}
+#[test]
+fn sprt_decides() {
+    let sprt = Sprt::new(0.0, 10.0);
+    let (lower, upper) = sprt.bounds();
+    assert!((lower + 2.944).abs() < 0.001 && (upper - 2.944).abs() < 0.001);
+    assert_eq!(sprt.test(&Score::new(6, 4, 2)), SprtResult::Continue);
+    assert_eq!(sprt.test(&Score::new(600, 400, 200)), SprtResult::AcceptH1);
+    assert_eq!(sprt.test(&Score::new(400, 600, 200)), SprtResult::AcceptH0);
+    // Stricter error rates need more evidence
+    let strict = sprt.with_error_rates(0.001, 0.001);
+    let score = Score::new(260, 140, 40);
+    assert!(sprt.test(&score) != SprtResult::Continue);
+    assert_eq!(strict.test(&score), SprtResult::Continue);
+}
+#[test]
+fn fitted_ratings_match_scores() {
+    // Two players: the fit should land close to the head to head Elo, pulled in slightly by the virtual draw
+    let score = Score::new(60, 30, 10);
+    let ratings = fit_ratings(&[vec![Score::default(), score], vec![score.reversed(), Score::default()]]);
+    let difference = ratings[0] - ratings[1];
+    assert!(difference > 0.0 && difference < score.elo().difference);
+    assert!((difference - score.elo().difference).abs() < 5.0);
+    // Winning every game still gives a finite rating, and players without games are rated zero
+    let perfect = Score::new(10, 0, 0);
+    let ratings = fit_ratings(&[
+        vec![Score::default(), perfect, Score::default()],
+        vec![perfect.reversed(), Score::default(), Score::default()],
+        vec![Score::default(); 3],
+    ]);
+    assert!(ratings[0].is_finite() && ratings[0] > 0.0);
+    assert_eq!(ratings[0], -ratings[1]);
+    assert_eq!(ratings[2], 0.0);
+}
@@ -0,0 +1,143 @@
+use std::sync::{Arc, atomic::AtomicBool};
+
+use onitama::{
+    ai::{
+        AIOpponent, AIVersion, BotSettings, MinMaxV4,
+        difficulty::Difficulty,
+        tablebase::{Tablebase, TablebaseValue},
+    },
+    game::{Board, GameStatus},
+};
+
+const CARDS: &str = "BCQKD";
+
+/// Every position with only the two senseis and red to move
+fn sensei_positions() -> Vec<Board> {
+    sensei_positions_with(CARDS)
+}
+
+fn sensei_positions_with(cards: &str) -> Vec<Board> {
+    let mut boards = vec![];
+    for red in 0..25 {
+        for blue in 0..25 {
+            if red == blue || red == 2 || blue == 22 {
+                continue;
+            }
+            let mut squares = ['.'; 25];
+            squares[red] = '2';
+            squares[blue] = '3';
+            let text: String = squares.iter().collect::<String>() + cards;
+            boards.push(Board::load_game(&text).unwrap());
+        }
+    }
+    boards
+}
+
+fn generate() -> Tablebase {
+    let cards = Board::load_game(CARDS).unwrap().cards();
+    Tablebase::generate(cards, 2, |_, _, _, _| ())
+}
+
+#[test]
+fn win_in_one_iff_immediate_win() {
+    let tablebase = generate();
+    for board in sensei_positions() {
+        let immediate_win = board.legal_moves().into_iter().any(|game_move| {
+            let mut next = board.clone();
+            next.make_move_unchecked(game_move);
+            next.status() == GameStatus::RedWon
+        });
+        assert_eq!(tablebase.probe(&board) == Some(TablebaseValue::Win(1)), immediate_win);
+    }
+}
+#[test]
+fn distances_are_consistent() {
+    let tablebase = generate();
+    let mut longest_win = 0;
+    for board in sensei_positions() {
+        let children: Vec<Option<TablebaseValue>> = board
+            .legal_moves()
+            .into_iter()
+            .filter_map(|game_move| {
+                let mut next = board.clone();
+                next.make_move_unchecked(game_move);
+                (!next.finished()).then(|| tablebase.probe(&next))
+            })
+            .collect();
+        match tablebase.probe(&board).unwrap() {
+            TablebaseValue::Win(1) => (),
+            TablebaseValue::Win(distance) => {
+                longest_win = longest_win.max(distance);
+                assert!(children.contains(&Some(TablebaseValue::Loss(distance - 1))));
+            }
+            TablebaseValue::Loss(distance) => {
+                assert!(children.iter().all(|child| matches!(child, Some(TablebaseValue::Win(d)) if *d < distance)));
+                assert!(children.contains(&Some(TablebaseValue::Win(distance - 1))));
+            }
+            TablebaseValue::Draw => {
+                assert!(!children.iter().any(|child| matches!(child, Some(TablebaseValue::Loss(_)))));
+            }
+        }
+    }
+    assert!(longest_win > 1);
+}
+#[test]
+fn search_plays_shortest_win() {
+    let tablebase = Arc::new(generate());
+    let ai = MinMaxV4::new(4).with_tablebase(tablebase.clone());
+    for board in sensei_positions().into_iter().step_by(7) {
+        if let Some(TablebaseValue::Win(distance)) = tablebase.probe(&board) {
+            let game_move = ai.search(Arc::new(AtomicBool::new(false)), board.clone(), None);
+            let mut next = board.clone();
+            next.make_move_unchecked(game_move);
+            if distance == 1 {
+                assert_eq!(next.status(), GameStatus::RedWon);
+            } else {
+                assert_eq!(tablebase.probe(&next), Some(TablebaseValue::Loss(distance - 1)));
+            }
+        }
+    }
+}
+#[test]
+fn settings_use_tablebase() {
+    let tablebase = Arc::new(generate());
+    let settings = BotSettings::default().with_tablebase(tablebase.clone());
+    for version in [AIVersion::MinMaxV4, AIVersion::Level(Difficulty::Expert)] {
+        let ai = version.make_opponent(&settings);
+        for board in sensei_positions().into_iter().step_by(13) {
+            if let Some(TablebaseValue::Win(distance)) = tablebase.probe(&board)
+                && distance > 1
+            {
+                let game_move = ai.search(Arc::new(AtomicBool::new(false)), board.clone(), None);
+                let mut next = board.clone();
+                next.make_move_unchecked(game_move);
+                assert_eq!(tablebase.probe(&next), Some(TablebaseValue::Loss(distance - 1)), "{version}");
+            }
+        }
+    }
+}
+#[test]
+fn other_cards_not_probed() {
+    let tablebase = generate();
+    // Only the transfer card differs from the tablebase's cards
+    for (board, other_transfer) in sensei_positions().iter().zip(sensei_positions_with("BCQKX")) {
+        if !board.finished() {
+            assert!(tablebase.probe(board).is_some());
+        }
+        assert_eq!(tablebase.probe(&other_transfer), None);
+    }
+    // Too many pieces
+    assert_eq!(tablebase.probe(&Board::load_game(CARDS).unwrap()), None);
+}
+#[test]
+fn file_round_trip() {
+    let tablebase = generate();
+    let path = std::env::temp_dir().join("onitama_tablebase_test.bin");
+    tablebase.save(&path).unwrap();
+    let loaded = Tablebase::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.max_pieces(), 2);
+    for board in sensei_positions() {
+        assert_eq!(loaded.probe(&board), tablebase.probe(&board));
+    }
+}
@@ -0,0 +1,185 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use onitama::{
+    ai::{
+        AIOpponent, AIVersion, BotSettings, DeterministicSettings, Fault,
+        tournament::{
+            Crosstable, GameResult, MoveLimit, Pairing, Player, TableFormat, pairings_both_colours,
+            OWN_LIMITS_TIMEOUT, STOP_GRACE, pairings_gauntlet, pairings_round_robin, play_game, random_start, run_games,
+        },
+    },
+    game::{Board, GameMove, GameStatus},
+};
+use tinyrand::{Seeded, StdRand};
+
+const SETTINGS: DeterministicSettings = DeterministicSettings {
+    depth: 2,
+    node_limit: 500,
+    seed: 0,
+};
+
+fn players() -> Vec<Player> {
+    vec![
+        Player::builtin(AIVersion::MinMaxV4, BotSettings::default(), Some(SETTINGS)),
+        Player::builtin(AIVersion::Random, BotSettings::default(), Some(SETTINGS)),
+    ]
+}
+
+#[test]
+fn random_starts_are_playable() {
+    let mut rng = StdRand::seed(1);
+    for _ in 0..20 {
+        let board = random_start(&mut rng, 10);
+        assert!(!board.finished());
+        assert!(!board.legal_moves().is_empty());
+    }
+}
+#[test]
+fn every_pairing_played_once() {
+    let mut rng = StdRand::seed(2);
+    let starts: Vec<_> = (0..3).map(|_| random_start(&mut rng, 6)).collect();
+    let pairings = pairings_both_colours(starts.len(), 0, 1);
+    let mut played = vec![];
+    run_games(&players(), &starts, &pairings, MoveLimit::OwnLimits(OWN_LIMITS_TIMEOUT), 100, 2, |result| {
+        let plies = result.game.game_length() - starts[result.pairing.start_index].game_length();
+        assert!(plies > 0);
+        assert_eq!(result.move_times.len(), plies);
+        if result.game.status() != GameStatus::Playing {
+            assert!(result.game.finished());
+        }
+        played.push(result.pairing);
+        true
+    });
+    played.sort_by_key(|pairing| (pairing.start_index, pairing.red));
+    assert_eq!(played, pairings);
+}
+#[test]
+fn stops_when_asked() {
+    let mut rng = StdRand::seed(3);
+    let starts: Vec<_> = (0..10).map(|_| random_start(&mut rng, 6)).collect();
+    let pairings = pairings_both_colours(starts.len(), 0, 1);
+    let mut num_results = 0;
+    run_games(&players(), &starts, &pairings, MoveLimit::OwnLimits(OWN_LIMITS_TIMEOUT), 100, 1, |_| {
+        num_results += 1;
+        false
+    });
+    assert_eq!(num_results, 1);
+}
+#[test]
+fn round_robin_and_gauntlet_pairings() {
+    let round_robin = pairings_round_robin(4, 3);
+    // 6 pairs, both colours, 3 starts
+    assert_eq!(round_robin.len(), 36);
+    for a in 0..4 {
+        for b in (0..4).filter(|&b| b != a) {
+            assert_eq!(round_robin.iter().filter(|pairing| pairing.red == a && pairing.blue == b).count(), 3);
+        }
+    }
+    let gauntlet = pairings_gauntlet(4, 3);
+    assert_eq!(gauntlet.len(), 18);
+    assert!(gauntlet.iter().all(|pairing| pairing.red == 0 || pairing.blue == 0));
+    // Two players are the same either way
+    assert_eq!(pairings_round_robin(2, 5), pairings_both_colours(5, 0, 1));
+}
+#[test]
+fn crosstable_ranks_players() {
+    let players: Vec<Player> = ["Strong", "Middle", "Weak"]
+        .into_iter()
+        .map(|name| Player::builtin(AIVersion::Dummy, BotSettings::default(), None).with_name(name))
+        .collect();
+    let mut crosstable = Crosstable::new(&players);
+    // Every game counts as a win for red
+    let red_win = Board::load_game("11.11..3.........2..00.00BXLUTBrsLhlTvl").unwrap();
+    assert_eq!(red_win.status(), GameStatus::RedWon);
+    let mut add = |red, blue, times| {
+        for _ in 0..times {
+            crosstable.add(&GameResult {
+                pairing: Pairing { start_index: 0, red, blue },
+                game: red_win.clone(),
+                move_times: vec![],
+                forfeit: None,
+            });
+        }
+    };
+    add(0, 1, 3);
+    add(1, 0, 1);
+    add(1, 2, 3);
+    add(2, 1, 1);
+    add(0, 2, 4);
+    assert_eq!(crosstable.score(0, 1).wins, 3);
+    assert_eq!(crosstable.score(1, 0).wins, 1);
+    assert_eq!(crosstable.total(2).games(), 8);
+
+    let ratings = crosstable.ratings();
+    assert!(ratings[0] > ratings[1] && ratings[1] > ratings[2]);
+    assert!(ratings.iter().sum::<f64>().abs() < 1e-6);
+    let markdown = crosstable.ranking_table(TableFormat::Markdown);
+    let lines: Vec<&str> = markdown.lines().collect();
+    assert!(lines[2].starts_with("| 1 | Strong |"));
+    assert!(lines[4].starts_with("| 3 | Weak |"));
+    let csv = crosstable.results_table(TableFormat::Csv);
+    assert_eq!(csv.lines().next(), Some("Player,Strong,Middle,Weak"));
+    assert_eq!(csv.lines().nth(1), Some("Strong,-,3/4,4/4"));
+}
+
+/// Panics, plays an illegal move or hangs on its first move, depending on the mode
+struct Faulty(u8);
+impl AIOpponent for Faulty {
+    fn search(&self, cancel_signal: Arc<AtomicBool>, board: Board, _remaining_time: Option<Duration>) -> GameMove {
+        match self.0 {
+            0 => panic!("faulty bot"),
+            1 => {
+                // A move with the card held by the opponent
+                let mut opponent_board = board.clone();
+                opponent_board.make_move_unchecked(board.legal_moves()[0].clone());
+                opponent_board.legal_moves()[0].clone()
+            }
+            _ => {
+                std::thread::sleep(STOP_GRACE * 3);
+                cancel_signal.store(true, Ordering::Relaxed);
+                board.legal_moves()[0].clone()
+            }
+        }
+    }
+}
+
+#[test]
+fn faults_forfeit_the_game() {
+    let random = Player::builtin(AIVersion::Random, BotSettings::default(), Some(SETTINGS));
+    let start = Board::load_game("BCQKD").unwrap();
+    for (mode, limit) in [
+        (0, MoveLimit::OwnLimits(OWN_LIMITS_TIMEOUT)),
+        (1, MoveLimit::OwnLimits(OWN_LIMITS_TIMEOUT)),
+        (2, MoveLimit::Time(Duration::from_millis(10))),
+        (2, MoveLimit::OwnLimits(Duration::from_millis(10))),
+    ] {
+        let faulty = Player::new("Faulty", move || Arc::new(Faulty(mode)));
+        let (game, move_times, forfeit) = play_game(&faulty, &random, start.clone(), limit, 100);
+        let forfeit = forfeit.unwrap();
+        assert!(forfeit.red);
+        assert_eq!(forfeit.status(), GameStatus::BlueWon);
+        assert_eq!(game.game_length(), 0);
+        assert!(move_times.is_empty());
+        match mode {
+            0 => assert_eq!(forfeit.fault, Fault::Crash("faulty bot".to_string())),
+            1 => assert!(matches!(forfeit.fault, Fault::IllegalMove(_))),
+            _ => assert_eq!(forfeit.fault, Fault::Timeout),
+        }
+    }
+    // The rest of the games are still played
+    let players = vec![Player::new("Faulty", || Arc::new(Faulty(0))), random];
+    let mut results = vec![];
+    let limit = MoveLimit::OwnLimits(OWN_LIMITS_TIMEOUT);
+    run_games(&players, &[start], &pairings_both_colours(1, 0, 1), limit, 100, 1, |result| {
+        results.push(result.clone());
+        true
+    });
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|result| result.winner() == Some(1) && result.forfeit.is_some()));
+}
@@ -0,0 +1,49 @@
+use onitama::{
+    ai::{
+        evaluation::{EvalTerm, Evaluator},
+        tuning::{Tuner, TuningPosition, extract_positions},
+    },
+    game::Board,
+};
+use tinyrand::{RandRange, Seeded, StdRand};
+
+fn random_game_positions(num_games: usize) -> Vec<TuningPosition> {
+    let mut rng = StdRand::seed(42);
+    let mut positions = vec![];
+    for _ in 0..num_games {
+        let mut board = Board::default();
+        while !board.finished() {
+            let legal_moves = board.legal_moves();
+            let game_move = legal_moves[rng.next_range(0..legal_moves.len())].clone();
+            board.make_move_unchecked(game_move);
+        }
+        positions.extend(extract_positions(&board, 0));
+    }
+    positions
+}
+
+#[test]
+fn tuning_reduces_error() {
+    let mut tuner = Tuner::new(random_game_positions(20), Evaluator::default(), 1);
+    let initial_error = tuner.error();
+    tuner.tune(5, |_, _, _| ());
+    assert!(tuner.error() < initial_error, "{} >= {initial_error}", tuner.error());
+}
+#[test]
+fn tuning_corrects_wrong_weights() {
+    // The side with more pieces usually wins, even in random games, so a negative material weight is wrong
+    let start = Evaluator::classic().with_weight(EvalTerm::Material, -100);
+    let mut tuner = Tuner::new(random_game_positions(20), start, 1);
+    let initial_error = tuner.error();
+    tuner.tune(5, |_, _, _| ());
+    assert!(tuner.error() < initial_error);
+    assert!(tuner.evaluator().weight(EvalTerm::Material) > -100);
+}
+#[test]
+fn tuning_is_reproducible() {
+    let mut tuner_a = Tuner::new(random_game_positions(10), Evaluator::default(), 7);
+    let mut tuner_b = Tuner::new(random_game_positions(10), Evaluator::default(), 7);
+    tuner_a.tune(3, |_, _, _| ());
+    tuner_b.tune(3, |_, _, _| ());
+    assert_eq!(tuner_a.evaluator(), tuner_b.evaluator());
+}